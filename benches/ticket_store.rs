@@ -0,0 +1,173 @@
+#![allow(unused)]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use my_first_axum::model::store::{MemTicketStore, TicketStore};
+use my_first_axum::model::{
+    Ticket, TicketForCreate, TicketForUpdate, TicketHistoryAction, TicketHistoryForInsert,
+};
+
+// 直接測試記憶體儲存層（RwLock）在多執行緒讀寫混合負載下的吞吐量，並與原本的 Mutex<Vec<Ticket>> 比較
+// 不經過 HTTP，結果只反映儲存層本身的 lock 與複製成本，以 cargo bench 執行（不使用 libtest 的 bench harness）：
+// cargo bench --bench ticket_store
+// 讀取為列出所有的 ticket，寫入為修改既有 ticket 的標題，ticket 的數量固定為 PRELOAD
+const PRELOAD: usize = 1000;
+const THREADS: usize = 8;
+const DURATION: Duration = Duration::from_secs(2);
+
+// cargo bench 會在參數中帶上 --bench，這裡不需要處理任何參數
+fn main() -> Result<()> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(THREADS)
+        .build()?
+        .block_on(ticket_store_mixed_load())
+}
+
+async fn ticket_store_mixed_load() -> Result<()> {
+    println!(
+        "{:<16} {:>8} {:>8} {:>12} {:>12}",
+        "store", "readers", "writers", "reads/s", "writes/s"
+    );
+    for writers in [0, 1, 2, 4] {
+        let readers = THREADS - writers;
+        let rwlock = Arc::new(MemTicketStore::default());
+        for i in 0..PRELOAD {
+            rwlock
+                .create(
+                    1,
+                    ticket_fc(format!("preload {i}")),
+                    history_fi(TicketHistoryAction::Create),
+                )
+                .await?;
+        }
+        // 以同樣的資料作為 Mutex<Vec<Ticket>> 的初始內容
+        let mutex_vec = Arc::new(MutexVecStore {
+            tickets: Mutex::new(TicketStore::list(&*rwlock).await?),
+        });
+        let stores: [(&str, Arc<dyn BenchStore>); 2] =
+            [("RwLock", rwlock), ("Mutex<Vec<_>>", mutex_vec)];
+        for (name, store) in stores {
+            let (reads, writes) = run_mixed_load(store, readers, writers).await?;
+            let secs = DURATION.as_secs_f64();
+            println!(
+                "{name:<16} {readers:>8} {writers:>8} {:>12.0} {:>12.0}",
+                reads as f64 / secs,
+                writes as f64 / secs
+            );
+        }
+    }
+    Ok(())
+}
+
+// 每個 reader 與 writer 各自在一個 worker thread 上不斷執行，直到時間結束
+// 儲存層的操作不會讓出執行權，所以 reader 與 writer 的總數不能超過 worker thread 的數量
+async fn run_mixed_load(
+    store: Arc<dyn BenchStore>,
+    readers: usize,
+    writers: usize,
+) -> Result<(u64, u64)> {
+    let reads = Arc::new(AtomicU64::new(0));
+    let writes = Arc::new(AtomicU64::new(0));
+    let deadline = Instant::now() + DURATION;
+    let mut tasks = Vec::new();
+    for _ in 0..readers {
+        let (store, reads) = (store.clone(), reads.clone());
+        tasks.push(tokio::spawn(async move {
+            while Instant::now() < deadline {
+                let tickets = store.list().await?;
+                anyhow::ensure!(tickets.len() == PRELOAD);
+                reads.fetch_add(1, Ordering::Relaxed);
+            }
+            anyhow::Ok(())
+        }));
+    }
+    for writer in 0..writers as u64 {
+        let (store, writes) = (store.clone(), writes.clone());
+        tasks.push(tokio::spawn(async move {
+            let mut n = writer;
+            while Instant::now() < deadline {
+                n += 1;
+                let id = n % PRELOAD as u64 + 1;
+                store.update_title(id, format!("bench {n}")).await?;
+                writes.fetch_add(1, Ordering::Relaxed);
+            }
+            anyhow::Ok(())
+        }));
+    }
+    for task in tasks {
+        task.await??;
+    }
+    Ok((
+        reads.load(Ordering::Relaxed),
+        writes.load(Ordering::Relaxed),
+    ))
+}
+
+// 比較用的共同介面，只包含負載中用到的操作
+#[async_trait]
+trait BenchStore: Send + Sync {
+    async fn list(&self) -> Result<Vec<Ticket>>;
+    async fn update_title(&self, id: u64, title: String) -> Result<()>;
+}
+
+#[async_trait]
+impl BenchStore for MemTicketStore {
+    async fn list(&self) -> Result<Vec<Ticket>> {
+        Ok(TicketStore::list(self).await?)
+    }
+
+    async fn update_title(&self, id: u64, title: String) -> Result<()> {
+        let ticket_fu = TicketForUpdate {
+            title: Some(title),
+            ..Default::default()
+        };
+        let history_fi = history_fi(TicketHistoryAction::Update);
+        TicketStore::update(self, id, 1, None, ticket_fu, history_fi).await?;
+        Ok(())
+    }
+}
+
+// 改用 RwLock 之前的做法：讀寫共用同一個 Mutex，列出時在持有 lock 的期間複製整個 Vec
+struct MutexVecStore {
+    tickets: Mutex<Vec<Ticket>>,
+}
+
+#[async_trait]
+impl BenchStore for MutexVecStore {
+    async fn list(&self) -> Result<Vec<Ticket>> {
+        let store = self.tickets.lock().unwrap();
+        Ok(store.iter().filter(|t| !t.is_deleted()).cloned().collect())
+    }
+
+    async fn update_title(&self, id: u64, title: String) -> Result<()> {
+        let mut store = self.tickets.lock().unwrap();
+        let ticket = store
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| anyhow::anyhow!("ticket {id} not found"))?;
+        ticket.title = title;
+        ticket.version += 1;
+        Ok(())
+    }
+}
+
+fn ticket_fc(title: String) -> TicketForCreate {
+    TicketForCreate {
+        title,
+        description: String::new(),
+        priority: Default::default(),
+        labels: Default::default(),
+    }
+}
+
+// 負載中不比較異動紀錄的成本，修改時沒有欄位的變化，儲存層不會寫入紀錄
+fn history_fi(action: TicketHistoryAction) -> TicketHistoryForInsert {
+    TicketHistoryForInsert {
+        actor_id: Some(1),
+        action,
+        changes: vec![],
+    }
+}
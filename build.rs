@@ -0,0 +1,4 @@
+// sqlx::migrate! 會在編譯時嵌入 migrations，新增 migration 檔案時需要重新編譯
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
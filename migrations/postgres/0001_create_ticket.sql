@@ -0,0 +1,6 @@
+-- BIGSERIAL 使用 sequence 分配 id，被刪除的 id 不會被重新使用
+CREATE TABLE ticket (
+    id    BIGSERIAL PRIMARY KEY,
+    cid   BIGINT NOT NULL,
+    title TEXT   NOT NULL
+);
@@ -0,0 +1,3 @@
+-- 最後一次修改的時間（Unix 秒）與修改者，從未修改過的 ticket 為 NULL
+ALTER TABLE ticket ADD COLUMN updated_at BIGINT;
+ALTER TABLE ticket ADD COLUMN updated_by BIGINT;
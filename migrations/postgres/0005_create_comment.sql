@@ -0,0 +1,10 @@
+-- ticket 底下的留言，ticket 被刪除時留言也會一起被刪除
+CREATE TABLE comment (
+    id         BIGSERIAL PRIMARY KEY,
+    ticket_id  BIGINT NOT NULL REFERENCES ticket(id) ON DELETE CASCADE,
+    author_id  BIGINT NOT NULL,
+    body       TEXT   NOT NULL,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT
+);
+CREATE INDEX comment_ticket_id ON comment (ticket_id);
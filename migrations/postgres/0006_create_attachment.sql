@@ -0,0 +1,11 @@
+-- 附件的資訊，內容存放在 BlobStore，ticket 被刪除時附件資訊也會一起被刪除
+CREATE TABLE attachment (
+    id           BIGSERIAL PRIMARY KEY,
+    ticket_id    BIGINT NOT NULL REFERENCES ticket(id) ON DELETE CASCADE,
+    uploader_id  BIGINT NOT NULL,
+    filename     TEXT   NOT NULL,
+    content_type TEXT   NOT NULL,
+    size         BIGINT NOT NULL,
+    created_at   BIGINT NOT NULL
+);
+CREATE INDEX attachment_ticket_id ON attachment (ticket_id);
@@ -0,0 +1,5 @@
+-- 移到垃圾桶的時間（Unix 秒）與刪除者，不在垃圾桶中的 ticket 為 NULL
+ALTER TABLE ticket ADD COLUMN deleted_at BIGINT;
+ALTER TABLE ticket ADD COLUMN deleted_by BIGINT;
+-- 自動永久刪除時依 deleted_at 查詢
+CREATE INDEX ticket_deleted_at ON ticket (deleted_at);
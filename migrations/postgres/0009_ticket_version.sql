@@ -0,0 +1,2 @@
+-- 每次修改都會遞增的版本，給 ETag 與 If-Match 使用，既有的資料從 1 開始
+ALTER TABLE ticket ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
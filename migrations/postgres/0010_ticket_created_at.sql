@@ -0,0 +1,9 @@
+-- 建立的時間（Unix 秒），給列表的篩選與排序使用
+-- 既有的資料從異動紀錄中找出建立的時間，找不到時為 0
+ALTER TABLE ticket ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+UPDATE ticket SET created_at = COALESCE(
+    (SELECT MIN(h.created_at) FROM ticket_history h
+     WHERE h.ticket_id = ticket.id AND h.action = 'Create'),
+    0
+);
+CREATE INDEX ticket_created_at ON ticket (created_at);
@@ -0,0 +1,26 @@
+-- 使用者、群組與邀請，與 ticket 存放在同一個資料庫，ticket 與 cookie 中的 user id 重新啟動後仍然對應到同一個人
+-- user 是 PostgreSQL 的保留字，所以使用 app_user；role、auth_source、status 以文字儲存；群組的成員以 user id 的 JSON 陣列文字儲存
+CREATE TABLE app_user (
+    id          BIGSERIAL PRIMARY KEY,
+    username    TEXT    NOT NULL UNIQUE,
+    email       TEXT    NOT NULL,
+    role        TEXT    NOT NULL,
+    active      BOOLEAN NOT NULL,
+    auth_source TEXT    NOT NULL,
+    pwd_hash    TEXT    NOT NULL
+);
+CREATE TABLE user_group (
+    id           BIGSERIAL PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    members      TEXT NOT NULL
+);
+CREATE TABLE invitation (
+    id          BIGSERIAL PRIMARY KEY,
+    cid         BIGINT NOT NULL,
+    email       TEXT   NOT NULL,
+    role        TEXT   NOT NULL,
+    status      TEXT   NOT NULL,
+    expires_at  BIGINT NOT NULL,
+    accepted_by BIGINT,
+    nonce       TEXT   NOT NULL
+);
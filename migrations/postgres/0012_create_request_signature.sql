@@ -0,0 +1,7 @@
+-- 簽章請求已經使用過的簽章，多個服務實例共用，重送到任何一個實例都會被拒絕
+-- 超出時間範圍的紀錄在寫入新的簽章時清除
+CREATE TABLE request_signature (
+    signature TEXT    PRIMARY KEY,
+    seen_at   BIGINT  NOT NULL
+);
+CREATE INDEX request_signature_seen_at ON request_signature (seen_at);
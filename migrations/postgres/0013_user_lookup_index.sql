@@ -0,0 +1,4 @@
+-- SCIM 以 userName、email（不分大小寫）與群組的 displayName 查詢，不需要掃描整個資料表
+CREATE INDEX app_user_username_lower ON app_user (lower(username));
+CREATE INDEX app_user_email_lower ON app_user (lower(email));
+CREATE INDEX user_group_display_name ON user_group (display_name);
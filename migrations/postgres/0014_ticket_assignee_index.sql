@@ -0,0 +1,2 @@
+-- /api/tickets/assigned 以指派對象查詢，不需要掃描整個資料表
+CREATE INDEX ticket_assignee ON ticket (assignee_type, assignee_id);
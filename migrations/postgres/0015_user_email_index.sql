@@ -0,0 +1,2 @@
+-- 接受邀請時以 email 查詢使用者；username 已經有 UNIQUE 的索引，email 可能重複所以不是 UNIQUE
+CREATE INDEX app_user_email ON app_user (email);
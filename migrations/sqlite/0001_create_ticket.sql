@@ -0,0 +1,6 @@
+-- AUTOINCREMENT 確保被刪除的 id 不會被重新使用
+CREATE TABLE ticket (
+    id    INTEGER PRIMARY KEY AUTOINCREMENT,
+    cid   INTEGER NOT NULL,
+    title TEXT    NOT NULL
+);
@@ -0,0 +1,5 @@
+-- 狀態與優先度以文字儲存（例如 'InProgress'、'High'），labels 以 JSON 陣列的文字儲存
+ALTER TABLE ticket ADD COLUMN description TEXT NOT NULL DEFAULT '';
+ALTER TABLE ticket ADD COLUMN status      TEXT NOT NULL DEFAULT 'Open';
+ALTER TABLE ticket ADD COLUMN priority    TEXT NOT NULL DEFAULT 'Normal';
+ALTER TABLE ticket ADD COLUMN labels      TEXT NOT NULL DEFAULT '[]';
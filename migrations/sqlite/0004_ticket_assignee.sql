@@ -0,0 +1,3 @@
+-- 指派對象，assignee_type 為 'User' 或 'Group'，未指派時兩個欄位都是 NULL
+ALTER TABLE ticket ADD COLUMN assignee_type TEXT;
+ALTER TABLE ticket ADD COLUMN assignee_id   INTEGER;
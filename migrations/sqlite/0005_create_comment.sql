@@ -0,0 +1,10 @@
+-- ticket 底下的留言，ticket 被刪除時留言也會一起被刪除（需要開啟 foreign_keys）
+CREATE TABLE comment (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id  INTEGER NOT NULL REFERENCES ticket(id) ON DELETE CASCADE,
+    author_id  INTEGER NOT NULL,
+    body       TEXT    NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER
+);
+CREATE INDEX comment_ticket_id ON comment (ticket_id);
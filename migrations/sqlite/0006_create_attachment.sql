@@ -0,0 +1,11 @@
+-- 附件的資訊，內容存放在 BlobStore，ticket 被刪除時附件資訊也會一起被刪除
+CREATE TABLE attachment (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id    INTEGER NOT NULL REFERENCES ticket(id) ON DELETE CASCADE,
+    uploader_id  INTEGER NOT NULL,
+    filename     TEXT    NOT NULL,
+    content_type TEXT    NOT NULL,
+    size         INTEGER NOT NULL,
+    created_at   INTEGER NOT NULL
+);
+CREATE INDEX attachment_ticket_id ON attachment (ticket_id);
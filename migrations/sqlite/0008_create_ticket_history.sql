@@ -0,0 +1,11 @@
+-- ticket 的異動紀錄，只會新增，不會修改或刪除
+-- 不設定 foreign key，ticket 被永久刪除後紀錄仍然保留；changes 以 JSON 陣列的文字儲存
+CREATE TABLE ticket_history (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_id  INTEGER NOT NULL,
+    actor_id   INTEGER,
+    action     TEXT    NOT NULL,
+    changes    TEXT    NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX ticket_history_ticket_id ON ticket_history (ticket_id);
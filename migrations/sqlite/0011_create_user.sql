@@ -0,0 +1,26 @@
+-- 使用者、群組與邀請，與 ticket 存放在同一個資料庫，ticket 與 cookie 中的 user id 重新啟動後仍然對應到同一個人
+-- role、auth_source、status 以文字儲存；群組的成員以 user id 的 JSON 陣列文字儲存
+CREATE TABLE app_user (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    username    TEXT    NOT NULL UNIQUE,
+    email       TEXT    NOT NULL,
+    role        TEXT    NOT NULL,
+    active      BOOLEAN NOT NULL,
+    auth_source TEXT    NOT NULL,
+    pwd_hash    TEXT    NOT NULL
+);
+CREATE TABLE user_group (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    display_name TEXT    NOT NULL,
+    members      TEXT    NOT NULL
+);
+CREATE TABLE invitation (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    cid         INTEGER NOT NULL,
+    email       TEXT    NOT NULL,
+    role        TEXT    NOT NULL,
+    status      TEXT    NOT NULL,
+    expires_at  INTEGER NOT NULL,
+    accepted_by INTEGER,
+    nonce       TEXT    NOT NULL
+);
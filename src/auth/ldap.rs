@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ldap3::{dn_escape, ldap_escape, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use uuid::Uuid;
+
+use super::Authenticator;
+use crate::config::LdapConfig;
+use crate::model::{AuthSource, ModelController, Role, User, UserForCreate, UserForUpdate};
+use crate::{Error, Result};
+
+// LDAP 回傳 Invalid credentials 時的 result code
+const RC_INVALID_CREDENTIALS: u32 = 49;
+
+// 使用 LDAP simple bind 驗證帳密，成功後依照使用者所屬的群組決定角色
+// 第一次登入的使用者會自動建立本地帳號，之後每次登入都會同步角色
+pub struct LdapAuthenticator {
+    config: LdapConfig,
+}
+
+impl LdapAuthenticator {
+    pub fn new(config: LdapConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Authenticator for LdapAuthenticator {
+    async fn authenticate(&self, mc: &ModelController, username: &str, pwd: &str) -> Result<User> {
+        // 空密碼在 LDAP 中會被視為 unauthenticated bind 而成功，必須先擋下來
+        if username.is_empty() || pwd.is_empty() {
+            return Err(Error::LoginFail);
+        }
+        let (email, role) = self.bind_and_fetch(username, pwd).await?;
+        provision_user(mc, username, email, role).await
+    }
+}
+
+impl LdapAuthenticator {
+    async fn bind_and_fetch(&self, username: &str, pwd: &str) -> Result<(String, Role)> {
+        let dn = bind_dn(&self.config.bind_dn_template, username);
+
+        let settings = LdapConnSettings::new().set_conn_timeout(Duration::from_secs(5));
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.config.url)
+            .await
+            .map_err(ldap_error)?;
+        // 連線需要在背景持續處理，drive! 會將它交給 tokio 執行
+        ldap3::drive!(conn);
+
+        let bind = ldap.simple_bind(&dn, pwd).await.map_err(ldap_error)?;
+        if bind.rc == RC_INVALID_CREDENTIALS {
+            return Err(Error::LoginFail);
+        }
+        bind.success().map_err(ldap_error)?;
+
+        // -- Email
+        let (entries, _res) = ldap
+            .search(&dn, Scope::Base, "(objectClass=*)", vec!["mail"])
+            .await
+            .and_then(|r| r.success())
+            .map_err(ldap_error)?;
+        let email = entries
+            .into_iter()
+            .next()
+            .map(SearchEntry::construct)
+            .and_then(|e| e.attrs.get("mail").and_then(|m| m.first().cloned()))
+            .unwrap_or_default();
+
+        // -- Groups
+        let mut role = Role::Member;
+        if let Some(group_base_dn) = &self.config.group_base_dn {
+            let filter = group_filter(&dn, username);
+            let (entries, _res) = ldap
+                .search(group_base_dn, Scope::Subtree, &filter, vec!["cn"])
+                .await
+                .and_then(|r| r.success())
+                .map_err(ldap_error)?;
+            let groups: Vec<String> = entries
+                .into_iter()
+                .map(SearchEntry::construct)
+                .filter_map(|e| e.attrs.get("cn").and_then(|cn| cn.first().cloned()))
+                .collect();
+            role = role_for_groups(&self.config.group_roles, &groups);
+        }
+
+        let _ = ldap.unbind().await;
+        Ok((email, role))
+    }
+}
+
+// 使用者名稱放進 DN 之前需要跳脫，避免 "," "=" 等字元改變 DN 的結構
+pub fn bind_dn(template: &str, username: &str) -> String {
+    template.replace("{username}", &dn_escape(username))
+}
+
+// 搜尋使用者所屬群組的 filter，DN 與使用者名稱都需要跳脫，避免 "*" "(" 等字元改變 filter 的條件
+pub fn group_filter(dn: &str, username: &str) -> String {
+    format!(
+        "(|(member={dn})(uniqueMember={dn})(memberUid={uid}))",
+        dn = ldap_escape(dn),
+        uid = ldap_escape(username),
+    )
+}
+
+// 使用者屬於多個群組時，取權限最高的角色，群組名稱（cn）不分大小寫
+pub fn role_for_groups(group_roles: &[(String, Role)], groups: &[String]) -> Role {
+    let mapped = group_roles
+        .iter()
+        .filter(|(cn, _)| groups.iter().any(|g| g.eq_ignore_ascii_case(cn)))
+        .map(|(_, role)| *role);
+    let mut role = Role::Member;
+    for r in mapped {
+        if r == Role::Admin {
+            role = Role::Admin;
+        }
+    }
+    role
+}
+
+// 以 LDAP 的資料建立或更新本地帳號，本地停用的帳號即使 LDAP 驗證成功也不能登入
+// 同名的帳號不是由 LDAP 建立的（例如本地或邀請的帳號）時拒絕登入，避免 LDAP 的身份接管本地帳號或改變它的角色
+async fn provision_user(
+    mc: &ModelController,
+    username: &str,
+    email: String,
+    role: Role,
+) -> Result<User> {
+    match mc.first_user_by_username(username).await? {
+        Some(user) if user.auth_source != AuthSource::Ldap => {
+            Err(Error::AuthFailLdapAccountConflict {
+                username: username.to_string(),
+            })
+        }
+        Some(user) if !user.active => Err(Error::LoginFail),
+        Some(user) => {
+            if !email.is_empty() && email != user.email {
+                let user_fu = UserForUpdate {
+                    email: Some(email),
+                    ..Default::default()
+                };
+                mc.update_user(user.id, user_fu).await?;
+            }
+            mc.update_user_role(user.id, role).await
+        }
+        None => {
+            mc.create_user(UserForCreate {
+                username: username.to_string(),
+                email,
+                // 本地密碼只是佔位用，LDAP 使用者無法透過本地帳密登入
+                pwd: Uuid::new_v4().to_string(),
+                role,
+                auth_source: AuthSource::Ldap,
+            })
+            .await
+        }
+    }
+}
+
+fn ldap_error(e: ldap3::LdapError) -> Error {
+    Error::AuthFailLdap {
+        detail: e.to_string(),
+    }
+}
@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+
+use super::Authenticator;
+use crate::model::{ModelController, User};
+use crate::Result;
+
+// 使用 ModelController 中儲存的帳號密碼驗證
+pub struct LocalAuthenticator;
+
+#[async_trait]
+impl Authenticator for LocalAuthenticator {
+    async fn authenticate(&self, mc: &ModelController, username: &str, pwd: &str) -> Result<User> {
+        mc.check_login(username, pwd).await
+    }
+}
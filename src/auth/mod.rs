@@ -0,0 +1,74 @@
+// 登入時的帳密驗證，透過 Authenticator trait 讓驗證的來源可以抽換
+// 目前有本地帳號（local）跟 LDAP 兩種實作，可以透過設定檔依序組合使用
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::config::config;
+use crate::model::{ModelController, User};
+use crate::{Error, Result};
+
+mod ldap;
+mod local;
+
+pub use self::ldap::{bind_dn, group_filter, role_for_groups, LdapAuthenticator};
+pub use self::local::LocalAuthenticator;
+
+// 驗證成功時回傳本地的 User，外部來源（例如 LDAP）的使用者會在第一次登入時建立本地帳號
+// 帳密錯誤時應回傳 Error::LoginFail，讓下一個 Authenticator 有機會嘗試
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    async fn authenticate(&self, mc: &ModelController, username: &str, pwd: &str) -> Result<User>;
+}
+
+// 依序嘗試多個 Authenticator，第一個成功的為準
+pub struct AuthenticatorChain {
+    authenticators: Vec<Box<dyn Authenticator>>,
+}
+
+// 全部失敗時，只要有任何一個 Authenticator 判定帳密錯誤就回傳 LoginFail
+// 例如 LDAP 連不上而本地帳號的密碼錯誤時，仍然是帳密錯誤，而不是服務錯誤
+// 沒有任何一個 Authenticator 能判定時（例如只設定了 LDAP 而 LDAP 連不上），才回傳第一個非帳密錯誤的錯誤
+#[async_trait]
+impl Authenticator for AuthenticatorChain {
+    async fn authenticate(&self, mc: &ModelController, username: &str, pwd: &str) -> Result<User> {
+        let mut login_fail = false;
+        let mut error = None;
+        for authenticator in &self.authenticators {
+            match authenticator.authenticate(mc, username, pwd).await {
+                Ok(user) => return Ok(user),
+                Err(Error::LoginFail) => login_fail = true,
+                Err(e) => {
+                    println!("->> {:<12} - authenticate - {e:?}", "AUTH");
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) if !login_fail => Err(e),
+            _ => Err(Error::LoginFail),
+        }
+    }
+}
+
+// 依照 SERVICE_AUTHENTICATORS（例如 "ldap,local"）建立 Authenticator
+pub fn authenticator_from_config() -> Result<Arc<dyn Authenticator>> {
+    let mut authenticators: Vec<Box<dyn Authenticator>> = Vec::new();
+    for name in &config().authenticators {
+        match name.as_str() {
+            "local" => authenticators.push(Box::new(LocalAuthenticator)),
+            "ldap" => {
+                let ldap_config = config().ldap.clone().ok_or(Error::ConfigMissingEnv {
+                    name: "SERVICE_LDAP_URL",
+                })?;
+                authenticators.push(Box::new(LdapAuthenticator::new(ldap_config)));
+            }
+            _ => {
+                return Err(Error::ConfigInvalidAuthenticator {
+                    authenticator: name.clone(),
+                })
+            }
+        }
+    }
+    Ok(Arc::new(AuthenticatorChain { authenticators }))
+}
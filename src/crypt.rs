@@ -0,0 +1,102 @@
+// 加密相關的共用工具，包含簽章與密碼雜湊
+// 所有需要「防止被竄改」的字串（像是邀請連結的 token）都透過這邊簽章跟驗證
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::config::config;
+use crate::{Error, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 以 token_key 簽章的各種 token，簽章前會在內容前面加上各自的前綴
+// 所有 token 共用同一把金鑰，加上前綴後一種 token 的簽章不能拿來冒充另一種 token
+#[derive(Debug, Clone, Copy)]
+pub enum TokenKind {
+    Auth,
+    Cursor,
+    Invitation,
+    Scim,
+}
+
+impl TokenKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Auth => "auth:",
+            Self::Cursor => "cursor:",
+            Self::Invitation => "inv:",
+            Self::Scim => "scim:",
+        }
+    }
+}
+
+// 使用 HMAC-SHA256 對內容簽章，並以 base64url 編碼回傳，方便直接放在網址或 cookie 中
+pub fn sign(kind: TokenKind, content: &str) -> String {
+    sign_with_key(&config().token_key, &format!("{}{content}", kind.prefix()))
+}
+
+// 驗證簽章是否正確，verify_slice 內部使用 constant time 比對，避免 timing attack
+pub fn verify(kind: TokenKind, content: &str, signature: &str) -> Result<()> {
+    verify_with_key(
+        &config().token_key,
+        &format!("{}{content}", kind.prefix()),
+        signature,
+    )
+}
+
+// 使用指定的金鑰驗證簽章，例如 API client 各自的 shared secret
+pub fn verify_with_key(key: &[u8], content: &str, signature: &str) -> Result<()> {
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| Error::CryptFailSignatureInvalid)?;
+    let mut mac = new_mac(key);
+    mac.update(content.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| Error::CryptFailSignatureInvalid)
+}
+
+// 計算內容的 SHA-256，並以小寫 hex 字串回傳
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// 密碼以 argon2id 雜湊，回傳 PHC 格式的字串（$argon2id$...），salt 與參數都包含在字串中
+// argon2 刻意設計成計算緩慢且需要大量記憶體，讓離線暴力破解的成本提高
+pub fn hash_pwd(pwd: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(pwd.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::CryptFailPwdHash {
+            detail: e.to_string(),
+        })
+}
+
+// 以 argon2 驗證密碼，pwd_hash 不是 PHC 格式時視為驗證失敗
+pub fn verify_pwd(pwd: &str, pwd_hash: &str) -> bool {
+    PasswordHash::new(pwd_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(pwd.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+fn sign_with_key(key: &[u8], content: &str) -> String {
+    let mut mac = new_mac(key);
+    mac.update(content.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+fn new_mac(key: &[u8]) -> HmacSha256 {
+    // HMAC 可以接受任意長度的金鑰，所以這邊不會失敗
+    HmacSha256::new_from_slice(key).expect("HMAC can take key of any size")
+}
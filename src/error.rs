@@ -2,22 +2,189 @@ use std::fmt::{write, Display};
 
 use axum::{http::StatusCode, response::IntoResponse};
 use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::model::TicketStatus;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
 // server error，給內部除錯使用的訊息，會定義的更加清楚跟具體，並加上除錯所需的資訊，以方便排除錯誤
 #[derive(Debug, Clone, strum_macros::AsRefStr, Serialize)]
-// 可以將序列化的資料做轉換，如果今天錯誤是觸發TicketDeleteFailIdNotFound，enum 的 variant 會是 type 的值，而 id 會是 data 的值
-// 則資料會被序列化為{"tag": "TicketDeleteFailIdNotFound", "data": "123"}
+// 可以將序列化的資料做轉換，如果今天錯誤是觸發TicketNotFound，enum 的 variant 會是 type 的值，而 id 會是 data 的值
+// 則資料會被序列化為{"tag": "TicketNotFound", "data": "123"}
 #[serde(tag = "type", content = "data")]
 pub enum Error {
     LoginFail,
+    // -- Config errors.
+    ConfigMissingEnv {
+        name: &'static str,
+    },
+    ConfigInvalidEnv {
+        name: &'static str,
+        value: String,
+    },
+    ConfigInvalidAuthenticator {
+        authenticator: String,
+    },
+    ConfigInvalidTls {
+        detail: String,
+    },
+    ConfigInvalidStore {
+        store: String,
+    },
+    // ticket 會保存下來，但使用者只存在記憶體中，重新啟動後 ticket 與 cookie 中的 user id 會對應到不同的人
+    ConfigUserStoreNotPersistent {
+        ticket_store: String,
+        user_store: String,
+    },
+    ConfigInvalidWorkflow {
+        transition: String,
+    },
+    // 資料檔或附件的路徑在 routes_static 公開的目錄底下
+    ConfigDataPathInStaticDir {
+        name: &'static str,
+        path: String,
+    },
     // -- Auth errors.
     AuthFailNoAuthTokenCookie,
     AuthFailTokenWrongFormat,
+    AuthFailTokenSignatureInvalid,
+    AuthFailTokenExpired {
+        user_id: u64,
+    },
     AuthFailCtxNotInRequestExt,
+    AuthFailUserNotFound {
+        user_id: u64,
+    },
+    AuthFailNotAdmin {
+        user_id: u64,
+    },
+    AuthFailUserInactive {
+        user_id: u64,
+    },
+    AuthFailLdap {
+        detail: String,
+    },
+    AuthFailLdapAccountConflict {
+        username: String,
+    },
+    AuthFailClientCertUnknownUser {
+        subject_cn: String,
+    },
+    AuthFailSignatureWrongFormat,
+    AuthFailSignatureUnknownKey {
+        key_id: String,
+    },
+    AuthFailSignatureUnknownUser {
+        username: String,
+    },
+    AuthFailSignatureClockSkew {
+        timestamp: u64,
+        server_time: u64,
+    },
+    AuthFailSignatureInvalid,
+    AuthFailSignatureReplayed,
+    AuthFailSignatureBodyTooLarge,
+    AuthFailSignatureBodyRead {
+        detail: String,
+    },
+    // -- SCIM errors.
+    ScimFailNotConfigured,
+    ScimFailNoBearerToken,
+    ScimFailTokenInvalid,
+    ScimFilterUnsupported {
+        filter: String,
+    },
+    ScimPatchFailUnsupported {
+        op: String,
+    },
+    // -- Crypt errors.
+    CryptFailSignatureInvalid,
+    CryptFailPwdHash {
+        detail: String,
+    },
     // -- Model errors.
-    TicketDeleteFailIdNotFound { id: u64 },
+    StoreFail {
+        detail: String,
+    },
+    StoreFailLockPoisoned,
+    StoreFailJournalPoisoned,
+    TicketNotFound {
+        id: u64,
+    },
+    TicketUpdateFailNotOwner {
+        id: u64,
+        user_id: u64,
+    },
+    TicketFailVersionMismatch {
+        id: u64,
+        expected: u64,
+        actual: u64,
+    },
+    TicketFailUpdateConflict {
+        id: u64,
+    },
+    TicketFailIfMatchRequired,
+    TicketFailIfMatchWrongFormat {
+        value: String,
+    },
+    TicketFailCursorInvalid,
+    TicketFailCursorSortMismatch,
+    TicketFailCursorWithOffset,
+    SearchFailEmptyQuery,
+    CommentNotFound {
+        id: u64,
+    },
+    CommentFailNotAuthor {
+        id: u64,
+        user_id: u64,
+    },
+    AttachmentNotFound {
+        id: u64,
+    },
+    AttachmentFailNotUploader {
+        id: u64,
+        user_id: u64,
+    },
+    AttachmentFailNoFile,
+    AttachmentFailMultipart {
+        detail: String,
+    },
+    AttachmentFailTooLarge {
+        max_bytes: usize,
+    },
+    AttachmentFailMimeNotAllowed {
+        content_type: String,
+    },
+    TicketTransitionNotAllowed {
+        id: u64,
+        from: TicketStatus,
+        to: TicketStatus,
+        allowed: Vec<TicketStatus>,
+    },
+    UserNotFound {
+        id: u64,
+    },
+    GroupNotFound {
+        id: u64,
+    },
+    UserCreateFailUsernameTaken {
+        username: String,
+    },
+    InvitationNotFound {
+        id: u64,
+    },
+    InvitationNotPending {
+        id: u64,
+    },
+    InvitationExpired {
+        id: u64,
+    },
+    // token 的簽章正確，但到期時間或 nonce 與儲存的邀請不符
+    InvitationTokenMismatch {
+        id: u64,
+    },
+    InvitationTokenWrongFormat,
 }
 
 // 為我們自定義的Error實作標準庫Error的trait，要滿足條件需要實作Display跟Debug的trait
@@ -50,14 +217,104 @@ impl Error {
         #[allow(unreachable_patterns)]
         match self {
             Self::LoginFail => (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL),
+            // LDAP 帳號與同名的本地帳號衝突時，不透露帳號是否存在，視為登入失敗
+            Self::AuthFailLdapAccountConflict { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL)
+            }
             // -- Auth
             Self::AuthFailCtxNotInRequestExt
             | Self::AuthFailNoAuthTokenCookie
-            | Self::AuthFailTokenWrongFormat => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
+            | Self::AuthFailTokenWrongFormat
+            | Self::AuthFailTokenSignatureInvalid
+            | Self::AuthFailTokenExpired { .. }
+            | Self::AuthFailUserNotFound { .. }
+            | Self::AuthFailUserInactive { .. }
+            | Self::AuthFailClientCertUnknownUser { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::NO_AUTH)
+            }
+            // 時間誤差需要 client 自行校正時間，所以單獨告知
+            Self::AuthFailSignatureClockSkew { .. } => {
+                (StatusCode::UNAUTHORIZED, ClientError::SIGNATURE_CLOCK_SKEW)
+            }
+            Self::AuthFailSignatureWrongFormat
+            | Self::AuthFailSignatureUnknownKey { .. }
+            | Self::AuthFailSignatureUnknownUser { .. }
+            | Self::AuthFailSignatureInvalid
+            | Self::AuthFailSignatureReplayed => (StatusCode::UNAUTHORIZED, ClientError::NO_AUTH),
+            Self::AuthFailSignatureBodyTooLarge => {
+                (StatusCode::PAYLOAD_TOO_LARGE, ClientError::INVALID_PARAMS)
+            }
+            Self::AuthFailSignatureBodyRead { .. } => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
+            }
+            Self::AuthFailNotAdmin { .. }
+            | Self::TicketUpdateFailNotOwner { .. }
+            | Self::CommentFailNotAuthor { .. }
+            | Self::AttachmentFailNotUploader { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::NO_PERMISSION)
+            }
+            // -- SCIM
+            Self::ScimFailNotConfigured
+            | Self::ScimFailNoBearerToken
+            | Self::ScimFailTokenInvalid => (StatusCode::UNAUTHORIZED, ClientError::NO_AUTH),
+            Self::ScimFilterUnsupported { .. } | Self::ScimPatchFailUnsupported { .. } => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
+            }
             // -- Model
-            Self::TicketDeleteFailIdNotFound { .. } => {
+            Self::TicketNotFound { .. }
+            | Self::CommentNotFound { .. }
+            | Self::AttachmentNotFound { .. }
+            | Self::UserNotFound { .. }
+            | Self::GroupNotFound { .. } => (StatusCode::NOT_FOUND, ClientError::ENTITY_NOT_FOUND),
+            Self::AttachmentFailNoFile | Self::AttachmentFailMultipart { .. } => {
                 (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
             }
+            Self::AttachmentFailTooLarge { .. } => {
+                (StatusCode::PAYLOAD_TOO_LARGE, ClientError::INVALID_PARAMS)
+            }
+            Self::AttachmentFailMimeNotAllowed { .. } => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ClientError::INVALID_PARAMS,
+            ),
+            Self::InvitationNotFound { .. } | Self::InvitationNotPending { .. } => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
+            }
+            Self::UserCreateFailUsernameTaken { .. } => {
+                (StatusCode::CONFLICT, ClientError::INVALID_PARAMS)
+            }
+            // 版本不符時 client 需要重新取得 ticket 再修改
+            Self::TicketFailVersionMismatch { .. } => (
+                StatusCode::PRECONDITION_FAILED,
+                ClientError::VERSION_MISMATCH,
+            ),
+            // 沒有指定版本的修改一直與其他人的修改衝突，client 可以稍後再試
+            Self::TicketFailUpdateConflict { .. } => {
+                (StatusCode::CONFLICT, ClientError::VERSION_MISMATCH)
+            }
+            Self::TicketFailIfMatchRequired => (
+                StatusCode::PRECONDITION_REQUIRED,
+                ClientError::INVALID_PARAMS,
+            ),
+            Self::TicketFailIfMatchWrongFormat { .. } => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
+            }
+            // 游標無法使用時 client 需要從第一頁重新查詢
+            Self::TicketFailCursorInvalid | Self::TicketFailCursorSortMismatch => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_CURSOR)
+            }
+            Self::TicketFailCursorWithOffset | Self::SearchFailEmptyQuery => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
+            }
+            Self::TicketTransitionNotAllowed { .. } => {
+                (StatusCode::CONFLICT, ClientError::INVALID_STATUS_TRANSITION)
+            }
+            // 邀請 token 的錯誤不區分細節，避免外部藉此猜測 token 的內容
+            Self::CryptFailSignatureInvalid
+            | Self::InvitationTokenWrongFormat
+            | Self::InvitationTokenMismatch { .. }
+            | Self::InvitationExpired { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::INVALID_INVITATION)
+            }
 
             // -- Fallback
             _ => (
@@ -66,6 +323,17 @@ impl Error {
             ),
         }
     }
+
+    // 讓 client 可以自行修正 request 的細節，放在錯誤回應的 detail 中，大部分的錯誤沒有
+    // 只回傳 client 本來就可以查詢到的資訊，例如目前的狀態與允許的狀態轉換
+    pub fn client_detail(&self) -> Option<Value> {
+        match self {
+            Self::TicketTransitionNotAllowed {
+                from, to, allowed, ..
+            } => Some(json!({"from": from, "to": to, "allowed": allowed})),
+            _ => None,
+        }
+    }
 }
 
 // client error，給外部看的，不會有太多細節，訊息也比較籠統
@@ -74,6 +342,13 @@ impl Error {
 pub enum ClientError {
     LOGIN_FAIL,
     NO_AUTH,
+    NO_PERMISSION,
+    SIGNATURE_CLOCK_SKEW,
     INVALID_PARAMS,
+    INVALID_CURSOR,
+    INVALID_INVITATION,
+    INVALID_STATUS_TRANSITION,
+    VERSION_MISMATCH,
+    ENTITY_NOT_FOUND,
     SERVICE_ERROR,
 }
@@ -0,0 +1,16 @@
+#![allow(unused)]
+// 服務的各個元件，main.rs 只負責組合路由與啟動服務
+// 以 library 的形式提供，整合測試與 benchmark 可以直接使用儲存層等元件，不需要透過 HTTP
+
+pub mod auth;
+pub mod config;
+pub mod crypt;
+pub mod ctx;
+pub mod error;
+pub mod log;
+pub mod model;
+pub mod tls;
+pub mod utils;
+pub mod web;
+
+pub use error::{Error, Result};
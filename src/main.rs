@@ -1,8 +1,5 @@
 #![allow(unused)]
 
-use crate::{log::log_request, model::ModelController, web::mw_auth};
-
-use self::error::{Error, Result};
 use axum::{
     extract::{Path, Query},
     http::{Method, Uri},
@@ -12,6 +9,10 @@ use axum::{
     Json, Router, ServiceExt,
 };
 use ctx::Ctx;
+use my_first_axum::config::{config, init_config};
+use my_first_axum::{auth, ctx, tls, web};
+use my_first_axum::{log::log_request, model::ModelController, web::mw_auth};
+use my_first_axum::{Error, Result};
 use serde::Deserialize;
 use serde_json::json;
 use std::{fmt::format, net::SocketAddr};
@@ -20,25 +21,30 @@ use tower_cookies::CookieManagerLayer;
 use tower_http::services::ServeDir;
 use uuid::Uuid;
 
-mod ctx;
-mod error;
-mod log;
-mod model;
-mod web;
 #[tokio::main]
 async fn main() -> Result<()> {
+    // 設定有誤（例如沒有設定 SERVICE_TOKEN_KEY）時直接結束，不要以不安全的預設值啟動
+    init_config()?;
     // 先建立我們的資料庫
     let mc = ModelController::new().await?;
+    // 登入時驗證帳密的來源（本地帳號、LDAP），依照設定組合
+    let authenticator = auth::authenticator_from_config()?;
     // 我們ticket相關的API呼叫，需要經過權限認證，因此我們加上一層middleware來進行驗證的動作
     // 而因為我們只希望權限驗證發生在這邊，所以我們使用route_layer，而不是layer
     let routes_apis = web::routes_tickets::routes(mc.clone())
+        .merge(web::routes_comments::routes(mc.clone()))
+        .merge(web::routes_attachments::routes(mc.clone()))
+        .merge(web::routes_invitations::routes(mc.clone()))
         .route_layer(middleware::from_fn(mw_auth::mw_require_auth));
 
     // 所有路由的匯總之處，透過merge可以將路由一部份一部份的加上去
     // 如果是一般的.route()則是添加一個路由handler
     let routes_all = Router::new()
         .merge(routes_hello())
-        .merge(web::routes_login::routes())
+        .merge(web::routes_login::routes(mc.clone(), authenticator))
+        .merge(web::routes_invitations::routes_accept(mc.clone()))
+        // SCIM 使用自己的 Bearer token 驗證，與一般使用者的 cookie 驗證分開，錯誤也使用 SCIM 的格式
+        .merge(web::routes_scim::routes(mc.clone()))
         // nest的作用是幫你把提供的路由再包上一層
         .nest("/api", routes_apis)
         // layer是全域範圍的，可以幫你對routes做額外的處理，要留意的是，layer會對你已存在的routes作處理，但不會處理後來添加的，
@@ -50,25 +56,54 @@ async fn main() -> Result<()> {
             mc.clone(),
             web::mw_auth::mw_ctx_resolver,
         ))
+        // 簽章請求需要在 mw_ctx_resolver 之前驗證，所以要放在它後面（越後面添加的layer越先執行）
+        .layer(middleware::from_fn_with_state(
+            mc.clone(),
+            web::mw_signature::mw_signature_verifier,
+        ))
         .layer(CookieManagerLayer::new())
         // fallback_service: 如果沒有匹配到任何Route，將會使用這邊提供的服務。
         .fallback_service(routes_static());
 
-    // 綁定一個SocketAddr變數
-    // 也可以透過此方式綁定：let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
-    // 但比較繁瑣，使用from可以直接進行轉換
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+    // 背景工作：定期永久刪除在垃圾桶中超過保留期限的 ticket
+    if config().trash_retention_secs > 0 {
+        tokio::spawn(purge_trash_periodically(mc.clone()));
+    }
+
+    // 綁定一個SocketAddr變數，預設為 127.0.0.1:8080，可以透過 SERVICE_ADDR 修改
+    let addr = config().addr;
     println!("->> Listen on addr: {addr}");
-    axum::Server::bind(&addr)
-        // serve參數提供的routes_all要呼叫.into_make_service才能使用
-        // 因為serve這個方法是hyper所定義的，而.into_make_service會幫你將axum的Router轉換成hyper可以接受的格式
-        .serve(routes_all.into_make_service())
-        // 這邊加上教學中沒有的with_graceful_shutdown，這個可以為這個服務加上一個服務用來處理特定信號
-        // 以我們這邊為例，當接受ctrl+c或是任何terminate的信號，就會觸發並在等候其他之前接受到的request完成之後將服務關閉
-        // 這邊也可以實作一些關閉連接、清除資源等等的動作，但也可以透過實作連接的物件的drop來處理，端看怎麼設計
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
+    match &config().tls {
+        // 有設定憑證時以 HTTPS 啟動，並透過 ClientCertAcceptor 取得 client certificate
+        Some(tls_config) => {
+            let acceptor = tls::ClientCertAcceptor::new(tls::rustls_config(tls_config)?);
+            // axum_server 的 graceful shutdown 是透過 Handle 觸發的
+            let handle = axum_server::Handle::new();
+            tokio::spawn(shutdown_on_signal(handle.clone()));
+            axum_server::bind(addr)
+                .acceptor(acceptor)
+                .handle(handle)
+                .serve(routes_all.into_make_service())
+                .await
+                .unwrap();
+        }
+        None => {
+            axum::Server::bind(&addr)
+                // serve參數提供的routes_all要呼叫.into_make_service才能使用
+                // 因為serve這個方法是hyper所定義的，而.into_make_service會幫你將axum的Router轉換成hyper可以接受的格式
+                .serve(routes_all.into_make_service())
+                // 這邊加上教學中沒有的with_graceful_shutdown，這個可以為這個服務加上一個服務用來處理特定信號
+                // 以我們這邊為例，當接受ctrl+c或是任何terminate的信號，就會觸發並在等候其他之前接受到的request完成之後將服務關閉
+                // 這邊也可以實作一些關閉連接、清除資源等等的動作，但也可以透過實作連接的物件的drop來處理，端看怎麼設計
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .unwrap();
+        }
+    }
+
+    // shutdown_signal 觸發且處理中的 request 都完成後，才讓儲存層寫入 snapshot，確保不會漏掉任何異動
+    mc.shutdown().await?;
+    println!("->> {:<12} - store flushed, bye", "SHUTDOWN");
 
     Ok(())
 }
@@ -87,16 +122,25 @@ async fn main_response_mapper(
     let service_error = res.extensions().get::<Error>();
     // 將我們附加的錯誤訊息取出，並呼叫轉換，以避免內部訊息洩漏出去
     let client_status_error = service_error.map(|se| se.client_status_and_error());
+    // SCIM 的錯誤已經由 routes_scim 轉換成 SCIM 的格式，這邊只需要記錄 log
+    let scim_mapped = res
+        .extensions()
+        .get::<web::routes_scim::ScimErrorMapped>()
+        .is_some();
     // -- If client error, build the new response.
     let error_response = client_status_error
         // 當我們想要借用別人的值，但我們不需要外部的嵌套(Option)時，可以使用as_ref
         .as_ref()
+        .filter(|_| !scim_mapped)
         // 並接續使用map，可以將內部數值直接轉換，不用使用到我們as_ref的對象的所有權
         .map(|(status_code, client_error)| {
-            let client_error_body =
+            let mut client_error_body =
                 // client_error可以呼叫as_ref是因為使用strum_macros::AsRefStr
                 // 可以將被衍生的enum其中的值都轉換成'static str取代原本使用的String，減少浪費開銷
                 json!({"error": {"type": client_error.as_ref(), "req_uuid": uuid.to_string()}});
+            if let Some(detail) = service_error.and_then(Error::client_detail) {
+                client_error_body["error"]["detail"] = detail;
+            }
             println!("   ->> client_error_body: {client_error_body}");
 
             // Build the new response from client_error_body
@@ -117,9 +161,10 @@ async fn main_response_mapper(
 }
 fn routes_static() -> Router {
     // nest_service: 將提供的service包裝在指定的path之下
-    // 這邊我們將檔案目錄下的內容直接提供外部存取，並包在"/"路徑底下，假使我們根目錄底下有1.png
+    // 這邊我們將 SERVICE_STATIC_DIR（預設為 ./static）的內容直接提供外部存取，並包在"/"路徑底下，假使目錄底下有1.png
     // 別人可以直接使用 https://<domain-name>/1.png來取得
-    Router::new().nest_service("/", get_service(ServeDir::new("./")))
+    // 只公開這個目錄，而不是整個工作目錄，避免資料檔與原始碼被下載
+    Router::new().nest_service("/", get_service(ServeDir::new(&config().static_dir)))
 }
 fn routes_hello() -> Router {
     // 簡單的範例，定義了一個GET方法的API跟一個GET方法使用Query的API
@@ -144,6 +189,32 @@ async fn handler_hello2(Path(name): Path<String>) -> impl IntoResponse {
     Html(format!("Hello <strong>{name}</strong>"))
 }
 
+async fn purge_trash_periodically(mc: ModelController) {
+    let period = std::time::Duration::from_secs(config().trash_purge_interval_secs.max(1));
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match mc.purge_expired_tickets().await {
+            Ok(tickets) if !tickets.is_empty() => {
+                println!(
+                    "->> {:<12} - purged {} expired tickets",
+                    "TRASH",
+                    tickets.len()
+                );
+            }
+            Ok(_) => {}
+            // 失敗時只記錄下來，下一次檢查時會再嘗試
+            Err(e) => println!("->> {:<12} - purge failed - {e:?}", "TRASH"),
+        }
+    }
+}
+
+async fn shutdown_on_signal(handle: axum_server::Handle) {
+    shutdown_signal().await;
+    // 最多等待 30 秒讓處理中的 request 完成
+    handle.graceful_shutdown(Some(std::time::Duration::from_secs(30)));
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -0,0 +1,158 @@
+// ticket 的附件（截圖、log 檔等），附件的資訊存放在 ticket 的儲存層，內容存放在 BlobStore
+// ticket 被刪除時附件的資訊與內容都會一起被刪除
+use serde::{Deserialize, Serialize};
+
+use super::ModelController;
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: u64,
+    pub ticket_id: u64,
+    pub uploader_id: u64,
+    pub filename: String,
+    pub content_type: String,
+    // 內容的大小（bytes）
+    pub size: u64,
+    // 上傳的時間（Unix 秒）
+    pub created_at: u64,
+}
+
+// 由 web 層從 multipart 的欄位解析出來，大小上限在讀取時就已經檢查過
+pub struct AttachmentForUpload {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+// 儲存層建立附件資訊時使用，內容另外寫入 BlobStore
+pub struct AttachmentForCreate {
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+// 儲存層共用：以分配好的 id 建立 Attachment
+impl AttachmentForCreate {
+    pub(in crate::model) fn into_attachment(
+        self,
+        id: u64,
+        ticket_id: u64,
+        uploader_id: u64,
+    ) -> Attachment {
+        Attachment {
+            id,
+            ticket_id,
+            uploader_id,
+            filename: self.filename,
+            content_type: self.content_type,
+            size: self.size,
+            created_at: now_unix_secs(),
+        }
+    }
+}
+
+// 同一個 ticket 的附件內容放在同一個目錄下，刪除 ticket 時可以一次刪除
+pub(in crate::model) fn ticket_blob_dir(ticket_id: u64) -> String {
+    format!("tickets/{ticket_id}")
+}
+
+impl Attachment {
+    fn blob_key(&self) -> String {
+        format!("{}/{}", ticket_blob_dir(self.ticket_id), self.id)
+    }
+}
+
+impl ModelController {
+    // 先建立附件資訊（同時確認 ticket 存在並分配 id），再寫入內容，寫入失敗時將附件資訊刪除
+    pub async fn create_attachment(
+        &self,
+        ctx: Ctx,
+        ticket_id: u64,
+        upload: AttachmentForUpload,
+    ) -> Result<Attachment> {
+        let content_type = upload.content_type.to_ascii_lowercase();
+        if !config().attachment_mime_types.contains(&content_type) {
+            return Err(Error::AttachmentFailMimeNotAllowed { content_type });
+        }
+        let attachment_fc = AttachmentForCreate {
+            filename: sanitize_filename(&upload.filename),
+            content_type,
+            size: upload.data.len() as u64,
+        };
+        let attachment = self
+            .attachments_store
+            .create(ticket_id, ctx.user_id(), attachment_fc)
+            .await?;
+        if let Err(e) = self
+            .blob_store
+            .put(&attachment.blob_key(), upload.data)
+            .await
+        {
+            self.attachments_store.delete(attachment.id).await?;
+            return Err(e);
+        }
+        Ok(attachment)
+    }
+
+    pub async fn list_attachments(&self, _ctx: Ctx, ticket_id: u64) -> Result<Vec<Attachment>> {
+        self.tickets_store.get(ticket_id).await?;
+        self.attachments_store.list(ticket_id).await
+    }
+
+    // 回傳附件資訊與內容，內容還沒寫入（上傳中）時視為找不到
+    pub async fn get_attachment_content(
+        &self,
+        _ctx: Ctx,
+        ticket_id: u64,
+        id: u64,
+    ) -> Result<(Attachment, Vec<u8>)> {
+        let attachment = self.get_attachment(ticket_id, id).await?;
+        let data = self
+            .blob_store
+            .get(&attachment.blob_key())
+            .await?
+            .ok_or(Error::AttachmentNotFound { id })?;
+        Ok((attachment, data))
+    }
+
+    // 上傳者可以刪除自己的附件，管理者可以刪除任何附件
+    pub async fn delete_attachment(&self, ctx: Ctx, ticket_id: u64, id: u64) -> Result<Attachment> {
+        let attachment = self.get_attachment(ticket_id, id).await?;
+        if attachment.uploader_id != ctx.user_id() && !ctx.is_admin() {
+            return Err(Error::AttachmentFailNotUploader {
+                id,
+                user_id: ctx.user_id(),
+            });
+        }
+        let attachment = self.attachments_store.delete(id).await?;
+        self.blob_store.delete(&attachment.blob_key()).await?;
+        Ok(attachment)
+    }
+
+    // 附件必須屬於網址中的 ticket，否則視為找不到
+    // ticket 在垃圾桶中時視為找不到，不能下載或刪除它的附件
+    async fn get_attachment(&self, ticket_id: u64, id: u64) -> Result<Attachment> {
+        self.tickets_store.get(ticket_id).await?;
+        let attachment = self.attachments_store.get(id).await?;
+        if attachment.ticket_id != ticket_id {
+            return Err(Error::AttachmentNotFound { id });
+        }
+        Ok(attachment)
+    }
+}
+
+// 只保留檔名的部分（部分瀏覽器會送出完整路徑），並移除控制字元
+fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,120 @@
+// ticket 底下的留言，作者由 Ctx 決定，只有作者可以修改自己的留言
+// 留言與 ticket 存放在同一個儲存層，ticket 被刪除時留言也會一起被刪除
+// 留言的內容也會被全文檢索，新增、修改、刪除後同步更新索引
+use serde::{Deserialize, Serialize};
+
+use super::ModelController;
+use crate::ctx::Ctx;
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: u64,
+    pub ticket_id: u64,
+    pub author_id: u64,
+    pub body: String,
+    // 建立與最後一次修改的時間（Unix 秒），從未修改過時 updated_at 為 None
+    pub created_at: u64,
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentForCreate {
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct CommentForUpdate {
+    pub body: String,
+}
+
+// 儲存層共用：以分配好的 id 建立 Comment
+impl CommentForCreate {
+    pub(in crate::model) fn into_comment(self, id: u64, ticket_id: u64, author_id: u64) -> Comment {
+        Comment {
+            id,
+            ticket_id,
+            author_id,
+            body: self.body,
+            created_at: now_unix_secs(),
+            updated_at: None,
+        }
+    }
+}
+
+// 儲存層共用：將 CommentForUpdate 套用到既有的 Comment 上
+impl CommentForUpdate {
+    pub(in crate::model) fn apply_to(self, comment: &mut Comment) {
+        comment.body = self.body;
+        comment.updated_at = Some(now_unix_secs());
+    }
+}
+
+impl ModelController {
+    pub async fn create_comment(
+        &self,
+        ctx: Ctx,
+        ticket_id: u64,
+        comment_fc: CommentForCreate,
+    ) -> Result<Comment> {
+        // 確認 ticket 存在，找不到時回傳 TicketNotFound
+        self.tickets_store.get(ticket_id).await?;
+        let comment = self
+            .comments_store
+            .create(ticket_id, ctx.user_id(), comment_fc)
+            .await?;
+        self.index_comment(&comment)?;
+        Ok(comment)
+    }
+
+    pub async fn list_comments(&self, _ctx: Ctx, ticket_id: u64) -> Result<Vec<Comment>> {
+        self.tickets_store.get(ticket_id).await?;
+        self.comments_store.list(ticket_id).await
+    }
+
+    // 只有作者可以修改留言
+    pub async fn update_comment(
+        &self,
+        ctx: Ctx,
+        ticket_id: u64,
+        id: u64,
+        comment_fu: CommentForUpdate,
+    ) -> Result<Comment> {
+        let comment = self.get_comment(ticket_id, id).await?;
+        if comment.author_id != ctx.user_id() {
+            return Err(Error::CommentFailNotAuthor {
+                id,
+                user_id: ctx.user_id(),
+            });
+        }
+        let comment = self.comments_store.update(id, comment_fu).await?;
+        self.index_comment(&comment)?;
+        Ok(comment)
+    }
+
+    // 作者可以刪除自己的留言，管理者可以刪除任何留言（例如不當的內容）
+    pub async fn delete_comment(&self, ctx: Ctx, ticket_id: u64, id: u64) -> Result<Comment> {
+        let comment = self.get_comment(ticket_id, id).await?;
+        if comment.author_id != ctx.user_id() && !ctx.is_admin() {
+            return Err(Error::CommentFailNotAuthor {
+                id,
+                user_id: ctx.user_id(),
+            });
+        }
+        let comment = self.comments_store.delete(id).await?;
+        self.unindex_comment(&comment)?;
+        Ok(comment)
+    }
+
+    // 留言必須屬於網址中的 ticket，否則視為找不到
+    // ticket 在垃圾桶中時與新增留言相同，回傳 TicketNotFound，不能修改或刪除底下的留言
+    async fn get_comment(&self, ticket_id: u64, id: u64) -> Result<Comment> {
+        self.tickets_store.get(ticket_id).await?;
+        let comment = self.comments_store.get(id).await?;
+        if comment.ticket_id != ticket_id {
+            return Err(Error::CommentNotFound { id });
+        }
+        Ok(comment)
+    }
+}
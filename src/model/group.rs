@@ -0,0 +1,104 @@
+// 群組相關的資料定義與操作，群組只是一組使用者的集合，目前主要由 SCIM 同步而來
+use serde::{Deserialize, Serialize};
+
+use super::ModelController;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub id: u64,
+    pub display_name: String,
+    pub members: Vec<u64>, // member user_ids
+}
+
+pub struct GroupForCreate {
+    pub display_name: String,
+    pub members: Vec<u64>,
+}
+
+impl GroupForCreate {
+    // id 由儲存層分配
+    pub(in crate::model) fn into_group(self, id: u64) -> Group {
+        Group {
+            id,
+            display_name: self.display_name,
+            members: dedup(self.members),
+        }
+    }
+}
+
+// 成員的異動分成「整批取代」跟「增加/移除」兩種，對應 SCIM PATCH 的 replace 與 add/remove
+#[derive(Default, Clone)]
+pub struct GroupForUpdate {
+    pub display_name: Option<String>,
+    pub members: Option<Vec<u64>>,
+    pub add_members: Vec<u64>,
+    pub remove_members: Vec<u64>,
+}
+
+impl GroupForUpdate {
+    pub(in crate::model) fn apply_to(self, group: &mut Group) {
+        if let Some(display_name) = self.display_name {
+            group.display_name = display_name;
+        }
+        if let Some(members) = self.members {
+            group.members = members;
+        }
+        group.members.extend(self.add_members);
+        group.members.retain(|m| !self.remove_members.contains(m));
+        group.members = dedup(std::mem::take(&mut group.members));
+    }
+}
+
+impl ModelController {
+    pub async fn create_group(&self, group_fc: GroupForCreate) -> Result<Group> {
+        self.check_members_exist(&group_fc.members).await?;
+        self.groups_store.create(group_fc).await
+    }
+
+    pub async fn get_group(&self, id: u64) -> Result<Group> {
+        self.groups_store.get(id).await
+    }
+
+    pub async fn list_groups(&self) -> Result<Vec<Group>> {
+        self.groups_store.list().await
+    }
+
+    pub async fn list_groups_by_display_name(&self, display_name: &str) -> Result<Vec<Group>> {
+        self.groups_store.list_by_display_name(display_name).await
+    }
+
+    pub async fn update_group(&self, id: u64, group_fu: GroupForUpdate) -> Result<Group> {
+        let mut ids = group_fu.add_members.clone();
+        ids.extend(group_fu.members.iter().flatten());
+        self.check_members_exist(&ids).await?;
+        self.groups_store.update(id, group_fu).await
+    }
+
+    pub async fn delete_group(&self, id: u64) -> Result<Group> {
+        self.groups_store.delete(id).await
+    }
+
+    // 使用者所屬的所有群組
+    pub async fn groups_of_user(&self, user_id: u64) -> Result<Vec<Group>> {
+        self.groups_store.list_by_member(user_id).await
+    }
+
+    async fn check_members_exist(&self, members: &[u64]) -> Result<()> {
+        for &id in members {
+            self.get_user(id).await?.ok_or(Error::UserNotFound { id })?;
+        }
+        Ok(())
+    }
+}
+
+// 保留第一次出現的順序並移除重複的成員
+fn dedup(members: Vec<u64>) -> Vec<u64> {
+    let mut result = Vec::with_capacity(members.len());
+    for m in members {
+        if !result.contains(&m) {
+            result.push(m);
+        }
+    }
+    result
+}
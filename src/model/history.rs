@@ -0,0 +1,210 @@
+// ticket 的異動紀錄（audit trail），每一次建立、修改、刪除、還原都會新增一筆，新增後不會再被修改或刪除
+// ticket 被永久刪除後紀錄仍然保留，之後仍然可以查詢是誰在什麼時候刪除的
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::{require_admin, ModelController, Ticket};
+use crate::ctx::Ctx;
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::AsRefStr)]
+pub enum TicketHistoryAction {
+    Create,
+    Update,
+    // 移到垃圾桶
+    Delete,
+    Restore,
+    // 永久刪除
+    Purge,
+}
+
+// 資料庫中以文字儲存，讀取時再轉換回來，與 JSON 中的表示方式相同
+impl FromStr for TicketHistoryAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "Create" => Ok(Self::Create),
+            "Update" => Ok(Self::Update),
+            "Delete" => Ok(Self::Delete),
+            "Restore" => Ok(Self::Restore),
+            "Purge" => Ok(Self::Purge),
+            _ => Err(format!("unknown ticket history action: {s}")),
+        }
+    }
+}
+
+// 單一欄位修改前後的值，以 JSON 表示，建立時 before 為 null
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TicketFieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketHistory {
+    pub id: u64,
+    pub ticket_id: u64,
+    // 執行操作的使用者，由系統自動執行（例如垃圾桶的自動清除）時為 None
+    pub actor_id: Option<u64>,
+    pub action: TicketHistoryAction,
+    // 有改變的欄位，刪除、還原與永久刪除記錄 deleted_at、deleted_by 與 version 的變化
+    pub changes: Vec<TicketFieldChange>,
+    // 發生的時間（Unix 秒）
+    pub created_at: u64,
+}
+
+pub struct TicketHistoryForCreate {
+    pub ticket_id: u64,
+    pub actor_id: Option<u64>,
+    pub action: TicketHistoryAction,
+    pub changes: Vec<TicketFieldChange>,
+}
+
+// 儲存層共用：以分配好的 id 建立 TicketHistory
+impl TicketHistoryForCreate {
+    pub(in crate::model) fn into_history(self, id: u64) -> TicketHistory {
+        TicketHistory {
+            id,
+            ticket_id: self.ticket_id,
+            actor_id: self.actor_id,
+            action: self.action,
+            changes: self.changes,
+            created_at: now_unix_secs(),
+        }
+    }
+}
+
+// 隨著 ticket 的異動一起交給儲存層，與異動在同一個操作（transaction）中寫入，不會只留下其中一個
+// ticket_id 由儲存層填入，建立 ticket 時 id 在寫入時才會分配
+#[derive(Clone)]
+pub struct TicketHistoryForInsert {
+    pub actor_id: Option<u64>,
+    pub action: TicketHistoryAction,
+    pub changes: Vec<TicketFieldChange>,
+}
+
+// 儲存層共用：填入 ticket_id，修改時所有欄位都與原本相同則不需要留下紀錄（回傳 None）
+impl TicketHistoryForInsert {
+    // 移到垃圾桶、還原與永久刪除時，以儲存層中修改前後的 ticket 填入 changes
+    // deleted_at 由儲存層寫入時才決定，所以無法在 ModelController 中先算好
+    pub(in crate::model) fn with_lifecycle(
+        mut self,
+        before: &Ticket,
+        after: Option<&Ticket>,
+    ) -> Self {
+        self.changes = diff_lifecycle(before, after);
+        self
+    }
+
+    pub(in crate::model) fn for_ticket(self, ticket_id: u64) -> Option<TicketHistoryForCreate> {
+        if self.action == TicketHistoryAction::Update && self.changes.is_empty() {
+            return None;
+        }
+        Some(TicketHistoryForCreate {
+            ticket_id,
+            actor_id: self.actor_id,
+            action: self.action,
+            changes: self.changes,
+        })
+    }
+}
+
+// 記錄變化的欄位，updated_at、deleted_at 等欄位本身就是異動的資訊，不列入比較
+fn ticket_fields(ticket: &Ticket) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("title".to_string(), json!(ticket.title));
+    fields.insert("description".to_string(), json!(ticket.description));
+    fields.insert("status".to_string(), json!(ticket.status));
+    fields.insert("priority".to_string(), json!(ticket.priority));
+    fields.insert("labels".to_string(), json!(ticket.labels));
+    fields.insert("assignee".to_string(), json!(ticket.assignee));
+    fields
+}
+
+// 移到垃圾桶、還原與永久刪除改變的欄位，永久刪除後 ticket 已經不存在，全部記錄為 null
+fn lifecycle_fields(ticket: Option<&Ticket>) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert(
+        "deleted_at".to_string(),
+        json!(ticket.and_then(|t| t.deleted_at)),
+    );
+    fields.insert(
+        "deleted_by".to_string(),
+        json!(ticket.and_then(|t| t.deleted_by)),
+    );
+    fields.insert("version".to_string(), json!(ticket.map(|t| t.version)));
+    fields
+}
+
+// 比較修改前後的 ticket，只回傳有改變的欄位，before 為 None 代表新建立的 ticket
+pub(in crate::model) fn diff_tickets(
+    before: Option<&Ticket>,
+    after: &Ticket,
+) -> Vec<TicketFieldChange> {
+    diff_fields(
+        before.map(ticket_fields).unwrap_or_default(),
+        ticket_fields(after),
+    )
+}
+
+fn diff_lifecycle(before: &Ticket, after: Option<&Ticket>) -> Vec<TicketFieldChange> {
+    diff_fields(lifecycle_fields(Some(before)), lifecycle_fields(after))
+}
+
+fn diff_fields(before: Map<String, Value>, after: Map<String, Value>) -> Vec<TicketFieldChange> {
+    after
+        .into_iter()
+        .filter_map(|(field, after)| {
+            let before = before.get(&field).cloned().unwrap_or(Value::Null);
+            (before != after).then_some(TicketFieldChange {
+                field,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+impl ModelController {
+    // 依發生的順序列出，垃圾桶中或已經永久刪除的 ticket 也可以查詢
+    // 可以看到的範圍與 ticket 本身相同：垃圾桶中的 ticket 需要修改的權限，已經永久刪除的只有管理者可以查詢
+    // 在加入異動紀錄之前建立的 ticket 沒有任何紀錄，這時只要 ticket 存在就回傳空的列表
+    pub async fn list_ticket_history(
+        &self,
+        ctx: Ctx,
+        ticket_id: u64,
+    ) -> Result<Vec<TicketHistory>> {
+        match self.tickets_store.get(ticket_id).await {
+            Ok(_) => self.history_store.list(ticket_id).await,
+            Err(Error::TicketNotFound { .. }) => {
+                self.check_history_access(&ctx, ticket_id).await?;
+                let history = self.history_store.list(ticket_id).await?;
+                if history.is_empty() {
+                    return Err(Error::TicketNotFound { id: ticket_id });
+                }
+                Ok(history)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // 不在 ticket 列表中的 ticket：在垃圾桶中時檢查修改的權限，都找不到時視為已經永久刪除
+    async fn check_history_access(&self, ctx: &Ctx, ticket_id: u64) -> Result<()> {
+        match self.tickets_store.get_deleted(ticket_id).await {
+            Ok(ticket) if !self.can_update_ticket(ctx, &ticket).await => {
+                Err(Error::TicketUpdateFailNotOwner {
+                    id: ticket_id,
+                    user_id: ctx.user_id(),
+                })
+            }
+            Ok(_) => Ok(()),
+            Err(Error::TicketNotFound { .. }) => require_admin(ctx),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// 邀請相關的資料定義與操作
+// 管理者以 email 邀請使用者加入組織，被邀請者拿到 token 之後再自行設定帳號密碼
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::user::{AuthSource, Role, User, UserForCreate};
+use super::{require_admin, ModelController};
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::AsRefStr)]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Revoked,
+}
+
+// 資料庫中以文字儲存，讀取時再轉換回來
+impl FromStr for InvitationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(Self::Pending),
+            "Accepted" => Ok(Self::Accepted),
+            "Revoked" => Ok(Self::Revoked),
+            _ => Err(format!("unknown invitation status: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Invitation {
+    pub id: u64,
+    pub cid: u64, // creator user_id
+    pub email: String,
+    pub role: Role,
+    pub status: InvitationStatus,
+    pub expires_at: u64, // unix timestamp (seconds)
+    pub accepted_by: Option<u64>,
+    // 每個邀請各自的隨機值，與 id、到期時間一起簽進 token，不會出現在回傳給外部的 JSON 中
+    #[serde(skip)]
+    pub(in crate::model) nonce: String,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationForCreate {
+    pub email: String,
+    pub role: Role,
+}
+
+// 寫入儲存層時使用，id 由儲存層分配
+pub struct InvitationForInsert {
+    pub cid: u64,
+    pub email: String,
+    pub role: Role,
+    pub expires_at: u64,
+    pub nonce: String,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationForAccept {
+    pub username: String,
+    pub pwd: String,
+}
+
+// 從邀請 token 中取出、已經驗證過簽章的內容
+pub struct InvitationClaims {
+    pub id: u64,
+    pub expires_at: u64,
+    pub nonce: String,
+}
+
+impl Invitation {
+    pub fn nonce(&self) -> &str {
+        &self.nonce
+    }
+}
+
+impl ModelController {
+    pub async fn create_invitation(
+        &self,
+        ctx: Ctx,
+        invitation_fc: InvitationForCreate,
+    ) -> Result<Invitation> {
+        require_admin(&ctx)?;
+        let invitation_fi = InvitationForInsert {
+            cid: ctx.user_id(),
+            email: invitation_fc.email,
+            role: invitation_fc.role,
+            expires_at: now_unix_secs() + config().invitation_ttl_secs,
+            nonce: Uuid::new_v4().simple().to_string(),
+        };
+        self.invitations_store.create(invitation_fi).await
+    }
+
+    pub async fn list_invitations(&self, ctx: Ctx) -> Result<Vec<Invitation>> {
+        require_admin(&ctx)?;
+        self.invitations_store.list().await
+    }
+
+    // 撤銷只是修改狀態，保留紀錄方便之後查詢是誰邀請了誰
+    pub async fn revoke_invitation(&self, ctx: Ctx, id: u64) -> Result<Invitation> {
+        require_admin(&ctx)?;
+        self.invitations_store
+            .update_status(
+                id,
+                InvitationStatus::Pending,
+                InvitationStatus::Revoked,
+                None,
+            )
+            .await
+    }
+
+    // 接受邀請：若該 email 已經有帳號，驗證密碼後將邀請的角色授予該帳號，否則建立新帳號
+    pub async fn accept_invitation(
+        &self,
+        claims: InvitationClaims,
+        invitation_fa: InvitationForAccept,
+    ) -> Result<User> {
+        // 先將邀請標記為已接受，避免同一個 token 被同時使用兩次
+        let invitation = self.claim_invitation(claims).await?;
+
+        let result = match self.first_user_by_email(&invitation.email).await? {
+            Some(user) => self.link_invitation(&invitation, user, invitation_fa).await,
+            None => {
+                self.create_user(UserForCreate {
+                    username: invitation_fa.username,
+                    email: invitation.email.clone(),
+                    pwd: invitation_fa.pwd,
+                    role: invitation.role,
+                    auth_source: AuthSource::Local,
+                })
+                .await
+            }
+        };
+
+        let (status, accepted_by) = match &result {
+            Ok(user) => (InvitationStatus::Accepted, Some(user.id)),
+            // 建立或連結帳號失敗時，讓邀請恢復成可以再次使用的狀態
+            Err(_) => (InvitationStatus::Pending, None),
+        };
+        self.invitations_store
+            .update_status(
+                invitation.id,
+                InvitationStatus::Accepted,
+                status,
+                accepted_by,
+            )
+            .await?;
+        result
+    }
+
+    async fn claim_invitation(&self, claims: InvitationClaims) -> Result<Invitation> {
+        let id = claims.id;
+        let invitation = self.invitations_store.get(id).await?;
+        // token 中的到期時間與 nonce 必須與建立時保存的相同，簽章正確但內容不符的 token 一樣拒絕
+        if invitation.expires_at != claims.expires_at || invitation.nonce != claims.nonce {
+            return Err(Error::InvitationTokenMismatch { id });
+        }
+        if invitation.status != InvitationStatus::Pending {
+            return Err(Error::InvitationNotPending { id });
+        }
+        if invitation.expires_at <= now_unix_secs() {
+            return Err(Error::InvitationExpired { id });
+        }
+        // 以儲存層的 compare-and-set 修改狀態，同時接受的兩個 request 只有一個會成功
+        self.invitations_store
+            .update_status(
+                id,
+                InvitationStatus::Pending,
+                InvitationStatus::Accepted,
+                None,
+            )
+            .await
+    }
+
+    async fn link_invitation(
+        &self,
+        invitation: &Invitation,
+        user: User,
+        invitation_fa: InvitationForAccept,
+    ) -> Result<User> {
+        // 連結既有帳號時必須提供該帳號的帳密，避免拿到邀請的人接管別人的帳號
+        let user = self
+            .check_login(&invitation_fa.username, &invitation_fa.pwd)
+            .await
+            .ok()
+            .filter(|u| u.id == user.id)
+            .ok_or(Error::LoginFail)?;
+        // 已經是 Admin 的帳號不會因為邀請而被降級
+        if user.role == Role::Admin {
+            return Ok(user);
+        }
+        self.update_user_role(user.id, invitation.role).await
+    }
+}
+
+// 儲存層共用：以分配好的 id 建立 Invitation，新的邀請為 Pending
+impl InvitationForInsert {
+    pub(in crate::model) fn into_invitation(self, id: u64) -> Invitation {
+        Invitation {
+            id,
+            cid: self.cid,
+            email: self.email,
+            role: self.role,
+            status: InvitationStatus::Pending,
+            expires_at: self.expires_at,
+            accepted_by: None,
+            nonce: self.nonce,
+        }
+    }
+}
+
+// 儲存層共用：目前的狀態與 expected 相同時才修改，否則回傳 InvitationNotPending
+impl Invitation {
+    pub(in crate::model) fn transition(
+        &mut self,
+        expected: InvitationStatus,
+        status: InvitationStatus,
+        accepted_by: Option<u64>,
+    ) -> Result<()> {
+        if self.status != expected {
+            return Err(Error::InvitationNotPending { id: self.id });
+        }
+        self.status = status;
+        self.accepted_by = accepted_by;
+        Ok(())
+    }
+}
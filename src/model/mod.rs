@@ -0,0 +1,132 @@
+//! Simplistic Model Layer
+//! (with mock-store layer)
+
+// MVC架構下，有模型（Model）、視圖（View）、控制器（Controller）三層
+// 模型層負責資料的定義與資料庫的互動，包含對資料的CRUD操作。
+// 複雜的邏輯運算、資料處理大多會在這一層完成，controller僅作呼叫內部已經定義好的功能並回傳。
+use crate::config::config;
+use crate::{ctx::Ctx, Error, Result};
+use std::sync::{Arc, RwLock};
+
+mod attachment;
+mod comment;
+mod group;
+mod history;
+mod invitation;
+mod search;
+pub mod store;
+mod ticket;
+mod user;
+mod workflow;
+
+pub use attachment::{Attachment, AttachmentForCreate, AttachmentForUpload};
+pub use comment::{Comment, CommentForCreate, CommentForUpdate};
+pub use group::{Group, GroupForCreate, GroupForUpdate};
+pub use history::{
+    TicketFieldChange, TicketHistory, TicketHistoryAction, TicketHistoryForCreate,
+    TicketHistoryForInsert,
+};
+use search::SearchIndex;
+pub use search::{
+    SearchField, SearchHighlight, TicketSearchHit, TicketSearchPage, TicketSearchParams,
+};
+use store::{
+    AttachmentStore, BlobStore, CommentStore, GroupStore, HistoryStore, InvitationStore,
+    ReplayStore, TicketStore, UserStore,
+};
+use workflow::TicketWorkflow;
+
+pub use invitation::{
+    Invitation, InvitationClaims, InvitationForAccept, InvitationForCreate, InvitationForInsert,
+    InvitationStatus,
+};
+pub use ticket::{
+    SortOrder, Ticket, TicketAssignee, TicketCursor, TicketFilter, TicketForAssign,
+    TicketForCreate, TicketForTransition, TicketForUpdate, TicketPage, TicketPageParams,
+    TicketPriority, TicketSort, TicketSortField, TicketSortKey, TicketStatus, TicketTransitions,
+};
+pub use user::{AuthSource, Role, User, UserFilter, UserForCreate, UserForInsert, UserForUpdate};
+
+// ticket、留言、附件、異動紀錄、使用者、群組與邀請透過 store 中定義的 trait 存取，實際使用的儲存方式（記憶體、資料庫）由設定決定
+// 全文檢索的索引只存在記憶體中，啟動時由儲存層的資料重建
+// 這個物件被作為Application Level State，這種State會被所有users＆sessions共享，通常是一些昂貴且使用頻繁的操作，像是資料庫連接、設定檔
+// 會在app運行時創建，由於全局共享，通常是唯獨的，避免有人誤改導致全部人受影響
+#[derive(Clone)]
+pub struct ModelController {
+    tickets_store: Arc<dyn TicketStore>,
+    comments_store: Arc<dyn CommentStore>,
+    attachments_store: Arc<dyn AttachmentStore>,
+    history_store: Arc<dyn HistoryStore>,
+    blob_store: Arc<dyn BlobStore>,
+    users_store: Arc<dyn UserStore>,
+    groups_store: Arc<dyn GroupStore>,
+    invitations_store: Arc<dyn InvitationStore>,
+    replay_store: Arc<dyn ReplayStore>,
+    // 全文檢索的索引只存在這個 process 的記憶體中，只會看到這個 instance 自己的異動，詳見 search.rs
+    search_index: Arc<RwLock<SearchIndex>>,
+    workflow: Arc<TicketWorkflow>,
+}
+
+impl ModelController {
+    // Rust中，self是參數，代表呼叫此方法的物件本身，而Self則是指此方法實作的那個型別
+    // self小寫，指定的單位較小，是特定物件，Self大寫，指定的是型別，
+    pub async fn new() -> Result<Self> {
+        let stores = store::stores_from_config().await?;
+        let mc = Self {
+            tickets_store: stores.tickets,
+            comments_store: stores.comments,
+            attachments_store: stores.attachments,
+            history_store: stores.history,
+            blob_store: stores.blobs,
+            users_store: stores.users,
+            groups_store: stores.groups,
+            invitations_store: stores.invitations,
+            replay_store: stores.replays,
+            search_index: Arc::default(),
+            workflow: Arc::new(TicketWorkflow::from_config()?),
+        };
+        mc.rebuild_search_index().await?;
+        // 開發用的管理者帳號，讓服務啟動後至少有一個人可以登入並邀請其他人
+        // 帳密是公開的，持久化的儲存層預設不建立，避免正式環境留下預設帳密，詳見 Config::seed_demo_user
+        if config().seed_demo_user && mc.first_user_by_username("demo1").await?.is_none() {
+            mc.create_user(UserForCreate {
+                username: "demo1".to_string(),
+                email: "demo1@example.com".to_string(),
+                pwd: "welcome".to_string(),
+                role: Role::Admin,
+                auth_source: AuthSource::Local,
+            })
+            .await?;
+        }
+        Ok(mc)
+    }
+
+    // 簽章請求的簽章第一次出現時回傳 true，早於 retain_secs 秒之前的紀錄會被清除
+    pub async fn record_signature(
+        &self,
+        signature: &str,
+        now: u64,
+        retain_secs: u64,
+    ) -> Result<bool> {
+        self.replay_store
+            .insert_signature(signature, now, now.saturating_sub(retain_secs))
+            .await
+    }
+
+    // 服務關閉前呼叫，讓儲存層將資料寫入磁碟
+    pub async fn shutdown(&self) -> Result<()> {
+        self.tickets_store.shutdown().await?;
+        self.users_store.shutdown().await
+    }
+}
+
+// 權限檢查，只有 Admin 可以執行的操作呼叫此函數
+fn require_admin(ctx: &Ctx) -> Result<()> {
+    if ctx.is_admin() {
+        Ok(())
+    } else {
+        Err(Error::AuthFailNotAdmin {
+            user_id: ctx.user_id(),
+        })
+    }
+}
@@ -0,0 +1,665 @@
+// ticket 的全文檢索，以倒排索引（inverted index）涵蓋標題、描述與留言
+// 索引只存在記憶體中，服務啟動時從儲存層重建，之後由 ModelController 的每一個異動同步更新
+// 垃圾桶中的 ticket 不在索引中，還原時再重新加入
+//
+// 限制：服務只能以單一 instance 執行。多個 instance 共用同一個資料庫（SQLite、PostgreSQL）時，
+// 每個 instance 的索引只會包含啟動時的資料與自己處理的異動，其他 instance 建立或修改的 ticket 要重新啟動後才查得到
+// 查詢時只從儲存層讀取該頁的 ticket，已經不存在的 ticket 不會出現在結果中，但仍會計入總數
+//
+// 斷詞：英文等以空白分隔的語言以連續的字母、數字為一個詞，轉為小寫
+// 中文、日文、韓文沒有空白分隔，以相鄰的兩個字（bigram）為一個詞，另外也收錄單一的字，讓只有一個字的查詢也能找到
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::ticket::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+use super::{Comment, ModelController, Ticket};
+use crate::ctx::Ctx;
+use crate::{Error, Result};
+
+// BM25 的參數，k1 控制詞頻的飽和程度，b 控制欄位長度的影響
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+// 標題的權重較高，符合標題的 ticket 排在只有描述或留言符合的前面
+const FIELD_WEIGHTS: [f64; 3] = [3.0, 1.0, 1.0];
+// 摘要的長度（字元數），以及第一個符合的位置前面保留的字元數
+const SNIPPET_CHARS: usize = 120;
+const SNIPPET_LEAD_CHARS: usize = 30;
+// 每個 ticket 最多回傳幾則符合的留言摘要
+const MAX_COMMENT_HIGHLIGHTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Title,
+    Description,
+    Comment,
+}
+
+impl SearchField {
+    fn index(self) -> usize {
+        match self {
+            Self::Title => 0,
+            Self::Description => 1,
+            Self::Comment => 2,
+        }
+    }
+}
+
+// 查詢條件，例如 /api/tickets/search?q=登入失敗&limit=20&offset=0
+#[derive(Debug, Deserialize)]
+pub struct TicketSearchParams {
+    pub q: String,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub offset: u64,
+}
+
+// 符合的欄位摘要，符合的詞以 <mark></mark> 標示，其餘的內容都經過 HTML escape，可以直接顯示
+// 符合的是留言時 comment_id 為該則留言的 id
+#[derive(Debug, Serialize)]
+pub struct SearchHighlight {
+    pub field: SearchField,
+    pub comment_id: Option<u64>,
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketSearchHit {
+    pub ticket: Ticket,
+    pub score: f64,
+    pub highlights: Vec<SearchHighlight>,
+}
+
+// 依相關程度由高到低排序，分數相同時依 id 排序
+#[derive(Debug, Serialize)]
+pub struct TicketSearchPage {
+    pub items: Vec<TicketSearchHit>,
+    pub total: u64,
+    pub limit: u64,
+    pub offset: u64,
+    pub next_offset: Option<u64>,
+}
+
+// -- Tokenizer
+struct Token {
+    term: String,
+    // 在原始文字中的位置（byte），標示摘要時使用
+    start: usize,
+    end: usize,
+}
+
+// 中日韓的文字：平假名、片假名、CJK 統一表意文字（含擴充區）、相容表意文字、韓文音節
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FFFF}')
+}
+
+// 將文字切成連續的字母數字或連續的 CJK 文字，其他字元（空白、標點）視為分隔
+// 索引時 with_unigrams 為 true，CJK 的部分同時收錄單一的字；查詢時只在只有一個字時使用單字
+fn tokenize(text: &str, with_unigrams: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if is_cjk(c) {
+            let mut run = vec![(start, c)];
+            while let Some(&(i, c)) = chars.peek().filter(|(_, c)| is_cjk(*c)) {
+                run.push((i, c));
+                chars.next();
+            }
+            push_cjk_tokens(&mut tokens, &run, with_unigrams);
+        } else if c.is_alphanumeric() {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_alphanumeric() && !is_cjk(*c))
+            {
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token {
+                term: text[start..end].to_lowercase(),
+                start,
+                end,
+            });
+        }
+    }
+    tokens
+}
+
+fn push_cjk_tokens(tokens: &mut Vec<Token>, run: &[(usize, char)], with_unigrams: bool) {
+    let token = |chars: &[(usize, char)]| Token {
+        term: chars.iter().map(|(_, c)| c).collect(),
+        start: chars[0].0,
+        end: chars[chars.len() - 1].0 + chars[chars.len() - 1].1.len_utf8(),
+    };
+    if run.len() == 1 || with_unigrams {
+        tokens.extend(run.chunks(1).map(token));
+    }
+    tokens.extend(run.windows(2).map(token));
+}
+
+// 查詢的詞，重複的只計算一次
+fn query_terms(q: &str) -> BTreeSet<String> {
+    tokenize(q, false).into_iter().map(|t| t.term).collect()
+}
+
+// -- Index
+// 一個 ticket 在索引中的內容，保留原始文字以產生摘要
+struct IndexedTicket {
+    version: u64,
+    title: String,
+    description: String,
+    comments: BTreeMap<u64, String>,
+    // 每個詞在各欄位（標題、描述、留言）出現的次數，以及各欄位的詞數
+    term_freqs: HashMap<String, [u32; 3]>,
+    lengths: [u32; 3],
+}
+
+impl IndexedTicket {
+    fn new(ticket: &Ticket, comments: BTreeMap<u64, String>) -> Self {
+        let mut doc = Self {
+            version: ticket.version,
+            title: ticket.title.clone(),
+            description: ticket.description.clone(),
+            comments,
+            term_freqs: HashMap::new(),
+            lengths: [0; 3],
+        };
+        doc.analyze();
+        doc
+    }
+
+    fn analyze(&mut self) {
+        let mut term_freqs: HashMap<String, [u32; 3]> = HashMap::new();
+        let mut lengths = [0; 3];
+        let fields = [
+            (SearchField::Title, &self.title),
+            (SearchField::Description, &self.description),
+        ]
+        .into_iter()
+        .chain(
+            self.comments
+                .values()
+                .map(|body| (SearchField::Comment, body)),
+        );
+        for (field, text) in fields {
+            for token in tokenize(text, true) {
+                term_freqs.entry(token.term).or_default()[field.index()] += 1;
+                lengths[field.index()] += 1;
+            }
+        }
+        self.term_freqs = term_freqs;
+        self.lengths = lengths;
+    }
+}
+
+#[derive(Default)]
+pub(in crate::model) struct SearchIndex {
+    docs: HashMap<u64, IndexedTicket>,
+    // 詞 -> 包含這個詞的 ticket id
+    postings: HashMap<String, BTreeSet<u64>>,
+    // 各欄位的詞數總和，計算平均長度使用
+    total_lengths: [u64; 3],
+    // 移到垃圾桶或永久刪除時的版本，比這個版本舊的修改不會再把 ticket 加回索引
+    removed: HashMap<u64, u64>,
+}
+
+impl SearchIndex {
+    fn insert(&mut self, id: u64, doc: IndexedTicket) {
+        self.remove(id);
+        for term in doc.term_freqs.keys() {
+            self.postings.entry(term.clone()).or_default().insert(id);
+        }
+        for (total, len) in self.total_lengths.iter_mut().zip(doc.lengths) {
+            *total += len as u64;
+        }
+        self.removed.remove(&id);
+        self.docs.insert(id, doc);
+    }
+
+    // 移到垃圾桶或永久刪除，記下當時的版本
+    fn remove_ticket(&mut self, ticket: &Ticket) {
+        self.remove(ticket.id);
+        self.removed.insert(ticket.id, ticket.version);
+    }
+
+    fn remove(&mut self, id: u64) -> Option<IndexedTicket> {
+        let doc = self.docs.remove(&id)?;
+        for term in doc.term_freqs.keys() {
+            if let Some(ids) = self.postings.get_mut(term) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        for (total, len) in self.total_lengths.iter_mut().zip(doc.lengths) {
+            *total -= len as u64;
+        }
+        Some(doc)
+    }
+
+    // 修改 ticket 的內容後更新，留言保持不變
+    // 同時有多個修改時，版本較舊的內容不會覆蓋較新的內容，也不會把已經移除的 ticket 加回來
+    fn put_ticket(&mut self, ticket: &Ticket) {
+        let comments = match self.docs.get(&ticket.id) {
+            Some(doc) if doc.version > ticket.version => return,
+            Some(_) => self.remove(ticket.id).map(|doc| doc.comments),
+            None if self
+                .removed
+                .get(&ticket.id)
+                .is_some_and(|version| *version >= ticket.version) =>
+            {
+                return
+            }
+            None => None,
+        };
+        let doc = IndexedTicket::new(ticket, comments.unwrap_or_default());
+        self.insert(ticket.id, doc);
+    }
+
+    // 留言所屬的 ticket 不在索引中（例如在垃圾桶中）時略過，還原時會重新讀取所有留言
+    fn update_comments(&mut self, ticket_id: u64, f: impl FnOnce(&mut BTreeMap<u64, String>)) {
+        if let Some(mut doc) = self.remove(ticket_id) {
+            f(&mut doc.comments);
+            doc.analyze();
+            self.insert(ticket_id, doc);
+        }
+    }
+
+    // 所有查詢的詞都要出現在 ticket 中（標題、描述或留言），依 BM25F 計算分數
+    fn search(&self, terms: &BTreeSet<String>) -> Vec<(u64, f64)> {
+        let mut postings: Vec<&BTreeSet<u64>> = Vec::with_capacity(terms.len());
+        for term in terms {
+            match self.postings.get(term) {
+                Some(ids) => postings.push(ids),
+                None => return Vec::new(),
+            }
+        }
+        // 從最少的開始交集
+        postings.sort_by_key(|ids| ids.len());
+        let Some((first, rest)) = postings.split_first() else {
+            return Vec::new();
+        };
+
+        let doc_count = self.docs.len() as f64;
+        let avg_lengths = self
+            .total_lengths
+            .map(|total| (total as f64 / doc_count).max(1.0));
+        let mut hits: Vec<(u64, f64)> = first
+            .iter()
+            .filter(|id| rest.iter().all(|ids| ids.contains(id)))
+            .filter_map(|id| {
+                let doc = self.docs.get(id)?;
+                let score = terms
+                    .iter()
+                    .map(|term| {
+                        let df = self.postings[term].len() as f64;
+                        let idf = (1.0 + (doc_count - df + 0.5) / (df + 0.5)).ln();
+                        let freqs = doc.term_freqs[term];
+                        let tf: f64 = (0..3)
+                            .map(|i| {
+                                let norm =
+                                    1.0 - BM25_B + BM25_B * doc.lengths[i] as f64 / avg_lengths[i];
+                                FIELD_WEIGHTS[i] * freqs[i] as f64 / norm
+                            })
+                            .sum();
+                        idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1)
+                    })
+                    .sum();
+                Some((*id, score))
+            })
+            .collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        hits
+    }
+
+    fn highlights(&self, id: u64, terms: &BTreeSet<String>) -> Vec<SearchHighlight> {
+        let Some(doc) = self.docs.get(&id) else {
+            return Vec::new();
+        };
+        let highlight = |field, comment_id, text: &str| {
+            snippet(text, terms).map(|snippet| SearchHighlight {
+                field,
+                comment_id,
+                snippet,
+            })
+        };
+        let fields = [
+            highlight(SearchField::Title, None, &doc.title),
+            highlight(SearchField::Description, None, &doc.description),
+        ];
+        let comments = doc
+            .comments
+            .iter()
+            .filter_map(|(id, body)| highlight(SearchField::Comment, Some(*id), body))
+            .take(MAX_COMMENT_HIGHLIGHTS);
+        fields.into_iter().flatten().chain(comments).collect()
+    }
+}
+
+// -- Highlight
+// 標示文字中符合的詞，相鄰或重疊的部分（例如 CJK 的 bigram）合併成一段
+// 文字過長時只取第一個符合位置附近的片段，前後被截斷時加上 "…"
+fn snippet(text: &str, terms: &BTreeSet<String>) -> Option<String> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for token in tokenize(text, true)
+        .into_iter()
+        .filter(|t| terms.contains(&t.term))
+    {
+        match ranges.last_mut() {
+            Some(last) if token.start <= last.1 => last.1 = last.1.max(token.end),
+            _ => ranges.push((token.start, token.end)),
+        }
+    }
+    let first = ranges.first()?.0;
+
+    let lead_start = text[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_LEAD_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let end = text[lead_start..]
+        .char_indices()
+        .nth(SNIPPET_CHARS)
+        .map_or(text.len(), |(i, _)| lead_start + i);
+
+    let mut snippet = String::new();
+    if lead_start > 0 {
+        snippet.push('…');
+    }
+    let mut pos = lead_start;
+    for (start, range_end) in ranges {
+        if start >= end {
+            break;
+        }
+        let range_end = range_end.min(end);
+        snippet.push_str(&escape_html(&text[pos..start]));
+        snippet.push_str("<mark>");
+        snippet.push_str(&escape_html(&text[start..range_end]));
+        snippet.push_str("</mark>");
+        pos = range_end;
+    }
+    snippet.push_str(&escape_html(&text[pos..end]));
+    if end < text.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// -- ModelController
+impl ModelController {
+    // 與 list_tickets 相同，所有登入的使用者都可以檢索所有 ticket
+    pub async fn search_tickets(&self, params: TicketSearchParams) -> Result<TicketSearchPage> {
+        let terms = query_terms(&params.q);
+        if terms.is_empty() {
+            return Err(Error::SearchFailEmptyQuery);
+        }
+        let limit = params
+            .limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT);
+        let offset = params.offset;
+
+        let (total, page) = {
+            let index = self
+                .search_index
+                .read()
+                .map_err(|_| Error::StoreFailLockPoisoned)?;
+            let hits = index.search(&terms);
+            let page: Vec<_> = hits
+                .iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .map(|&(id, score)| (id, score, index.highlights(id, &terms)))
+                .collect();
+            (hits.len() as u64, page)
+        };
+
+        // 只讀取這一頁的 ticket，其他 instance 已經移到垃圾桶或刪除的 ticket 略過
+        let mut items = Vec::with_capacity(page.len());
+        for (id, score, highlights) in page {
+            match self.tickets_store.get(id).await {
+                Ok(ticket) => items.push(TicketSearchHit {
+                    ticket,
+                    score,
+                    highlights,
+                }),
+                Err(Error::TicketNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let next_offset = offset + limit;
+        Ok(TicketSearchPage {
+            items,
+            total,
+            limit,
+            offset,
+            next_offset: (next_offset < total).then_some(next_offset),
+        })
+    }
+
+    // 服務啟動時呼叫，讀取所有不在垃圾桶中的 ticket 與它們的留言
+    pub(in crate::model) async fn rebuild_search_index(&self) -> Result<()> {
+        let mut index = SearchIndex::default();
+        for ticket in self.tickets_store.list().await? {
+            let comments = self.ticket_comment_bodies(ticket.id).await?;
+            index.insert(ticket.id, IndexedTicket::new(&ticket, comments));
+        }
+        *self
+            .search_index
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)? = index;
+        Ok(())
+    }
+
+    // 建立、修改 ticket 後呼叫
+    pub(in crate::model) fn index_ticket(&self, ticket: &Ticket) -> Result<()> {
+        self.search_index
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?
+            .put_ticket(ticket);
+        Ok(())
+    }
+
+    // 從垃圾桶還原後呼叫，重新讀取留言
+    pub(in crate::model) async fn reindex_ticket(&self, ticket: &Ticket) -> Result<()> {
+        let comments = self.ticket_comment_bodies(ticket.id).await?;
+        self.search_index
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?
+            .insert(ticket.id, IndexedTicket::new(ticket, comments));
+        Ok(())
+    }
+
+    // 移到垃圾桶或永久刪除後呼叫
+    pub(in crate::model) fn unindex_ticket(&self, ticket: &Ticket) -> Result<()> {
+        self.search_index
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?
+            .remove_ticket(ticket);
+        Ok(())
+    }
+
+    // 新增、修改留言後呼叫
+    pub(in crate::model) fn index_comment(&self, comment: &Comment) -> Result<()> {
+        self.search_index
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?
+            .update_comments(comment.ticket_id, |comments| {
+                comments.insert(comment.id, comment.body.clone());
+            });
+        Ok(())
+    }
+
+    // 刪除留言後呼叫
+    pub(in crate::model) fn unindex_comment(&self, comment: &Comment) -> Result<()> {
+        self.search_index
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?
+            .update_comments(comment.ticket_id, |comments| {
+                comments.remove(&comment.id);
+            });
+        Ok(())
+    }
+
+    async fn ticket_comment_bodies(&self, ticket_id: u64) -> Result<BTreeMap<u64, String>> {
+        let comments = self.comments_store.list(ticket_id).await?;
+        Ok(comments.into_iter().map(|c| (c.id, c.body)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TicketForCreate;
+
+    fn terms(text: &str, with_unigrams: bool) -> Vec<String> {
+        tokenize(text, with_unigrams)
+            .into_iter()
+            .map(|t| t.term)
+            .collect()
+    }
+
+    fn highlight(text: &str, q: &str) -> Option<String> {
+        snippet(text, &query_terms(q))
+    }
+
+    #[test]
+    fn tokenize_mixed_latin_cjk() {
+        // 英文轉為小寫，CJK 與英文相連時也會分開，標點符號（含全形）視為分隔
+        assert_eq!(
+            terms("Login登入失敗，ÉCOLE v2", false),
+            ["login", "登入", "入失", "失敗", "école", "v2"]
+        );
+        assert_eq!(
+            terms("登入失敗", true),
+            ["登", "入", "失", "敗", "登入", "入失", "失敗"]
+        );
+        // 日文假名與韓文同樣以 bigram 切分
+        assert_eq!(terms("ログイン", false), ["ログ", "グイ", "イン"]);
+        assert_eq!(terms("로그인", false), ["로그", "그인"]);
+
+        // 位置以 byte 計算，可以直接切出原始文字
+        let text = "Login登入 ok";
+        let tokens = tokenize(text, false);
+        let spans: Vec<&str> = tokens.iter().map(|t| &text[t.start..t.end]).collect();
+        assert_eq!(spans, ["Login", "登入", "ok"]);
+        assert_eq!((tokens[1].start, tokens[1].end), (5, 11));
+    }
+
+    #[test]
+    fn push_cjk_tokens_single_char_and_offsets() {
+        // 只有一個字時即使不收錄單字也會保留，否則單一個字的查詢找不到任何 ticket
+        assert_eq!(terms("登", false), ["登"]);
+        assert_eq!(terms("登 入", false), ["登", "入"]);
+
+        // 位置沿用 run 中的 byte 位置，4 bytes 的擴充區文字也正確計算結尾
+        let mut tokens = Vec::new();
+        push_cjk_tokens(&mut tokens, &[(3, '中'), (6, '𠀀'), (10, '文')], false);
+        let spans: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.start, t.end))
+            .collect();
+        assert_eq!(spans, [("中𠀀", 3, 10), ("𠀀文", 6, 13)]);
+    }
+
+    #[test]
+    fn snippet_marks_cjk_terms() {
+        assert_eq!(
+            highlight("輸入密碼後無法登入", "登入").as_deref(),
+            Some("輸入密碼後無法<mark>登入</mark>")
+        );
+        // 重疊的 bigram 合併成一段
+        assert_eq!(
+            highlight("輸入密碼後無法登入", "無法登入").as_deref(),
+            Some("輸入密碼後<mark>無法登入</mark>")
+        );
+        assert_eq!(
+            highlight("Login cookie expired", "COOKIE").as_deref(),
+            Some("Login <mark>cookie</mark> expired")
+        );
+        assert_eq!(highlight("輸入密碼", "登入"), None);
+    }
+
+    #[test]
+    fn snippet_escapes_html() {
+        // ticket 的內容中出現的 HTML 一律跳脫，只有 <mark> 是摘要加上的標籤
+        assert_eq!(
+            highlight("a < b & <script>cookie</script>", "cookie").as_deref(),
+            Some("a &lt; b &amp; &lt;script&gt;<mark>cookie</mark>&lt;/script&gt;")
+        );
+        assert_eq!(
+            highlight("<b>登入</b> & \"x\" 'y'", "登入").as_deref(),
+            Some("&lt;b&gt;<mark>登入</mark>&lt;/b&gt; &amp; &quot;x&quot; &#39;y&#39;")
+        );
+        assert_eq!(
+            highlight("<script>", "script").as_deref(),
+            Some("&lt;<mark>script</mark>&gt;")
+        );
+    }
+
+    #[test]
+    fn snippet_truncates_on_char_boundaries() {
+        // 前後都被截斷，截斷的位置落在多 byte 的文字中間也不會 panic
+        let text = format!("{}登入{}", "密".repeat(100), "😀".repeat(200));
+        let snippet = highlight(&text, "登入").unwrap();
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        let plain = snippet.replace("<mark>", "").replace("</mark>", "");
+        assert_eq!(plain.chars().count(), SNIPPET_CHARS + 2);
+        assert!(snippet.starts_with(&format!("…{}<mark>登入</mark>", "密".repeat(30))));
+
+        // 符合的詞跨過摘要的結尾時只標示摘要內的部分
+        let text = format!("登入{}登入{}", "密".repeat(117), "密".repeat(10));
+        let snippet = highlight(&text, "登入").unwrap();
+        assert!(snippet.starts_with("<mark>登入</mark>"));
+        assert!(snippet.ends_with("<mark>登</mark>…"));
+    }
+
+    #[test]
+    fn removed_ticket_not_reindexed_by_stale_update() {
+        let mut ticket = TicketForCreate {
+            title: "登入失敗".to_string(),
+            description: String::new(),
+            priority: Default::default(),
+            labels: Default::default(),
+        }
+        .into_ticket(1, 1);
+        let mut index = SearchIndex::default();
+        index.put_ticket(&ticket);
+        let stale = ticket.clone();
+        ticket.version += 1;
+        index.remove_ticket(&ticket);
+
+        // 移除之前的修改較晚才更新索引，不會把 ticket 加回來
+        index.put_ticket(&stale);
+        assert!(index.search(&query_terms("登入")).is_empty());
+
+        // 還原後的版本較新，重新加入索引
+        ticket.version += 1;
+        index.put_ticket(&ticket);
+        assert_eq!(index.search(&query_terms("登入")).len(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+// 附件的內容（blob）與 ticket 的資料分開存放，資料庫中只保存附件的名稱、類型等資訊
+// key 由 ModelController 產生，格式為 "tickets/[ticket-id]/[attachment-id]"，不會包含使用者輸入的內容
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+
+use super::{store_error, BlobStore};
+use crate::{Error, Result};
+
+// 資料只存在記憶體中，服務重新啟動後就會消失，適合開發與測試使用
+#[derive(Default)]
+pub struct MemBlobStore {
+    blobs: RwLock<BTreeMap<String, Vec<u8>>>,
+}
+
+#[async_trait]
+impl BlobStore for MemBlobStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let mut blobs = self
+            .blobs
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?;
+        blobs.insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let blobs = self
+            .blobs
+            .read()
+            .map_err(|_| Error::StoreFailLockPoisoned)?;
+        Ok(blobs.get(key).cloned())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let mut blobs = self
+            .blobs
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?;
+        blobs.remove(key);
+        Ok(())
+    }
+
+    async fn delete_dir(&self, dir: &str) -> Result<()> {
+        let mut blobs = self
+            .blobs
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)?;
+        let prefix = format!("{dir}/");
+        blobs.retain(|key, _| !key.starts_with(&prefix));
+        Ok(())
+    }
+}
+
+// 每個 blob 存成 root 底下的一個檔案，key 中的 "/" 對應到子目錄
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).map_err(store_error)?;
+        Ok(Self { root })
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    // 先寫入暫存檔再 rename，中途當機也不會留下只寫了一半的檔案
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(store_error)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, data)
+            .await
+            .map_err(store_error)?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.root.join(key)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.root.join(key)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(store_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_dir(&self, dir: &str) -> Result<()> {
+        match tokio::fs::remove_dir_all(self.root.join(dir)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(store_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
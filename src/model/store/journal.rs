@@ -0,0 +1,375 @@
+// 記憶體儲存層的持久化：每一次異動先寫入 append-only 的 journal，再定期將完整資料寫成 snapshot
+// 啟動時先讀取 snapshot，再依序重播 snapshot 之後的 journal，就可以還原到關閉前的狀態
+// 寫 snapshot 時先寫到暫存檔再 rename，rename 是 atomic 的，不會留下寫到一半的 snapshot
+// snapshot 在背景寫入：先將目前的 journal 換成 pending，之後的異動寫到新的 journal，snapshot 完成後才刪除 pending
+// 當機時 pending 可能還在，啟動時依序重播 pending 與 journal，重播的是異動後的結果，重複套用不會改變資料
+//
+// 儲存層在持有資料的 lock 時只把異動排入佇列（append），順序與記憶體中的異動一致
+// 放開 lock 之後再等待寫入磁碟（Commit::wait），實際的寫檔與 sync_data 在 spawn_blocking 中執行
+// 同時等待的多筆異動由其中一個一次寫入、sync 一次（group commit），不會在寫檔的期間擋住其他的讀寫
+// 寫入失敗時截回最後一筆完整異動的位置，並停用 journal（poisoned），記憶體中的資料已經與檔案不一致，重新啟動後才能恢復
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::store_error;
+use crate::{Error, Result};
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.json.tmp";
+const JOURNAL_FILE: &str = "journal.jsonl";
+const PENDING_FILE: &str = "journal.pending.jsonl";
+
+// 可以複製，儲存層與背景的 snapshot 共用同一份 journal
+#[derive(Clone)]
+pub struct Journal {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    dir: PathBuf,
+    // 累積多少筆 journal 之後寫一次 snapshot
+    snapshot_every: u64,
+    queue: Mutex<Queue>,
+    writer: Mutex<Writer>,
+    poisoned: AtomicBool,
+    // 背景的 snapshot 還沒完成時不會開始下一次
+    snapshot_running: AtomicBool,
+}
+
+// 已經排入、還沒寫入檔案的異動，每一筆依序分配一個序號
+struct Queue {
+    ops: Vec<(u64, Op)>,
+    next_seq: u64,
+    entries_since_snapshot: u64,
+}
+
+enum Op {
+    // 序列化後的一行（包含換行）
+    Append(Vec<u8>),
+    // 換 journal，結果交給等待中的 SnapshotJob
+    Rotate(SyncSender<Result<()>>),
+}
+
+struct Writer {
+    file: File,
+    // 檔案中完整的異動所佔的長度，寫入失敗時截回這個位置
+    len: u64,
+    // 已經寫入並 sync 的最後一個序號
+    synced: u64,
+}
+
+// append 回傳的憑證，wait 完成後這筆異動才確定寫到磁碟
+#[must_use]
+pub struct Commit {
+    journal: Journal,
+    seq: u64,
+}
+
+// 換 journal 之後的 snapshot 工作，寫入時不需要持有資料的 lock
+pub struct SnapshotJob {
+    journal: Journal,
+    seq: u64,
+    rotated: Receiver<Result<()>>,
+}
+
+// 讀取 snapshot 與 journal 後的結果，交給儲存層自行重播
+pub struct Recovered<S, E> {
+    pub snapshot: Option<S>,
+    pub entries: Vec<E>,
+}
+
+impl Journal {
+    // 開啟（或建立）目錄底下的 snapshot 與 journal
+    pub fn open<S, E>(dir: impl AsRef<Path>, snapshot_every: u64) -> Result<(Self, Recovered<S, E>)>
+    where
+        S: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(store_error)?;
+
+        let snapshot = match fs::read(dir.join(SNAPSHOT_FILE)) {
+            Ok(bytes) => Some(serde_json::from_slice(&bytes).map_err(store_error)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(store_error(e)),
+        };
+
+        // 上一次的 snapshot 還沒完成就結束時，pending 中的異動還沒有包含在 snapshot 裡，要先重播
+        let mut entries = read_entries(&dir.join(PENDING_FILE))?.entries;
+        let journal_path = dir.join(JOURNAL_FILE);
+        let JournalFile {
+            entries: journal_entries,
+            bytes,
+            valid_len,
+        } = read_entries(&journal_path)?;
+        entries.extend(journal_entries);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal_path)
+            .map_err(store_error)?;
+        // 之後的異動會接在檔案的最後面，必須先截掉不完整的內容，並補上最後一筆缺少的換行
+        // 否則新的異動會與不完整的內容接在同一行，下次啟動時無法讀取
+        let mut len = valid_len as u64;
+        if valid_len < bytes.len() {
+            file.set_len(len).map_err(store_error)?;
+        }
+        if valid_len > 0 && bytes[valid_len - 1] != b'\n' {
+            file.write_all(b"\n").map_err(store_error)?;
+            len += 1;
+        }
+        let journal = Journal {
+            shared: Arc::new(Shared {
+                dir,
+                snapshot_every,
+                queue: Mutex::new(Queue {
+                    ops: Vec::new(),
+                    next_seq: 1,
+                    entries_since_snapshot: entries.len() as u64,
+                }),
+                writer: Mutex::new(Writer {
+                    file,
+                    len,
+                    synced: 0,
+                }),
+                poisoned: AtomicBool::new(false),
+                snapshot_running: AtomicBool::new(false),
+            }),
+        };
+        Ok((journal, Recovered { snapshot, entries }))
+    }
+
+    // 寫入失敗過之後不再接受新的異動，儲存層也不再使用記憶體中的資料
+    pub fn is_poisoned(&self) -> bool {
+        self.shared.poisoned.load(Ordering::Acquire)
+    }
+
+    // 將一筆異動排入佇列，必須在持有資料的 lock 時、修改資料之前呼叫
+    // 序列化失敗或 journal 已經停用時回傳錯誤，記憶體中的資料不會改變
+    pub fn append(&self, entry: &impl Serialize) -> Result<Commit> {
+        if self.is_poisoned() {
+            return Err(Error::StoreFailJournalPoisoned);
+        }
+        let mut line = serde_json::to_vec(entry).map_err(store_error)?;
+        line.push(b'\n');
+        let mut queue = self.queue()?;
+        queue.entries_since_snapshot += 1;
+        let seq = queue.push(Op::Append(line));
+        Ok(Commit {
+            journal: self.clone(),
+            seq,
+        })
+    }
+
+    // 是否已經累積足夠的 journal，該寫 snapshot 了
+    pub fn should_snapshot(&self) -> Result<bool> {
+        Ok(self.queue()?.entries_since_snapshot >= self.shared.snapshot_every)
+    }
+
+    // 開始一次 snapshot：排入換 journal 的動作，在它之前的異動寫到 pending，之後的異動寫到新的 journal
+    // 必須在持有資料的 lock 時呼叫，並在同一個 lock 中複製資料交給 SnapshotJob，snapshot 才會剛好包含 pending 中的異動
+    // 上一次的 snapshot 還在背景執行時回傳 None
+    pub fn begin_snapshot(&self) -> Result<Option<SnapshotJob>> {
+        if self.shared.snapshot_running.swap(true, Ordering::AcqRel) {
+            return Ok(None);
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        let mut job = SnapshotJob {
+            journal: self.clone(),
+            seq: 0,
+            rotated: rx,
+        };
+        let mut queue = self.queue()?;
+        queue.entries_since_snapshot = 0;
+        job.seq = queue.push(Op::Rotate(tx));
+        Ok(Some(job))
+    }
+
+    fn queue(&self) -> Result<MutexGuard<'_, Queue>> {
+        self.shared
+            .queue
+            .lock()
+            .map_err(|_| Error::StoreFailLockPoisoned)
+    }
+
+    // 將序號 seq 以前（包含）的異動寫入檔案並 sync，會執行檔案 I/O，只能在 spawn_blocking 中呼叫
+    // 已經由其他的呼叫一起寫入時直接回傳
+    fn sync_through(&self, seq: u64) -> Result<()> {
+        let mut writer = self
+            .shared
+            .writer
+            .lock()
+            .map_err(|_| Error::StoreFailLockPoisoned)?;
+        if writer.synced >= seq {
+            return Ok(());
+        }
+        if self.is_poisoned() {
+            return Err(Error::StoreFailJournalPoisoned);
+        }
+        let ops = std::mem::take(&mut self.queue()?.ops);
+        let Some(last) = ops.last().map(|(seq, _)| *seq) else {
+            return Ok(());
+        };
+        let mut batch = Vec::new();
+        for (_, op) in ops {
+            match op {
+                Op::Append(line) => batch.extend_from_slice(&line),
+                Op::Rotate(tx) => {
+                    self.write_batch(&mut writer, &batch)?;
+                    batch.clear();
+                    // 換 journal 失敗時異動仍然寫在原本的檔案中，只影響這一次 snapshot
+                    let _ = tx.send(writer.rotate(&self.shared.dir));
+                }
+            }
+        }
+        self.write_batch(&mut writer, &batch)?;
+        writer.synced = last;
+        Ok(())
+    }
+
+    // 寫入失敗時檔案中可能留下寫到一半的一行，截回最後一筆完整異動的位置，並停用 journal
+    // 這一批與之後排入的異動都不會寫入，等待的 request 都會收到錯誤
+    fn write_batch(&self, writer: &mut Writer, batch: &[u8]) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let result = writer
+            .file
+            .write_all(batch)
+            .and_then(|_| writer.file.sync_data());
+        if let Err(e) = result {
+            self.shared.poisoned.store(true, Ordering::Release);
+            if let Err(e) = writer.file.set_len(writer.len) {
+                println!("->> {:<12} - journal - truncate failed: {e:?}", "STORE");
+            }
+            return Err(store_error(e));
+        }
+        writer.len += batch.len() as u64;
+        Ok(())
+    }
+}
+
+impl Queue {
+    fn push(&mut self, op: Op) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.ops.push((seq, op));
+        seq
+    }
+}
+
+impl Writer {
+    // 將目前的 journal 換成 pending，之後的異動寫到新的 journal
+    fn rotate(&mut self, dir: &Path) -> Result<()> {
+        let journal_path = dir.join(JOURNAL_FILE);
+        let pending_path = dir.join(PENDING_FILE);
+        // 上一次的 snapshot 失敗時 pending 還在，將目前的 journal 接在它後面，兩者都會包含在這次的 snapshot 中
+        if pending_path.exists() {
+            let bytes = fs::read(&journal_path).map_err(store_error)?;
+            let mut pending = OpenOptions::new()
+                .append(true)
+                .open(&pending_path)
+                .map_err(store_error)?;
+            pending.write_all(&bytes).map_err(store_error)?;
+            pending.sync_all().map_err(store_error)?;
+            fs::remove_file(&journal_path).map_err(store_error)?;
+        } else {
+            fs::rename(&journal_path, &pending_path).map_err(store_error)?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal_path)
+            .map_err(store_error)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Commit {
+    // 放開資料的 lock 之後呼叫，在 spawn_blocking 中寫入檔案，確認寫到磁碟後才回傳
+    // 服務或機器當機都不會遺失已經回應的異動
+    pub async fn wait(self) -> Result<()> {
+        tokio::task::spawn_blocking(move || self.journal.sync_through(self.seq))
+            .await
+            .map_err(store_error)?
+    }
+}
+
+impl SnapshotJob {
+    // 等換 journal 之前的異動都寫入、換好 journal 之後，寫入完整的 snapshot
+    // 成功後 pending 中的內容都已經包含在 snapshot 裡，可以刪除
+    // 失敗時保留 pending，下一次 snapshot 或重新啟動時仍然可以還原
+    // 會執行檔案 I/O，只能在 spawn_blocking 中呼叫
+    pub fn write(self, state: &impl Serialize) -> Result<()> {
+        self.journal.sync_through(self.seq)?;
+        self.rotated.try_recv().map_err(store_error)??;
+        let dir = &self.journal.shared.dir;
+        let tmp_path = dir.join(SNAPSHOT_TMP_FILE);
+        let mut tmp = std::io::BufWriter::new(File::create(&tmp_path).map_err(store_error)?);
+        serde_json::to_writer(&mut tmp, state).map_err(store_error)?;
+        let tmp = tmp.into_inner().map_err(store_error)?;
+        tmp.sync_all().map_err(store_error)?;
+        fs::rename(&tmp_path, dir.join(SNAPSHOT_FILE)).map_err(store_error)?;
+        fs::remove_file(dir.join(PENDING_FILE)).map_err(store_error)?;
+        Ok(())
+    }
+}
+
+// 不論 snapshot 成功、失敗，或是換 journal 時就失敗，都要讓下一次 snapshot 可以開始
+impl Drop for SnapshotJob {
+    fn drop(&mut self) {
+        self.journal
+            .shared
+            .snapshot_running
+            .store(false, Ordering::Release);
+    }
+}
+
+struct JournalFile<E> {
+    entries: Vec<E>,
+    bytes: Vec<u8>,
+    // 完整的異動所佔的長度，不完整的最後一行不算在內
+    valid_len: usize,
+}
+
+// 讀取 journal 檔案中的所有異動，檔案不存在時視為空的
+fn read_entries<E: DeserializeOwned>(path: &Path) -> Result<JournalFile<E>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(store_error(e)),
+    };
+    let mut valid_len = 0;
+    let mut entries = Vec::new();
+    let mut lines = bytes.split_inclusive(|b| *b == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        match serde_json::from_slice(line) {
+            Ok(entry) => {
+                entries.push(entry);
+                valid_len += line.len();
+            }
+            // 寫入途中當機時，最後一行可能不完整，這筆異動本來就沒有成功回應，直接忽略
+            Err(_) if lines.peek().is_none() => {
+                println!(
+                    "->> {:<12} - journal - ignore incomplete last entry",
+                    "STORE"
+                )
+            }
+            Err(e) => return Err(store_error(e)),
+        }
+    }
+    Ok(JournalFile {
+        entries,
+        bytes,
+        valid_len,
+    })
+}
@@ -0,0 +1,991 @@
+// 使用嵌入式的 key-value 資料庫（redb）儲存 ticket 與使用者，整個服務只需要一個執行檔加上一個資料檔
+// 適合部署在不方便執行 SQL 資料庫的邊緣設備上
+// redb 的每個寫入 transaction 在 commit 時都會寫入磁碟，中途當機也不會留下寫到一半的資料
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use redb::{Database, ReadableTable, Table, TableDefinition, WriteTransaction};
+use serde::{Deserialize, Serialize};
+
+use super::record::{InvitationRecord, UserRecord};
+use super::{
+    store_error, AttachmentStore, CommentStore, GroupStore, HistoryStore, InvitationStore,
+    ReplayStore, TicketStore, UserStore,
+};
+use crate::model::{
+    Attachment, AttachmentForCreate, AuthSource, Comment, CommentForCreate, CommentForUpdate,
+    Group, GroupForCreate, GroupForUpdate, Invitation, InvitationForInsert, InvitationStatus, Role,
+    Ticket, TicketForCreate, TicketForUpdate, TicketHistory, TicketHistoryForCreate,
+    TicketHistoryForInsert, User, UserForInsert, UserForUpdate,
+};
+use crate::{Error, Result};
+
+// 資料以 JSON 序列化後儲存，key 為 id
+const TICKETS: TableDefinition<u64, &[u8]> = TableDefinition::new("tickets");
+const USERS: TableDefinition<u64, &[u8]> = TableDefinition::new("users");
+const GROUPS: TableDefinition<u64, &[u8]> = TableDefinition::new("groups");
+const INVITATIONS: TableDefinition<u64, &[u8]> = TableDefinition::new("invitations");
+// 留言與附件資訊的 key 為 (ticket_id, id)，同一個 ticket 的資料會排在一起，列出或一併刪除時只需要掃描一段範圍
+// 以 id 查詢時，先透過 XXX_TICKETS 找出所屬的 ticket_id
+const COMMENTS: TableDefinition<(u64, u64), &[u8]> = TableDefinition::new("comments");
+const COMMENT_TICKETS: TableDefinition<u64, u64> = TableDefinition::new("comment_tickets");
+const ATTACHMENTS: TableDefinition<(u64, u64), &[u8]> = TableDefinition::new("attachments");
+const ATTACHMENT_TICKETS: TableDefinition<u64, u64> = TableDefinition::new("attachment_tickets");
+// 異動紀錄的 key 同樣為 (ticket_id, id)，只會以 ticket_id 列出，不需要反查的索引
+const HISTORY: TableDefinition<(u64, u64), &[u8]> = TableDefinition::new("history");
+// 使用者的索引：username 對應到 id；email 可能重複，key 為 (email, id)，以範圍查詢找出 id 最小的一個
+const USERNAMES: TableDefinition<&str, u64> = TableDefinition::new("usernames");
+const USER_EMAILS: TableDefinition<(&str, u64), ()> = TableDefinition::new("user_emails");
+// 簽章請求已經使用過的簽章，value 為第一次出現的時間
+const SIGNATURES: TableDefinition<&str, u64> = TableDefinition::new("signatures");
+// 記錄下一個可以使用的 id，與資料在同一個 transaction 中更新，確保 id 分配是 atomic 的
+const SEQUENCES: TableDefinition<&str, u64> = TableDefinition::new("sequences");
+
+// ticket、使用者、群組與邀請共用同一個資料檔，redb 同一個檔案只能被開啟一次，所以所有的 trait 都由 KvStore 實作
+#[derive(Clone)]
+pub struct KvStore {
+    db: Arc<Database>,
+}
+
+impl KvStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = Database::create(path).map_err(store_error)?;
+        // 先建立所有的 table，之後的讀取 transaction 才不會因為 table 不存在而失敗
+        let tx = db.begin_write().map_err(store_error)?;
+        tx.open_table(TICKETS).map_err(store_error)?;
+        tx.open_table(USERS).map_err(store_error)?;
+        tx.open_table(USERNAMES).map_err(store_error)?;
+        tx.open_table(USER_EMAILS).map_err(store_error)?;
+        tx.open_table(GROUPS).map_err(store_error)?;
+        tx.open_table(INVITATIONS).map_err(store_error)?;
+        tx.open_table(COMMENTS).map_err(store_error)?;
+        tx.open_table(COMMENT_TICKETS).map_err(store_error)?;
+        tx.open_table(ATTACHMENTS).map_err(store_error)?;
+        tx.open_table(ATTACHMENT_TICKETS).map_err(store_error)?;
+        tx.open_table(HISTORY).map_err(store_error)?;
+        tx.open_table(SIGNATURES).map_err(store_error)?;
+        tx.open_table(SEQUENCES).map_err(store_error)?;
+        index_existing_users(&tx)?;
+        tx.commit().map_err(store_error)?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    // redb 的操作都是同步（blocking）的，交給 tokio 的 blocking thread 執行，避免卡住 async runtime
+    async fn blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .map_err(store_error)?
+    }
+}
+
+// 在同一個寫入 transaction 中取得並遞增序號，id 從 1 開始
+fn next_id(sequences: &mut Table<&str, u64>, name: &str) -> Result<u64> {
+    let id = sequences
+        .get(name)
+        .map_err(store_error)?
+        .map(|v| v.value())
+        .unwrap_or(1);
+    sequences.insert(name, id + 1).map_err(store_error)?;
+    Ok(id)
+}
+
+fn to_bytes(value: &impl Serialize) -> Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(store_error)
+}
+
+fn from_bytes<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(store_error)
+}
+
+// -- Tickets
+#[async_trait]
+impl TicketStore for KvStore {
+    async fn create(
+        &self,
+        cid: u64,
+        ticket_fc: TicketForCreate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let ticket = {
+                let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+                let mut tickets = tx.open_table(TICKETS).map_err(store_error)?;
+                let ticket = ticket_fc.into_ticket(next_id(&mut sequences, "tickets")?, cid);
+                tickets
+                    .insert(ticket.id, to_bytes(&ticket)?.as_slice())
+                    .map_err(store_error)?;
+                ticket
+            };
+            insert_history(&tx, history_fi.for_ticket(ticket.id))?;
+            tx.commit().map_err(store_error)?;
+            Ok(ticket)
+        })
+        .await
+    }
+
+    async fn get(&self, id: u64) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+            read_ticket(&tickets, id)?
+                .filter(|t| !t.is_deleted())
+                .ok_or(Error::TicketNotFound { id })
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<Ticket>> {
+        self.blocking(|db| {
+            let tickets = list_tickets(db)?;
+            Ok(tickets.into_iter().filter(|t| !t.is_deleted()).collect())
+        })
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: u64,
+        updated_by: u64,
+        expected_version: Option<u64>,
+        ticket_fu: TicketForUpdate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let (ticket, ()) = modify_ticket(&tx, id, false, |ticket| {
+                ticket.check_version(expected_version)?;
+                ticket_fu.apply_to(ticket, updated_by);
+                Ok(())
+            })?;
+            insert_history(&tx, history_fi.for_ticket(id))?;
+            tx.commit().map_err(store_error)?;
+            Ok(ticket)
+        })
+        .await
+    }
+
+    async fn delete(
+        &self,
+        id: u64,
+        deleted_by: u64,
+        expected_version: Option<u64>,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let (ticket, before) = modify_ticket(&tx, id, false, |ticket| {
+                ticket.check_version(expected_version)?;
+                let before = ticket.clone();
+                ticket.mark_deleted(deleted_by);
+                Ok(before)
+            })?;
+            let history_fi = history_fi.with_lifecycle(&before, Some(&ticket));
+            insert_history(&tx, history_fi.for_ticket(id))?;
+            tx.commit().map_err(store_error)?;
+            Ok(ticket)
+        })
+        .await
+    }
+
+    async fn list_deleted(&self) -> Result<Vec<Ticket>> {
+        self.blocking(|db| {
+            let tickets = list_tickets(db)?;
+            Ok(tickets.into_iter().filter(|t| t.is_deleted()).collect())
+        })
+        .await
+    }
+
+    async fn get_deleted(&self, id: u64) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+            read_ticket(&tickets, id)?
+                .filter(|t| t.is_deleted())
+                .ok_or(Error::TicketNotFound { id })
+        })
+        .await
+    }
+
+    async fn restore(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let (ticket, before) = modify_ticket(&tx, id, true, |ticket| {
+                let before = ticket.clone();
+                ticket.mark_restored();
+                Ok(before)
+            })?;
+            let history_fi = history_fi.with_lifecycle(&before, Some(&ticket));
+            insert_history(&tx, history_fi.for_ticket(id))?;
+            tx.commit().map_err(store_error)?;
+            Ok(ticket)
+        })
+        .await
+    }
+
+    async fn purge(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let ticket = {
+                let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+                read_ticket(&tickets, id)?
+                    .filter(|t| t.is_deleted())
+                    .ok_or(Error::TicketNotFound { id })?
+            };
+            remove_ticket(&tx, id)?;
+            insert_history(&tx, history_fi.with_lifecycle(&ticket, None).for_ticket(id))?;
+            tx.commit().map_err(store_error)?;
+            Ok(ticket)
+        })
+        .await
+    }
+
+    // 在同一個 transaction 中找出並刪除所有過期的 ticket
+    async fn purge_deleted_before(
+        &self,
+        cutoff: u64,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Vec<Ticket>> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let mut expired = Vec::new();
+            {
+                let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+                for entry in tickets.iter().map_err(store_error)? {
+                    let (_id, bytes) = entry.map_err(store_error)?;
+                    let ticket: Ticket = from_bytes(bytes.value())?;
+                    if ticket.deleted_at.is_some_and(|at| at <= cutoff) {
+                        expired.push(ticket);
+                    }
+                }
+            }
+            for ticket in &expired {
+                remove_ticket(&tx, ticket.id)?;
+                let history_fi = history_fi.clone().with_lifecycle(ticket, None);
+                insert_history(&tx, history_fi.for_ticket(ticket.id))?;
+            }
+            tx.commit().map_err(store_error)?;
+            Ok(expired)
+        })
+        .await
+    }
+}
+
+// 在 ticket 異動的寫入 transaction 中新增異動紀錄，沒有需要記錄的內容（None）時略過
+fn insert_history(tx: &WriteTransaction, history_fc: Option<TicketHistoryForCreate>) -> Result<()> {
+    let Some(history_fc) = history_fc else {
+        return Ok(());
+    };
+    let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+    let mut table = tx.open_table(HISTORY).map_err(store_error)?;
+    let history = history_fc.into_history(next_id(&mut sequences, "history")?);
+    table
+        .insert(
+            (history.ticket_id, history.id),
+            to_bytes(&history)?.as_slice(),
+        )
+        .map_err(store_error)?;
+    Ok(())
+}
+
+// 先讀出並轉換成 Ticket，讓讀取的借用結束後才能寫入
+fn read_ticket(
+    tickets: &impl ReadableTable<u64, &'static [u8]>,
+    id: u64,
+) -> Result<Option<Ticket>> {
+    match tickets.get(id).map_err(store_error)? {
+        Some(bytes) => Ok(Some(from_bytes(bytes.value())?)),
+        None => Ok(None),
+    }
+}
+
+// 包含垃圾桶中的 ticket
+fn list_tickets(db: &Database) -> Result<Vec<Ticket>> {
+    let tx = db.begin_read().map_err(store_error)?;
+    let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+    let mut result = Vec::new();
+    for entry in tickets.iter().map_err(store_error)? {
+        let (_id, bytes) = entry.map_err(store_error)?;
+        result.push(from_bytes(bytes.value())?);
+    }
+    Ok(result)
+}
+
+// 讀出 ticket 並修改後寫回，in_trash 表示要處理的是垃圾桶中的 ticket，不符合時視為找不到
+// f 回傳錯誤時不會寫回，呼叫端也不會 commit，成功時一併回傳 f 的結果（例如修改前的 ticket）
+fn modify_ticket<T>(
+    tx: &WriteTransaction,
+    id: u64,
+    in_trash: bool,
+    f: impl FnOnce(&mut Ticket) -> Result<T>,
+) -> Result<(Ticket, T)> {
+    let mut tickets = tx.open_table(TICKETS).map_err(store_error)?;
+    let mut ticket = read_ticket(&tickets, id)?
+        .filter(|t| t.is_deleted() == in_trash)
+        .ok_or(Error::TicketNotFound { id })?;
+    let value = f(&mut ticket)?;
+    tickets
+        .insert(ticket.id, to_bytes(&ticket)?.as_slice())
+        .map_err(store_error)?;
+    Ok((ticket, value))
+}
+
+// 永久刪除 ticket，並在同一個 transaction 中刪除底下的留言與附件資訊
+fn remove_ticket(tx: &WriteTransaction, id: u64) -> Result<()> {
+    tx.open_table(TICKETS)
+        .map_err(store_error)?
+        .remove(id)
+        .map_err(store_error)?;
+    remove_by_ticket(
+        &mut tx.open_table(COMMENTS).map_err(store_error)?,
+        &mut tx.open_table(COMMENT_TICKETS).map_err(store_error)?,
+        id,
+    )?;
+    remove_by_ticket(
+        &mut tx.open_table(ATTACHMENTS).map_err(store_error)?,
+        &mut tx.open_table(ATTACHMENT_TICKETS).map_err(store_error)?,
+        id,
+    )
+}
+
+// 刪除某個 ticket 底下的所有資料（留言、附件資訊）與對應的 ticket_id 索引
+fn remove_by_ticket(
+    table: &mut Table<(u64, u64), &[u8]>,
+    ticket_index: &mut Table<u64, u64>,
+    ticket_id: u64,
+) -> Result<()> {
+    let mut keys = Vec::new();
+    let range = table
+        .range((ticket_id, 0)..=(ticket_id, u64::MAX))
+        .map_err(store_error)?;
+    for entry in range {
+        let (key, _bytes) = entry.map_err(store_error)?;
+        keys.push(key.value());
+    }
+    for key in keys {
+        table.remove(key).map_err(store_error)?;
+        ticket_index.remove(key.1).map_err(store_error)?;
+    }
+    Ok(())
+}
+
+// -- Comments
+// 透過 COMMENT_TICKETS 找出留言所屬的 ticket，組成 COMMENTS 的 key
+fn comment_key(comment_tickets: &impl ReadableTable<u64, u64>, id: u64) -> Result<(u64, u64)> {
+    let ticket_id = comment_tickets.get(id).map_err(store_error)?;
+    match ticket_id {
+        Some(ticket_id) => Ok((ticket_id.value(), id)),
+        None => Err(Error::CommentNotFound { id }),
+    }
+}
+
+#[async_trait]
+impl CommentStore for KvStore {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        author_id: u64,
+        comment_fc: CommentForCreate,
+    ) -> Result<Comment> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let comment = {
+                // 在同一個 transaction 中確認 ticket 仍然存在且不在垃圾桶中
+                let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+                if read_ticket(&tickets, ticket_id)?.is_none_or(|t| t.is_deleted()) {
+                    return Err(Error::TicketNotFound { id: ticket_id });
+                }
+                let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+                let mut comments = tx.open_table(COMMENTS).map_err(store_error)?;
+                let mut comment_tickets = tx.open_table(COMMENT_TICKETS).map_err(store_error)?;
+                let id = next_id(&mut sequences, "comments")?;
+                let comment = comment_fc.into_comment(id, ticket_id, author_id);
+                comments
+                    .insert((ticket_id, id), to_bytes(&comment)?.as_slice())
+                    .map_err(store_error)?;
+                comment_tickets.insert(id, ticket_id).map_err(store_error)?;
+                comment
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(comment)
+        })
+        .await
+    }
+
+    async fn get(&self, id: u64) -> Result<Comment> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let comment_tickets = tx.open_table(COMMENT_TICKETS).map_err(store_error)?;
+            let comments = tx.open_table(COMMENTS).map_err(store_error)?;
+            let key = comment_key(&comment_tickets, id)?;
+            let comment = comments.get(key).map_err(store_error)?;
+            match comment {
+                Some(bytes) => from_bytes(bytes.value()),
+                None => Err(Error::CommentNotFound { id }),
+            }
+        })
+        .await
+    }
+
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Comment>> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let comments = tx.open_table(COMMENTS).map_err(store_error)?;
+            let mut result = Vec::new();
+            let range = comments
+                .range((ticket_id, 0)..=(ticket_id, u64::MAX))
+                .map_err(store_error)?;
+            for entry in range {
+                let (_key, bytes) = entry.map_err(store_error)?;
+                result.push(from_bytes(bytes.value())?);
+            }
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn update(&self, id: u64, comment_fu: CommentForUpdate) -> Result<Comment> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let comment = {
+                let comment_tickets = tx.open_table(COMMENT_TICKETS).map_err(store_error)?;
+                let mut comments = tx.open_table(COMMENTS).map_err(store_error)?;
+                let key = comment_key(&comment_tickets, id)?;
+                let existing: Option<Comment> = match comments.get(key).map_err(store_error)? {
+                    Some(bytes) => Some(from_bytes(bytes.value())?),
+                    None => None,
+                };
+                let mut comment = existing.ok_or(Error::CommentNotFound { id })?;
+                comment_fu.apply_to(&mut comment);
+                comments
+                    .insert(key, to_bytes(&comment)?.as_slice())
+                    .map_err(store_error)?;
+                comment
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(comment)
+        })
+        .await
+    }
+
+    async fn delete(&self, id: u64) -> Result<Comment> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let comment = {
+                let mut comment_tickets = tx.open_table(COMMENT_TICKETS).map_err(store_error)?;
+                let mut comments = tx.open_table(COMMENTS).map_err(store_error)?;
+                let key = comment_key(&comment_tickets, id)?;
+                comment_tickets.remove(id).map_err(store_error)?;
+                let removed = comments.remove(key).map_err(store_error)?;
+                match removed {
+                    Some(bytes) => from_bytes::<Comment>(bytes.value())?,
+                    None => return Err(Error::CommentNotFound { id }),
+                }
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(comment)
+        })
+        .await
+    }
+}
+
+// -- Attachments
+fn attachment_key(
+    attachment_tickets: &impl ReadableTable<u64, u64>,
+    id: u64,
+) -> Result<(u64, u64)> {
+    let ticket_id = attachment_tickets.get(id).map_err(store_error)?;
+    match ticket_id {
+        Some(ticket_id) => Ok((ticket_id.value(), id)),
+        None => Err(Error::AttachmentNotFound { id }),
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for KvStore {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        uploader_id: u64,
+        attachment_fc: AttachmentForCreate,
+    ) -> Result<Attachment> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let attachment = {
+                let tickets = tx.open_table(TICKETS).map_err(store_error)?;
+                if read_ticket(&tickets, ticket_id)?.is_none_or(|t| t.is_deleted()) {
+                    return Err(Error::TicketNotFound { id: ticket_id });
+                }
+                let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+                let mut attachments = tx.open_table(ATTACHMENTS).map_err(store_error)?;
+                let mut attachment_tickets =
+                    tx.open_table(ATTACHMENT_TICKETS).map_err(store_error)?;
+                let id = next_id(&mut sequences, "attachments")?;
+                let attachment = attachment_fc.into_attachment(id, ticket_id, uploader_id);
+                attachments
+                    .insert((ticket_id, id), to_bytes(&attachment)?.as_slice())
+                    .map_err(store_error)?;
+                attachment_tickets
+                    .insert(id, ticket_id)
+                    .map_err(store_error)?;
+                attachment
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(attachment)
+        })
+        .await
+    }
+
+    async fn get(&self, id: u64) -> Result<Attachment> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let attachment_tickets = tx.open_table(ATTACHMENT_TICKETS).map_err(store_error)?;
+            let attachments = tx.open_table(ATTACHMENTS).map_err(store_error)?;
+            let key = attachment_key(&attachment_tickets, id)?;
+            let attachment = attachments.get(key).map_err(store_error)?;
+            match attachment {
+                Some(bytes) => from_bytes(bytes.value()),
+                None => Err(Error::AttachmentNotFound { id }),
+            }
+        })
+        .await
+    }
+
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Attachment>> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let attachments = tx.open_table(ATTACHMENTS).map_err(store_error)?;
+            let mut result = Vec::new();
+            let range = attachments
+                .range((ticket_id, 0)..=(ticket_id, u64::MAX))
+                .map_err(store_error)?;
+            for entry in range {
+                let (_key, bytes) = entry.map_err(store_error)?;
+                result.push(from_bytes(bytes.value())?);
+            }
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn delete(&self, id: u64) -> Result<Attachment> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let attachment = {
+                let mut attachment_tickets =
+                    tx.open_table(ATTACHMENT_TICKETS).map_err(store_error)?;
+                let mut attachments = tx.open_table(ATTACHMENTS).map_err(store_error)?;
+                let key = attachment_key(&attachment_tickets, id)?;
+                attachment_tickets.remove(id).map_err(store_error)?;
+                let removed = attachments.remove(key).map_err(store_error)?;
+                match removed {
+                    Some(bytes) => from_bytes::<Attachment>(bytes.value())?,
+                    None => return Err(Error::AttachmentNotFound { id }),
+                }
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(attachment)
+        })
+        .await
+    }
+}
+
+// -- History
+// 永久刪除 ticket 時不會刪除 HISTORY 中的紀錄
+#[async_trait]
+impl HistoryStore for KvStore {
+    async fn list(&self, ticket_id: u64) -> Result<Vec<TicketHistory>> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let table = tx.open_table(HISTORY).map_err(store_error)?;
+            let mut result = Vec::new();
+            let range = table
+                .range((ticket_id, 0)..=(ticket_id, u64::MAX))
+                .map_err(store_error)?;
+            for entry in range {
+                let (_key, bytes) = entry.map_err(store_error)?;
+                result.push(from_bytes(bytes.value())?);
+            }
+            Ok(result)
+        })
+        .await
+    }
+}
+
+// -- Users
+fn read_users(users: &impl ReadableTable<u64, &'static [u8]>) -> Result<Vec<User>> {
+    let mut result = Vec::new();
+    for entry in users.iter().map_err(store_error)? {
+        let (_id, bytes) = entry.map_err(store_error)?;
+        result.push(from_bytes::<UserRecord>(bytes.value())?.into());
+    }
+    Ok(result)
+}
+
+// 在寫入 transaction 中新增或取代使用者，並更新 username 與 email 的索引
+fn put_user(tx: &WriteTransaction, user: &User) -> Result<()> {
+    let mut users = tx.open_table(USERS).map_err(store_error)?;
+    let mut usernames = tx.open_table(USERNAMES).map_err(store_error)?;
+    let mut emails = tx.open_table(USER_EMAILS).map_err(store_error)?;
+    let existing = users
+        .get(user.id)
+        .map_err(store_error)?
+        .map(|bytes| from_bytes::<UserRecord>(bytes.value()).map(User::from))
+        .transpose()?;
+    if let Some(existing) = existing {
+        usernames
+            .remove(existing.username.as_str())
+            .map_err(store_error)?;
+        emails
+            .remove((existing.email.as_str(), existing.id))
+            .map_err(store_error)?;
+    }
+    users
+        .insert(user.id, to_bytes(&UserRecord::from(user))?.as_slice())
+        .map_err(store_error)?;
+    usernames
+        .insert(user.username.as_str(), user.id)
+        .map_err(store_error)?;
+    emails
+        .insert((user.email.as_str(), user.id), ())
+        .map_err(store_error)?;
+    Ok(())
+}
+
+// 加入索引之前建立的資料檔沒有索引，開啟時補上
+fn index_existing_users(tx: &WriteTransaction) -> Result<()> {
+    let users = read_users(&tx.open_table(USERS).map_err(store_error)?)?;
+    let indexed = tx.open_table(USERNAMES).map_err(store_error)?.len();
+    if indexed.map_err(store_error)? == users.len() as u64 {
+        return Ok(());
+    }
+    for user in &users {
+        put_user(tx, user)?;
+    }
+    Ok(())
+}
+
+fn get_user(tx: &redb::ReadTransaction, id: u64) -> Result<Option<User>> {
+    let users = tx.open_table(USERS).map_err(store_error)?;
+    let user = users.get(id).map_err(store_error)?;
+    user.map(|bytes| from_bytes::<UserRecord>(bytes.value()).map(User::from))
+        .transpose()
+}
+
+#[async_trait]
+impl UserStore for KvStore {
+    async fn create(&self, user_fi: UserForInsert) -> Result<User> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let user = {
+                let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+                let usernames = tx.open_table(USERNAMES).map_err(store_error)?;
+                // 在同一個寫入 transaction 中檢查，避免同時建立兩個相同的 username
+                if usernames
+                    .get(user_fi.username.as_str())
+                    .map_err(store_error)?
+                    .is_some()
+                {
+                    return Err(Error::UserCreateFailUsernameTaken {
+                        username: user_fi.username,
+                    });
+                }
+                user_fi.into_user(next_id(&mut sequences, "users")?)
+            };
+            put_user(&tx, &user)?;
+            tx.commit().map_err(store_error)?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn get(&self, id: u64) -> Result<Option<User>> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            get_user(&tx, id)
+        })
+        .await
+    }
+
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>> {
+        let username = username.to_string();
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let usernames = tx.open_table(USERNAMES).map_err(store_error)?;
+            let id = usernames.get(username.as_str()).map_err(store_error)?;
+            match id.map(|id| id.value()) {
+                Some(id) => get_user(&tx, id),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<Option<User>> {
+        let email = email.to_string();
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let emails = tx.open_table(USER_EMAILS).map_err(store_error)?;
+            let id = emails
+                .range((email.as_str(), 0)..=(email.as_str(), u64::MAX))
+                .map_err(store_error)?
+                .next()
+                .transpose()
+                .map_err(store_error)?
+                .map(|(key, _)| key.value().1);
+            match id {
+                Some(id) => get_user(&tx, id),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<User>> {
+        self.blocking(|db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let users = tx.open_table(USERS).map_err(store_error)?;
+            read_users(&users)
+        })
+        .await
+    }
+
+    async fn update(&self, id: u64, user_fu: UserForUpdate) -> Result<User> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let mut user = {
+                let usernames = tx.open_table(USERNAMES).map_err(store_error)?;
+                if let Some(username) = &user_fu.username {
+                    let other = usernames.get(username.as_str()).map_err(store_error)?;
+                    if other.is_some_and(|other| other.value() != id) {
+                        return Err(Error::UserCreateFailUsernameTaken {
+                            username: username.clone(),
+                        });
+                    }
+                }
+                let users = tx.open_table(USERS).map_err(store_error)?;
+                let user = users.get(id).map_err(store_error)?;
+                user.map(|bytes| from_bytes::<UserRecord>(bytes.value()).map(User::from))
+                    .transpose()?
+                    .ok_or(Error::UserNotFound { id })?
+            };
+            user_fu.apply_to(&mut user);
+            put_user(&tx, &user)?;
+            tx.commit().map_err(store_error)?;
+            Ok(user)
+        })
+        .await
+    }
+}
+
+// -- Groups
+fn read_group(groups: &impl ReadableTable<u64, &'static [u8]>, id: u64) -> Result<Group> {
+    match groups.get(id).map_err(store_error)? {
+        Some(bytes) => from_bytes(bytes.value()),
+        None => Err(Error::GroupNotFound { id }),
+    }
+}
+
+#[async_trait]
+impl GroupStore for KvStore {
+    async fn create(&self, group_fc: GroupForCreate) -> Result<Group> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let group = {
+                let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+                let mut groups = tx.open_table(GROUPS).map_err(store_error)?;
+                let group = group_fc.into_group(next_id(&mut sequences, "groups")?);
+                groups
+                    .insert(group.id, to_bytes(&group)?.as_slice())
+                    .map_err(store_error)?;
+                group
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(group)
+        })
+        .await
+    }
+
+    async fn get(&self, id: u64) -> Result<Group> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let groups = tx.open_table(GROUPS).map_err(store_error)?;
+            read_group(&groups, id)
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<Group>> {
+        self.blocking(|db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let groups = tx.open_table(GROUPS).map_err(store_error)?;
+            let mut result = Vec::new();
+            for entry in groups.iter().map_err(store_error)? {
+                let (_id, bytes) = entry.map_err(store_error)?;
+                result.push(from_bytes(bytes.value())?);
+            }
+            Ok(result)
+        })
+        .await
+    }
+
+    async fn update(&self, id: u64, group_fu: GroupForUpdate) -> Result<Group> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let group = {
+                let mut groups = tx.open_table(GROUPS).map_err(store_error)?;
+                let mut group = read_group(&groups, id)?;
+                group_fu.apply_to(&mut group);
+                groups
+                    .insert(group.id, to_bytes(&group)?.as_slice())
+                    .map_err(store_error)?;
+                group
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(group)
+        })
+        .await
+    }
+
+    async fn delete(&self, id: u64) -> Result<Group> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let group = {
+                let mut groups = tx.open_table(GROUPS).map_err(store_error)?;
+                let group = read_group(&groups, id)?;
+                groups.remove(id).map_err(store_error)?;
+                group
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(group)
+        })
+        .await
+    }
+}
+
+// -- Invitations
+fn read_invitation(
+    invitations: &impl ReadableTable<u64, &'static [u8]>,
+    id: u64,
+) -> Result<Invitation> {
+    match invitations.get(id).map_err(store_error)? {
+        Some(bytes) => Ok(from_bytes::<InvitationRecord>(bytes.value())?.into()),
+        None => Err(Error::InvitationNotFound { id }),
+    }
+}
+
+fn write_invitation(invitations: &mut Table<u64, &[u8]>, invitation: &Invitation) -> Result<()> {
+    let record = InvitationRecord::from(invitation);
+    invitations
+        .insert(invitation.id, to_bytes(&record)?.as_slice())
+        .map_err(store_error)?;
+    Ok(())
+}
+
+#[async_trait]
+impl InvitationStore for KvStore {
+    async fn create(&self, invitation_fi: InvitationForInsert) -> Result<Invitation> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let invitation = {
+                let mut sequences = tx.open_table(SEQUENCES).map_err(store_error)?;
+                let mut invitations = tx.open_table(INVITATIONS).map_err(store_error)?;
+                let invitation =
+                    invitation_fi.into_invitation(next_id(&mut sequences, "invitations")?);
+                write_invitation(&mut invitations, &invitation)?;
+                invitation
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(invitation)
+        })
+        .await
+    }
+
+    async fn get(&self, id: u64) -> Result<Invitation> {
+        self.blocking(move |db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let invitations = tx.open_table(INVITATIONS).map_err(store_error)?;
+            read_invitation(&invitations, id)
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<Invitation>> {
+        self.blocking(|db| {
+            let tx = db.begin_read().map_err(store_error)?;
+            let invitations = tx.open_table(INVITATIONS).map_err(store_error)?;
+            let mut result = Vec::new();
+            for entry in invitations.iter().map_err(store_error)? {
+                let (_id, bytes) = entry.map_err(store_error)?;
+                result.push(from_bytes::<InvitationRecord>(bytes.value())?.into());
+            }
+            Ok(result)
+        })
+        .await
+    }
+
+    // 讀出、檢查狀態與寫回都在同一個寫入 transaction 中，redb 同時只會有一個寫入 transaction
+    async fn update_status(
+        &self,
+        id: u64,
+        expected: InvitationStatus,
+        status: InvitationStatus,
+        accepted_by: Option<u64>,
+    ) -> Result<Invitation> {
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let invitation = {
+                let mut invitations = tx.open_table(INVITATIONS).map_err(store_error)?;
+                let mut invitation = read_invitation(&invitations, id)?;
+                invitation.transition(expected, status, accepted_by)?;
+                write_invitation(&mut invitations, &invitation)?;
+                invitation
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(invitation)
+        })
+        .await
+    }
+}
+
+// -- Signatures
+#[async_trait]
+impl ReplayStore for KvStore {
+    async fn insert_signature(
+        &self,
+        signature: &str,
+        seen_at: u64,
+        expire_before: u64,
+    ) -> Result<bool> {
+        let signature = signature.to_string();
+        self.blocking(move |db| {
+            let tx = db.begin_write().map_err(store_error)?;
+            let inserted = {
+                let mut table = tx.open_table(SIGNATURES).map_err(store_error)?;
+                let mut expired = Vec::new();
+                for entry in table.iter().map_err(store_error)? {
+                    let (key, at) = entry.map_err(store_error)?;
+                    if at.value() < expire_before {
+                        expired.push(key.value().to_string());
+                    }
+                }
+                for key in expired {
+                    table.remove(key.as_str()).map_err(store_error)?;
+                }
+                let previous = table
+                    .insert(signature.as_str(), seen_at)
+                    .map_err(store_error)?;
+                previous.is_none()
+            };
+            tx.commit().map_err(store_error)?;
+            Ok(inserted)
+        })
+        .await
+    }
+}
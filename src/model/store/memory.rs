@@ -0,0 +1,1199 @@
+// 一般來說是使用資料庫存取，這邊為了簡單，使用物件（記憶體）當作資料庫使用
+// 沒有設定 journal 時，服務重新啟動後資料就會消失，適合開發與測試使用
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::journal::{Commit, Journal};
+use super::record::{InvitationRecord, UserRecord};
+use super::{
+    store_error, AttachmentStore, CommentStore, GroupStore, HistoryStore, InvitationStore,
+    ReplayStore, TicketStore, UserStore,
+};
+use crate::model::{
+    Attachment, AttachmentForCreate, Comment, CommentForCreate, CommentForUpdate, Group,
+    GroupForCreate, GroupForUpdate, Invitation, InvitationForInsert, InvitationStatus, Ticket,
+    TicketForCreate, TicketForUpdate, TicketHistory, TicketHistoryForInsert, User, UserFilter,
+    UserForInsert, UserForUpdate,
+};
+use crate::{Error, Result};
+
+// 讀取（list）遠比寫入頻繁，使用 RwLock 讓多個讀取可以同時進行，只有寫入時才需要獨佔
+#[derive(Default)]
+pub struct MemTicketStore {
+    tickets: RwLock<Tickets>,
+    // 有設定時，每一次異動都會寫入 journal
+    journal: Option<Journal>,
+}
+
+// id 由遞增的計數器分配，刪除後不會被重新使用，與 SQL 儲存層的 AUTOINCREMENT 行為一致
+// 以 BTreeMap 依 id 儲存，刪除時直接移除，不會留下空位，list 時也會依 id 排序
+// 同時也會寫入 snapshot，by_creator 與 xxx_by_ticket 只是索引，載入後再重建即可
+// 留言、附件資訊與 ticket 放在同一份資料中，刪除 ticket 時可以在同一個 lock 中一併刪除
+// 異動紀錄依 ticket_id 分組，只會新增，永久刪除 ticket 時也會保留
+#[derive(Clone, Serialize, Deserialize)]
+struct Tickets {
+    next_id: u64,
+    by_id: BTreeMap<u64, Ticket>,
+    #[serde(skip)]
+    by_creator: HashMap<u64, BTreeSet<u64>>,
+    #[serde(default = "first_id")]
+    next_comment_id: u64,
+    #[serde(default)]
+    comments: BTreeMap<u64, Comment>,
+    #[serde(skip)]
+    comments_by_ticket: HashMap<u64, BTreeSet<u64>>,
+    #[serde(default = "first_id")]
+    next_attachment_id: u64,
+    #[serde(default)]
+    attachments: BTreeMap<u64, Attachment>,
+    #[serde(skip)]
+    attachments_by_ticket: HashMap<u64, BTreeSet<u64>>,
+    #[serde(default = "first_id")]
+    next_history_id: u64,
+    #[serde(default)]
+    history: BTreeMap<u64, Vec<TicketHistory>>,
+}
+
+// 與其他儲存層一致，id 從 1 開始
+fn first_id() -> u64 {
+    1
+}
+
+impl Default for Tickets {
+    fn default() -> Self {
+        Self {
+            next_id: first_id(),
+            by_id: BTreeMap::new(),
+            by_creator: HashMap::new(),
+            next_comment_id: first_id(),
+            comments: BTreeMap::new(),
+            comments_by_ticket: HashMap::new(),
+            next_attachment_id: first_id(),
+            attachments: BTreeMap::new(),
+            attachments_by_ticket: HashMap::new(),
+            next_history_id: first_id(),
+            history: BTreeMap::new(),
+        }
+    }
+}
+
+impl Tickets {
+    // 不在垃圾桶中的 ticket
+    fn live(&self, id: u64) -> Option<&Ticket> {
+        self.by_id.get(&id).filter(|t| !t.is_deleted())
+    }
+
+    // 垃圾桶中的 ticket
+    fn deleted(&self, id: u64) -> Option<&Ticket> {
+        self.by_id.get(&id).filter(|t| t.is_deleted())
+    }
+
+    fn insert(&mut self, ticket: Ticket) {
+        // 重播 journal 時 id 已經分配好，計數器要跳過已經使用過的 id
+        self.next_id = self.next_id.max(ticket.id + 1);
+        self.by_creator
+            .entry(ticket.cid)
+            .or_default()
+            .insert(ticket.id);
+        self.by_id.insert(ticket.id, ticket);
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Ticket> {
+        let ticket = self.by_id.remove(&id)?;
+        if let Some(ids) = self.by_creator.get_mut(&ticket.cid) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.by_creator.remove(&ticket.cid);
+            }
+        }
+        for comment_id in self.comments_by_ticket.remove(&id).unwrap_or_default() {
+            self.comments.remove(&comment_id);
+        }
+        for attachment_id in self.attachments_by_ticket.remove(&id).unwrap_or_default() {
+            self.attachments.remove(&attachment_id);
+        }
+        Some(ticket)
+    }
+
+    fn insert_comment(&mut self, comment: Comment) {
+        self.next_comment_id = self.next_comment_id.max(comment.id + 1);
+        self.comments_by_ticket
+            .entry(comment.ticket_id)
+            .or_default()
+            .insert(comment.id);
+        self.comments.insert(comment.id, comment);
+    }
+
+    fn remove_comment(&mut self, id: u64) -> Option<Comment> {
+        let comment = self.comments.remove(&id)?;
+        if let Some(ids) = self.comments_by_ticket.get_mut(&comment.ticket_id) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.comments_by_ticket.remove(&comment.ticket_id);
+            }
+        }
+        Some(comment)
+    }
+
+    fn insert_attachment(&mut self, attachment: Attachment) {
+        self.next_attachment_id = self.next_attachment_id.max(attachment.id + 1);
+        self.attachments_by_ticket
+            .entry(attachment.ticket_id)
+            .or_default()
+            .insert(attachment.id);
+        self.attachments.insert(attachment.id, attachment);
+    }
+
+    fn remove_attachment(&mut self, id: u64) -> Option<Attachment> {
+        let attachment = self.attachments.remove(&id)?;
+        if let Some(ids) = self.attachments_by_ticket.get_mut(&attachment.ticket_id) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.attachments_by_ticket.remove(&attachment.ticket_id);
+            }
+        }
+        Some(attachment)
+    }
+
+    // 同一個 ticket 的紀錄依 id 遞增，重播 journal 時已經存在的紀錄直接略過
+    fn insert_history(&mut self, history: TicketHistory) {
+        self.next_history_id = self.next_history_id.max(history.id + 1);
+        let entries = self.history.entry(history.ticket_id).or_default();
+        if entries.last().is_none_or(|h| h.id < history.id) {
+            entries.push(history);
+        }
+    }
+
+    // 以目前的計數器分配紀錄的 id，修改時沒有任何欄位改變則為 None
+    fn new_history(
+        &self,
+        history_fi: TicketHistoryForInsert,
+        ticket_id: u64,
+    ) -> Option<TicketHistory> {
+        history_fi
+            .for_ticket(ticket_id)
+            .map(|history_fc| history_fc.into_history(self.next_history_id))
+    }
+
+    fn apply(&mut self, entry: TicketEntry) {
+        match entry {
+            TicketEntry::Create(ticket) | TicketEntry::Update(ticket) => self.insert(ticket),
+            TicketEntry::Delete(id) => {
+                self.remove(id);
+            }
+            TicketEntry::CommentCreate(comment) | TicketEntry::CommentUpdate(comment) => {
+                self.insert_comment(comment)
+            }
+            TicketEntry::CommentDelete(id) => {
+                self.remove_comment(id);
+            }
+            TicketEntry::AttachmentCreate(attachment) => self.insert_attachment(attachment),
+            TicketEntry::AttachmentDelete(id) => {
+                self.remove_attachment(id);
+            }
+            TicketEntry::HistoryAppend(history) => self.insert_history(history),
+            TicketEntry::Batch(entries) => {
+                for entry in entries {
+                    self.apply(entry);
+                }
+            }
+        }
+    }
+
+    fn rebuild_index(&mut self) {
+        self.by_creator.clear();
+        for ticket in self.by_id.values() {
+            self.by_creator
+                .entry(ticket.cid)
+                .or_default()
+                .insert(ticket.id);
+        }
+        self.comments_by_ticket.clear();
+        for comment in self.comments.values() {
+            self.comments_by_ticket
+                .entry(comment.ticket_id)
+                .or_default()
+                .insert(comment.id);
+        }
+        self.attachments_by_ticket.clear();
+        for attachment in self.attachments.values() {
+            self.attachments_by_ticket
+                .entry(attachment.ticket_id)
+                .or_default()
+                .insert(attachment.id);
+        }
+    }
+}
+
+// journal 中的每一筆異動，重播時直接套用結果，重複套用同一筆也不會改變結果
+#[derive(Serialize, Deserialize)]
+enum TicketEntry {
+    Create(Ticket),
+    Update(Ticket),
+    Delete(u64),
+    CommentCreate(Comment),
+    CommentUpdate(Comment),
+    CommentDelete(u64),
+    AttachmentCreate(Attachment),
+    AttachmentDelete(u64),
+    HistoryAppend(TicketHistory),
+    // 寫成 journal 中的同一行，重播時依序全部套用，例如 ticket 的異動與對應的異動紀錄
+    Batch(Vec<TicketEntry>),
+}
+
+impl TicketEntry {
+    // 有異動紀錄時與異動合併成一筆 Batch
+    fn with_history(self, history: Option<TicketHistory>) -> Self {
+        match history {
+            Some(history) => Self::Batch(vec![self, Self::HistoryAppend(history)]),
+            None => self,
+        }
+    }
+}
+
+impl MemTicketStore {
+    // 從 journal 目錄還原資料，之後的異動都會寫入 journal
+    pub fn with_journal(dir: impl AsRef<Path>, snapshot_every: u64) -> Result<Self> {
+        let (journal, recovered) = Journal::open(dir, snapshot_every)?;
+        let mut tickets: Tickets = recovered.snapshot.unwrap_or_default();
+        tickets.rebuild_index();
+        for entry in recovered.entries {
+            tickets.apply(entry);
+        }
+        println!(
+            "->> {:<12} - journal - recovered {} tickets",
+            "STORE",
+            tickets.by_id.len()
+        );
+        Ok(Self {
+            tickets: RwLock::new(tickets),
+            journal: Some(journal),
+        })
+    }
+
+    // 持有 lock 的執行緒 panic 時，資料可能只改到一半（lock poisoning）
+    // 這時不再繼續使用這份資料，而是回傳錯誤，讓 request 以 500 結束，而不是讓整個服務跟著 panic
+    fn read(&self) -> Result<RwLockReadGuard<'_, Tickets>> {
+        journal_check(&self.journal)?;
+        self.tickets
+            .read()
+            .map_err(|_| Error::StoreFailLockPoisoned)
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Tickets>> {
+        journal_check(&self.journal)?;
+        self.tickets
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)
+    }
+
+    // 在持有 tickets 的 lock 時將異動排入 journal，確保 journal 的順序與記憶體中的異動順序一致
+    // 必須在修改記憶體中的資料之前呼叫，排入失敗時直接回傳錯誤，記憶體中的資料不會改變
+    // 放開 lock 之後再以 journal_wait 等待寫入磁碟，確認寫入後才回應
+    fn record(&self, entry: &TicketEntry) -> Result<Option<Commit>> {
+        journal_append(&self.journal, entry)
+    }
+
+    // 修改記憶體中的資料之後，累積足夠的 journal 時寫入 snapshot
+    fn snapshot_if_due(&self, tickets: &Tickets) -> Result<()> {
+        journal_snapshot_if_due(&self.journal, || tickets.clone())
+    }
+}
+
+fn journal_append(journal: &Option<Journal>, entry: &impl Serialize) -> Result<Option<Commit>> {
+    journal
+        .as_ref()
+        .map(|journal| journal.append(entry))
+        .transpose()
+}
+
+// 必須在放開資料的 lock 之後呼叫，寫入失敗時 journal 會停用，這個 request 以 500 結束
+async fn journal_wait(commit: Option<Commit>) -> Result<()> {
+    match commit {
+        Some(commit) => commit.wait().await,
+        None => Ok(()),
+    }
+}
+
+// journal 寫入失敗後，記憶體中可能有沒有寫入檔案的異動，與 lock poisoning 相同不再使用這份資料
+// 重新啟動時會從檔案還原，只包含已經確認寫入的異動
+fn journal_check(journal: &Option<Journal>) -> Result<()> {
+    match journal {
+        Some(journal) if journal.is_poisoned() => Err(Error::StoreFailJournalPoisoned),
+        _ => Ok(()),
+    }
+}
+
+// 在 lock 中只複製資料（state），寫檔交給 spawn_blocking 在背景執行，不會在寫 snapshot 的期間擋住其他的讀寫
+// 異動都在 journal 中，snapshot 失敗也不會遺失資料，只記錄錯誤，下一次累積足夠的 journal 時再重試
+fn journal_snapshot_if_due<S>(journal: &Option<Journal>, state: impl FnOnce() -> S) -> Result<()>
+where
+    S: Serialize + Send + 'static,
+{
+    let Some(journal) = journal else {
+        return Ok(());
+    };
+    if !journal.should_snapshot()? {
+        return Ok(());
+    }
+    match journal.begin_snapshot() {
+        Ok(Some(job)) => {
+            let state = state();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = job.write(&state) {
+                    println!("->> {:<12} - journal - snapshot failed: {e:?}", "STORE");
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(e) => println!("->> {:<12} - journal - snapshot failed: {e:?}", "STORE"),
+    }
+    Ok(())
+}
+
+// 服務關閉前寫入最後一次 snapshot，背景的 snapshot 還沒完成時等它完成後再開始
+// 與異動相同，在資料的 lock（read）中排入換 journal 的動作並複製資料，讓 snapshot 剛好包含換下來的 journal 中的異動
+async fn journal_shutdown<G, S>(
+    journal: &Option<Journal>,
+    read: impl Fn() -> Result<G>,
+) -> Result<()>
+where
+    G: Deref<Target = S>,
+    S: Clone + Serialize + Send + 'static,
+{
+    let Some(journal) = journal else {
+        return Ok(());
+    };
+    loop {
+        let snapshot = {
+            let state = read()?;
+            journal.begin_snapshot()?.map(|job| (job, state.clone()))
+        };
+        match snapshot {
+            Some((job, state)) => {
+                return tokio::task::spawn_blocking(move || job.write(&state))
+                    .await
+                    .map_err(store_error)?;
+            }
+            None => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    }
+}
+
+#[async_trait]
+impl TicketStore for MemTicketStore {
+    async fn create(
+        &self,
+        cid: u64,
+        ticket_fc: TicketForCreate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        let (ticket, commit) = {
+            let mut store = self.write()?;
+            let ticket = ticket_fc.into_ticket(store.next_id, cid);
+            let history = store.new_history(history_fi, ticket.id);
+            let entry = TicketEntry::Create(ticket.clone()).with_history(history);
+            let commit = self.record(&entry)?;
+            store.apply(entry);
+            self.snapshot_if_due(&store)?;
+            (ticket, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(ticket)
+    }
+
+    async fn get(&self, id: u64) -> Result<Ticket> {
+        let store = self.read()?;
+        store.live(id).cloned().ok_or(Error::TicketNotFound { id })
+    }
+
+    async fn list(&self) -> Result<Vec<Ticket>> {
+        let store = self.read()?;
+        Ok(store
+            .by_id
+            .values()
+            .filter(|t| !t.is_deleted())
+            .cloned()
+            .collect())
+    }
+
+    async fn list_by_creator(&self, cid: u64) -> Result<Vec<Ticket>> {
+        let store = self.read()?;
+        let ids = store.by_creator.get(&cid).into_iter().flatten();
+        Ok(ids.filter_map(|id| store.live(*id).cloned()).collect())
+    }
+
+    async fn list_by_assignee(&self, user_id: u64, group_ids: &[u64]) -> Result<Vec<Ticket>> {
+        let store = self.read()?;
+        Ok(store
+            .by_id
+            .values()
+            .filter(|t| !t.is_deleted() && t.is_assigned_to(user_id, group_ids))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(
+        &self,
+        id: u64,
+        updated_by: u64,
+        expected_version: Option<u64>,
+        ticket_fu: TicketForUpdate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        let (ticket, commit) = {
+            let mut store = self.write()?;
+            let mut ticket = store
+                .live(id)
+                .cloned()
+                .ok_or(Error::TicketNotFound { id })?;
+            ticket.check_version(expected_version)?;
+            ticket_fu.apply_to(&mut ticket, updated_by);
+            // 建立者不會改變，直接覆蓋即可，by_creator 的索引不需要調整
+            let history = store.new_history(history_fi, id);
+            let entry = TicketEntry::Update(ticket.clone()).with_history(history);
+            let commit = self.record(&entry)?;
+            store.apply(entry);
+            self.snapshot_if_due(&store)?;
+            (ticket, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(ticket)
+    }
+
+    // 移到垃圾桶只是修改 ticket 的欄位，journal 中記錄為 Update
+    async fn delete(
+        &self,
+        id: u64,
+        deleted_by: u64,
+        expected_version: Option<u64>,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        let (ticket, commit) = {
+            let mut store = self.write()?;
+            // ok_or 可以將 Some 轉成 Result，相當好用
+            let mut ticket = store
+                .live(id)
+                .cloned()
+                .ok_or(Error::TicketNotFound { id })?;
+            ticket.check_version(expected_version)?;
+            let before = ticket.clone();
+            ticket.mark_deleted(deleted_by);
+            let history = store.new_history(history_fi.with_lifecycle(&before, Some(&ticket)), id);
+            let entry = TicketEntry::Update(ticket.clone()).with_history(history);
+            let commit = self.record(&entry)?;
+            store.apply(entry);
+            self.snapshot_if_due(&store)?;
+            (ticket, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(ticket)
+    }
+
+    async fn list_deleted(&self) -> Result<Vec<Ticket>> {
+        let store = self.read()?;
+        Ok(store
+            .by_id
+            .values()
+            .filter(|t| t.is_deleted())
+            .cloned()
+            .collect())
+    }
+
+    async fn get_deleted(&self, id: u64) -> Result<Ticket> {
+        let store = self.read()?;
+        store
+            .by_id
+            .get(&id)
+            .filter(|t| t.is_deleted())
+            .cloned()
+            .ok_or(Error::TicketNotFound { id })
+    }
+
+    async fn restore(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket> {
+        let (ticket, commit) = {
+            let mut store = self.write()?;
+            let mut ticket = store
+                .deleted(id)
+                .cloned()
+                .ok_or(Error::TicketNotFound { id })?;
+            let before = ticket.clone();
+            ticket.mark_restored();
+            let history = store.new_history(history_fi.with_lifecycle(&before, Some(&ticket)), id);
+            let entry = TicketEntry::Update(ticket.clone()).with_history(history);
+            let commit = self.record(&entry)?;
+            store.apply(entry);
+            self.snapshot_if_due(&store)?;
+            (ticket, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(ticket)
+    }
+
+    async fn purge(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket> {
+        let (ticket, commit) = {
+            let mut store = self.write()?;
+            let ticket = store
+                .deleted(id)
+                .cloned()
+                .ok_or(Error::TicketNotFound { id })?;
+            let history = store.new_history(history_fi.with_lifecycle(&ticket, None), id);
+            let entry = TicketEntry::Delete(id).with_history(history);
+            let commit = self.record(&entry)?;
+            store.apply(entry);
+            self.snapshot_if_due(&store)?;
+            (ticket, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(ticket)
+    }
+
+    // 所有被刪除的 ticket 與異動紀錄寫成 journal 中的同一筆
+    async fn purge_deleted_before(
+        &self,
+        cutoff: u64,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Vec<Ticket>> {
+        let (tickets, commit) = {
+            let mut store = self.write()?;
+            let tickets: Vec<Ticket> = store
+                .by_id
+                .values()
+                .filter(|t| t.deleted_at.is_some_and(|at| at <= cutoff))
+                .cloned()
+                .collect();
+            if tickets.is_empty() {
+                return Ok(tickets);
+            }
+            let mut entries = Vec::new();
+            let mut next_history_id = store.next_history_id;
+            for ticket in &tickets {
+                entries.push(TicketEntry::Delete(ticket.id));
+                let history_fi = history_fi.clone().with_lifecycle(ticket, None);
+                if let Some(history_fc) = history_fi.for_ticket(ticket.id) {
+                    entries.push(TicketEntry::HistoryAppend(
+                        history_fc.into_history(next_history_id),
+                    ));
+                    next_history_id += 1;
+                }
+            }
+            let entry = TicketEntry::Batch(entries);
+            let commit = self.record(&entry)?;
+            store.apply(entry);
+            self.snapshot_if_due(&store)?;
+            (tickets, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(tickets)
+    }
+
+    // 等背景的 snapshot 結束後，再以目前的資料寫一次完整的 snapshot
+    async fn shutdown(&self) -> Result<()> {
+        journal_shutdown(&self.journal, || self.read()).await
+    }
+}
+
+#[async_trait]
+impl CommentStore for MemTicketStore {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        author_id: u64,
+        comment_fc: CommentForCreate,
+    ) -> Result<Comment> {
+        let (comment, commit) = {
+            let mut store = self.write()?;
+            // 在同一個 lock 中確認 ticket 仍然存在且不在垃圾桶中
+            if store.live(ticket_id).is_none() {
+                return Err(Error::TicketNotFound { id: ticket_id });
+            }
+            let comment = comment_fc.into_comment(store.next_comment_id, ticket_id, author_id);
+            let commit = self.record(&TicketEntry::CommentCreate(comment.clone()))?;
+            store.insert_comment(comment.clone());
+            self.snapshot_if_due(&store)?;
+            (comment, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(comment)
+    }
+
+    async fn get(&self, id: u64) -> Result<Comment> {
+        let store = self.read()?;
+        store
+            .comments
+            .get(&id)
+            .cloned()
+            .ok_or(Error::CommentNotFound { id })
+    }
+
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Comment>> {
+        let store = self.read()?;
+        let ids = store
+            .comments_by_ticket
+            .get(&ticket_id)
+            .into_iter()
+            .flatten();
+        Ok(ids
+            .filter_map(|id| store.comments.get(id).cloned())
+            .collect())
+    }
+
+    async fn update(&self, id: u64, comment_fu: CommentForUpdate) -> Result<Comment> {
+        let (comment, commit) = {
+            let mut store = self.write()?;
+            let mut comment = store
+                .comments
+                .get(&id)
+                .cloned()
+                .ok_or(Error::CommentNotFound { id })?;
+            comment_fu.apply_to(&mut comment);
+            let commit = self.record(&TicketEntry::CommentUpdate(comment.clone()))?;
+            store.insert_comment(comment.clone());
+            self.snapshot_if_due(&store)?;
+            (comment, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(comment)
+    }
+
+    async fn delete(&self, id: u64) -> Result<Comment> {
+        let (comment, commit) = {
+            let mut store = self.write()?;
+            if !store.comments.contains_key(&id) {
+                return Err(Error::CommentNotFound { id });
+            }
+            let commit = self.record(&TicketEntry::CommentDelete(id))?;
+            let comment = store
+                .remove_comment(id)
+                .ok_or(Error::CommentNotFound { id })?;
+            self.snapshot_if_due(&store)?;
+            (comment, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(comment)
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for MemTicketStore {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        uploader_id: u64,
+        attachment_fc: AttachmentForCreate,
+    ) -> Result<Attachment> {
+        let (attachment, commit) = {
+            let mut store = self.write()?;
+            if store.live(ticket_id).is_none() {
+                return Err(Error::TicketNotFound { id: ticket_id });
+            }
+            let attachment =
+                attachment_fc.into_attachment(store.next_attachment_id, ticket_id, uploader_id);
+            let commit = self.record(&TicketEntry::AttachmentCreate(attachment.clone()))?;
+            store.insert_attachment(attachment.clone());
+            self.snapshot_if_due(&store)?;
+            (attachment, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(attachment)
+    }
+
+    async fn get(&self, id: u64) -> Result<Attachment> {
+        let store = self.read()?;
+        store
+            .attachments
+            .get(&id)
+            .cloned()
+            .ok_or(Error::AttachmentNotFound { id })
+    }
+
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Attachment>> {
+        let store = self.read()?;
+        let ids = store
+            .attachments_by_ticket
+            .get(&ticket_id)
+            .into_iter()
+            .flatten();
+        Ok(ids
+            .filter_map(|id| store.attachments.get(id).cloned())
+            .collect())
+    }
+
+    async fn delete(&self, id: u64) -> Result<Attachment> {
+        let (attachment, commit) = {
+            let mut store = self.write()?;
+            if !store.attachments.contains_key(&id) {
+                return Err(Error::AttachmentNotFound { id });
+            }
+            let commit = self.record(&TicketEntry::AttachmentDelete(id))?;
+            let attachment = store
+                .remove_attachment(id)
+                .ok_or(Error::AttachmentNotFound { id })?;
+            self.snapshot_if_due(&store)?;
+            (attachment, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(attachment)
+    }
+}
+
+#[async_trait]
+impl HistoryStore for MemTicketStore {
+    async fn list(&self, ticket_id: u64) -> Result<Vec<TicketHistory>> {
+        let store = self.read()?;
+        Ok(store.history.get(&ticket_id).cloned().unwrap_or_default())
+    }
+}
+
+// 每個 request 都會讀取使用者（建立 Ctx），與 ticket 相同使用 RwLock，lock poisoning 時回傳錯誤
+// 與 ticket 相同可以搭配 journal，服務重新啟動後使用者的 id 仍然對應到同一個人
+#[derive(Default)]
+pub struct MemUserStore {
+    directory: RwLock<Directory>,
+    journal: Option<Journal>,
+    // 簽章請求已經使用過的簽章，只在時間範圍內有意義，不寫入 journal
+    signatures: Mutex<HashMap<String, u64>>,
+}
+
+// 使用者、群組與邀請放在同一份資料中，id 與 ticket 相同由遞增的計數器分配，刪除後不會被重新使用
+// User、Invitation 序列化時會略過密碼與 nonce，寫入 snapshot 時轉換成 DirectorySnapshot
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "DirectorySnapshot", from = "DirectorySnapshot")]
+struct Directory {
+    next_user_id: u64,
+    users: BTreeMap<u64, User>,
+    // 登入、簽章與 client 憑證都以 username 找使用者，邀請以 email 找使用者，不需要掃描所有的使用者
+    // 索引不寫入 snapshot，還原時由 put_user 重建；email 可能重複，對應到所有使用這個 email 的 id
+    by_username: HashMap<String, u64>,
+    by_email: HashMap<String, BTreeSet<u64>>,
+    next_group_id: u64,
+    groups: BTreeMap<u64, Group>,
+    next_invitation_id: u64,
+    invitations: BTreeMap<u64, Invitation>,
+}
+
+impl Default for Directory {
+    fn default() -> Self {
+        Self {
+            next_user_id: first_id(),
+            users: BTreeMap::new(),
+            by_username: HashMap::new(),
+            by_email: HashMap::new(),
+            next_group_id: first_id(),
+            groups: BTreeMap::new(),
+            next_invitation_id: first_id(),
+            invitations: BTreeMap::new(),
+        }
+    }
+}
+
+impl Directory {
+    // 新增或取代使用者，並更新 username 與 email 的索引
+    fn put_user(&mut self, user: User) {
+        if let Some(existing) = self.users.get(&user.id) {
+            self.by_username.remove(&existing.username);
+            if let Some(ids) = self.by_email.get_mut(&existing.email) {
+                ids.remove(&user.id);
+                if ids.is_empty() {
+                    self.by_email.remove(&existing.email);
+                }
+            }
+        }
+        self.by_username.insert(user.username.clone(), user.id);
+        self.by_email
+            .entry(user.email.clone())
+            .or_default()
+            .insert(user.id);
+        self.next_user_id = self.next_user_id.max(user.id + 1);
+        self.users.insert(user.id, user);
+    }
+
+    fn insert_group(&mut self, group: Group) {
+        self.next_group_id = self.next_group_id.max(group.id + 1);
+        self.groups.insert(group.id, group);
+    }
+
+    fn insert_invitation(&mut self, invitation: Invitation) {
+        self.next_invitation_id = self.next_invitation_id.max(invitation.id + 1);
+        self.invitations.insert(invitation.id, invitation);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct DirectorySnapshot {
+    // 加入這個欄位之前的 snapshot 沒有計數器，還原使用者時由 put_user 推算
+    #[serde(default = "first_id")]
+    next_user_id: u64,
+    users: Vec<UserRecord>,
+    next_group_id: u64,
+    groups: BTreeMap<u64, Group>,
+    next_invitation_id: u64,
+    invitations: Vec<InvitationRecord>,
+}
+
+impl From<Directory> for DirectorySnapshot {
+    fn from(directory: Directory) -> Self {
+        Self {
+            next_user_id: directory.next_user_id,
+            users: directory.users.values().map(UserRecord::from).collect(),
+            next_group_id: directory.next_group_id,
+            groups: directory.groups,
+            next_invitation_id: directory.next_invitation_id,
+            invitations: directory
+                .invitations
+                .values()
+                .map(InvitationRecord::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<DirectorySnapshot> for Directory {
+    fn from(snapshot: DirectorySnapshot) -> Self {
+        let mut directory = Self {
+            next_user_id: snapshot.next_user_id,
+            users: BTreeMap::new(),
+            by_username: HashMap::new(),
+            by_email: HashMap::new(),
+            next_group_id: snapshot.next_group_id,
+            groups: snapshot.groups,
+            next_invitation_id: snapshot.next_invitation_id,
+            invitations: snapshot
+                .invitations
+                .into_iter()
+                .map(|record| {
+                    let invitation = Invitation::from(record);
+                    (invitation.id, invitation)
+                })
+                .collect(),
+        };
+        for user in snapshot.users {
+            directory.put_user(user.into());
+        }
+        directory
+    }
+}
+
+// journal 中的每一筆異動，與 TicketEntry 相同記錄異動後的結果
+#[derive(Serialize, Deserialize)]
+enum DirectoryEntry {
+    UserCreate(UserRecord),
+    UserUpdate(UserRecord),
+    GroupCreate(Group),
+    GroupUpdate(Group),
+    GroupDelete(u64),
+    InvitationCreate(InvitationRecord),
+    InvitationUpdate(InvitationRecord),
+}
+
+impl MemUserStore {
+    // 從 journal 目錄還原資料，之後的異動都會寫入 journal
+    pub fn with_journal(dir: impl AsRef<Path>, snapshot_every: u64) -> Result<Self> {
+        let (journal, recovered) = Journal::open(dir, snapshot_every)?;
+        let mut directory: Directory = recovered.snapshot.unwrap_or_default();
+        for entry in recovered.entries {
+            match entry {
+                DirectoryEntry::UserCreate(user) | DirectoryEntry::UserUpdate(user) => {
+                    directory.put_user(user.into())
+                }
+                DirectoryEntry::GroupCreate(group) | DirectoryEntry::GroupUpdate(group) => {
+                    directory.insert_group(group)
+                }
+                DirectoryEntry::GroupDelete(id) => {
+                    directory.groups.remove(&id);
+                }
+                DirectoryEntry::InvitationCreate(invitation)
+                | DirectoryEntry::InvitationUpdate(invitation) => {
+                    directory.insert_invitation(invitation.into())
+                }
+            }
+        }
+        println!(
+            "->> {:<12} - journal - recovered {} users",
+            "STORE",
+            directory.users.len()
+        );
+        Ok(Self {
+            directory: RwLock::new(directory),
+            journal: Some(journal),
+            signatures: Mutex::default(),
+        })
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, Directory>> {
+        journal_check(&self.journal)?;
+        self.directory
+            .read()
+            .map_err(|_| Error::StoreFailLockPoisoned)
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Directory>> {
+        journal_check(&self.journal)?;
+        self.directory
+            .write()
+            .map_err(|_| Error::StoreFailLockPoisoned)
+    }
+
+    // 與 MemTicketStore 相同，在持有資料的 lock 時、修改資料之前排入 journal
+    fn record(&self, entry: &DirectoryEntry) -> Result<Option<Commit>> {
+        journal_append(&self.journal, entry)
+    }
+
+    fn snapshot_if_due(&self, directory: &Directory) -> Result<()> {
+        journal_snapshot_if_due(&self.journal, || directory.clone())
+    }
+}
+
+#[async_trait]
+impl UserStore for MemUserStore {
+    async fn create(&self, user_fi: UserForInsert) -> Result<User> {
+        let (user, commit) = {
+            let mut store = self.write()?;
+            if store.by_username.contains_key(&user_fi.username) {
+                return Err(Error::UserCreateFailUsernameTaken {
+                    username: user_fi.username,
+                });
+            }
+            // 使用者的 id 從 1 開始，與 auth token 中的 user-[user-id] 對應
+            let user = user_fi.into_user(store.next_user_id);
+            let commit = self.record(&DirectoryEntry::UserCreate(UserRecord::from(&user)))?;
+            store.put_user(user.clone());
+            self.snapshot_if_due(&store)?;
+            (user, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(user)
+    }
+
+    async fn get(&self, id: u64) -> Result<Option<User>> {
+        let store = self.read()?;
+        Ok(store.users.get(&id).cloned())
+    }
+
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>> {
+        let store = self.read()?;
+        let id = store.by_username.get(username);
+        Ok(id.and_then(|id| store.users.get(id)).cloned())
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<Option<User>> {
+        let store = self.read()?;
+        let id = store.by_email.get(email).and_then(|ids| ids.first());
+        Ok(id.and_then(|id| store.users.get(id)).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<User>> {
+        let store = self.read()?;
+        Ok(store.users.values().cloned().collect())
+    }
+
+    // 在 lock 中篩選，只複製符合的使用者
+    async fn list_by_filter(&self, filter: &UserFilter) -> Result<Vec<User>> {
+        let store = self.read()?;
+        Ok(store
+            .users
+            .values()
+            .filter(|u| filter.matches(u))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, id: u64, user_fu: UserForUpdate) -> Result<User> {
+        let (user, commit) = {
+            let mut store = self.write()?;
+            if let Some(username) = &user_fu.username {
+                if store
+                    .by_username
+                    .get(username)
+                    .is_some_and(|other| *other != id)
+                {
+                    return Err(Error::UserCreateFailUsernameTaken {
+                        username: username.clone(),
+                    });
+                }
+            }
+            let mut user = store
+                .users
+                .get(&id)
+                .cloned()
+                .ok_or(Error::UserNotFound { id })?;
+            user_fu.apply_to(&mut user);
+            let commit = self.record(&DirectoryEntry::UserUpdate(UserRecord::from(&user)))?;
+            store.put_user(user.clone());
+            self.snapshot_if_due(&store)?;
+            (user, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(user)
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        journal_shutdown(&self.journal, || self.read()).await
+    }
+}
+
+#[async_trait]
+impl GroupStore for MemUserStore {
+    async fn create(&self, group_fc: GroupForCreate) -> Result<Group> {
+        let (group, commit) = {
+            let mut store = self.write()?;
+            let group = group_fc.into_group(store.next_group_id);
+            let commit = self.record(&DirectoryEntry::GroupCreate(group.clone()))?;
+            store.insert_group(group.clone());
+            self.snapshot_if_due(&store)?;
+            (group, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(group)
+    }
+
+    async fn get(&self, id: u64) -> Result<Group> {
+        let store = self.read()?;
+        store
+            .groups
+            .get(&id)
+            .cloned()
+            .ok_or(Error::GroupNotFound { id })
+    }
+
+    async fn list(&self) -> Result<Vec<Group>> {
+        let store = self.read()?;
+        Ok(store.groups.values().cloned().collect())
+    }
+
+    async fn list_by_display_name(&self, display_name: &str) -> Result<Vec<Group>> {
+        let store = self.read()?;
+        Ok(store
+            .groups
+            .values()
+            .filter(|g| g.display_name == display_name)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_by_member(&self, user_id: u64) -> Result<Vec<Group>> {
+        let store = self.read()?;
+        Ok(store
+            .groups
+            .values()
+            .filter(|g| g.members.contains(&user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, id: u64, group_fu: GroupForUpdate) -> Result<Group> {
+        let (group, commit) = {
+            let mut store = self.write()?;
+            let mut group = store
+                .groups
+                .get(&id)
+                .cloned()
+                .ok_or(Error::GroupNotFound { id })?;
+            group_fu.apply_to(&mut group);
+            let commit = self.record(&DirectoryEntry::GroupUpdate(group.clone()))?;
+            store.insert_group(group.clone());
+            self.snapshot_if_due(&store)?;
+            (group, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(group)
+    }
+
+    async fn delete(&self, id: u64) -> Result<Group> {
+        let (group, commit) = {
+            let mut store = self.write()?;
+            let group = store
+                .groups
+                .get(&id)
+                .cloned()
+                .ok_or(Error::GroupNotFound { id })?;
+            let commit = self.record(&DirectoryEntry::GroupDelete(id))?;
+            store.groups.remove(&id);
+            self.snapshot_if_due(&store)?;
+            (group, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(group)
+    }
+}
+
+#[async_trait]
+impl InvitationStore for MemUserStore {
+    async fn create(&self, invitation_fi: InvitationForInsert) -> Result<Invitation> {
+        let (invitation, commit) = {
+            let mut store = self.write()?;
+            let invitation = invitation_fi.into_invitation(store.next_invitation_id);
+            let commit = self.record(&DirectoryEntry::InvitationCreate(InvitationRecord::from(
+                &invitation,
+            )))?;
+            store.insert_invitation(invitation.clone());
+            self.snapshot_if_due(&store)?;
+            (invitation, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(invitation)
+    }
+
+    async fn get(&self, id: u64) -> Result<Invitation> {
+        let store = self.read()?;
+        store
+            .invitations
+            .get(&id)
+            .cloned()
+            .ok_or(Error::InvitationNotFound { id })
+    }
+
+    async fn list(&self) -> Result<Vec<Invitation>> {
+        let store = self.read()?;
+        Ok(store.invitations.values().cloned().collect())
+    }
+
+    async fn update_status(
+        &self,
+        id: u64,
+        expected: InvitationStatus,
+        status: InvitationStatus,
+        accepted_by: Option<u64>,
+    ) -> Result<Invitation> {
+        let (invitation, commit) = {
+            let mut store = self.write()?;
+            let mut invitation = store
+                .invitations
+                .get(&id)
+                .cloned()
+                .ok_or(Error::InvitationNotFound { id })?;
+            invitation.transition(expected, status, accepted_by)?;
+            let commit = self.record(&DirectoryEntry::InvitationUpdate(InvitationRecord::from(
+                &invitation,
+            )))?;
+            store.insert_invitation(invitation.clone());
+            self.snapshot_if_due(&store)?;
+            (invitation, commit)
+        };
+        journal_wait(commit).await?;
+        Ok(invitation)
+    }
+}
+
+#[async_trait]
+impl ReplayStore for MemUserStore {
+    async fn insert_signature(
+        &self,
+        signature: &str,
+        seen_at: u64,
+        expire_before: u64,
+    ) -> Result<bool> {
+        let mut signatures = self
+            .signatures
+            .lock()
+            .map_err(|_| Error::StoreFailLockPoisoned)?;
+        signatures.retain(|_, at| *at >= expire_before);
+        Ok(signatures.insert(signature.to_string(), seen_at).is_none())
+    }
+}
@@ -0,0 +1,440 @@
+// 儲存層，ModelController 只透過 TicketStore、UserStore 等 trait 存取資料
+// 這樣可以依照部署環境透過設定切換不同的儲存方式，而 ModelController 與 routes_tickets 都不需要修改
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{
+    Attachment, AttachmentForCreate, Comment, CommentForCreate, CommentForUpdate, Group,
+    GroupForCreate, GroupForUpdate, Invitation, InvitationForInsert, InvitationStatus, Ticket,
+    TicketCursor, TicketFilter, TicketForCreate, TicketForUpdate, TicketHistory,
+    TicketHistoryForCreate, TicketHistoryForInsert, TicketSort, User, UserFilter, UserForInsert,
+    UserForUpdate,
+};
+use crate::config::config;
+use crate::{Error, Result};
+
+mod blob;
+mod journal;
+mod kv;
+mod memory;
+mod postgres;
+mod record;
+mod sqlite;
+
+pub use self::blob::{LocalBlobStore, MemBlobStore};
+pub use self::kv::KvStore;
+pub use self::memory::{MemTicketStore, MemUserStore};
+pub use self::postgres::PgTicketStore;
+pub use self::sqlite::SqliteTicketStore;
+
+// 儲存層只負責資料的存取，權限檢查等商業邏輯留在 ModelController
+// 異動 ticket 的操作都會帶著一筆異動紀錄（history_fi），與異動在同一個 transaction 中寫入
+// 異動失敗時不會留下紀錄，寫入紀錄失敗時異動也不會生效
+#[async_trait]
+pub trait TicketStore: Send + Sync {
+    // 由儲存層負責分配 id
+    async fn create(
+        &self,
+        cid: u64,
+        ticket_fc: TicketForCreate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket>;
+    // get、list、update 只會處理不在垃圾桶中的 ticket，垃圾桶中的 ticket 視為找不到
+    async fn get(&self, id: u64) -> Result<Ticket>;
+    async fn list(&self) -> Result<Vec<Ticket>>;
+    // 依條件篩選、排序後，回傳從 offset 開始的最多 limit 筆，以及符合條件的總數
+    // 有游標（after）時只回傳排序在游標之後的 ticket，total 仍然是符合篩選條件的總數
+    // 預設的實作在記憶體中處理，SQL 儲存層覆寫成在查詢中完成
+    async fn list_page(
+        &self,
+        filter: &TicketFilter,
+        sort: TicketSort,
+        after: Option<&TicketCursor>,
+        limit: u64,
+        offset: u64,
+    ) -> Result<(Vec<Ticket>, u64)> {
+        let mut tickets: Vec<Ticket> = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|t| filter.matches(t))
+            .collect();
+        tickets.sort_by(|a, b| sort.compare(a, b));
+        let total = tickets.len() as u64;
+        let page = tickets
+            .into_iter()
+            .filter(|t| after.is_none_or(|cursor| sort.is_after(t, cursor)))
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        Ok((page, total))
+    }
+    // 某個使用者建立的所有 ticket，儲存層有索引時可以覆寫這個預設的實作
+    async fn list_by_creator(&self, cid: u64) -> Result<Vec<Ticket>> {
+        let tickets = self.list().await?;
+        Ok(tickets.into_iter().filter(|t| t.cid == cid).collect())
+    }
+    // 直接指派給 user_id，或指派給 group_ids 其中一個群組的 ticket，依 id 排序
+    async fn list_by_assignee(&self, user_id: u64, group_ids: &[u64]) -> Result<Vec<Ticket>> {
+        let tickets = self.list().await?;
+        Ok(tickets
+            .into_iter()
+            .filter(|t| t.is_assigned_to(user_id, group_ids))
+            .collect())
+    }
+    // 只更新 ticket_fu 中有提供的欄位，並記錄修改的時間與修改者
+    // expected_version 為 Some 時，需要在同一個操作中確認目前的版本相同，否則回傳 TicketFailVersionMismatch
+    async fn update(
+        &self,
+        id: u64,
+        updated_by: u64,
+        expected_version: Option<u64>,
+        ticket_fu: TicketForUpdate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket>;
+    // 移到垃圾桶，並記錄刪除的時間與刪除者，expected_version 與 update 相同
+    async fn delete(
+        &self,
+        id: u64,
+        deleted_by: u64,
+        expected_version: Option<u64>,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket>;
+    // -- Trash
+    // 以下操作只處理垃圾桶中的 ticket，不在垃圾桶中的 ticket 視為找不到
+    async fn list_deleted(&self) -> Result<Vec<Ticket>>;
+    async fn get_deleted(&self, id: u64) -> Result<Ticket>;
+    async fn restore(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket>;
+    // 永久刪除，ticket 底下的留言與附件資訊也會一起被刪除
+    async fn purge(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket>;
+    // 永久刪除在 cutoff（Unix 秒）之前（含）被移到垃圾桶的 ticket，回傳被刪除的 ticket
+    // 每一個被刪除的 ticket 都寫入一筆 history_fi
+    async fn purge_deleted_before(
+        &self,
+        cutoff: u64,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Vec<Ticket>>;
+    // 服務關閉前呼叫，讓儲存層有機會將資料寫入磁碟
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// 留言與 ticket 存放在同一個儲存層，由同一個物件實作，刪除 ticket 時才能在同一個操作中一併刪除留言
+#[async_trait]
+pub trait CommentStore: Send + Sync {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        author_id: u64,
+        comment_fc: CommentForCreate,
+    ) -> Result<Comment>;
+    async fn get(&self, id: u64) -> Result<Comment>;
+    // 依建立的順序（id）排序
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Comment>>;
+    async fn update(&self, id: u64, comment_fu: CommentForUpdate) -> Result<Comment>;
+    async fn delete(&self, id: u64) -> Result<Comment>;
+}
+
+// 附件的資訊（名稱、類型、大小），與留言一樣由 ticket 的儲存層實作，內容則存放在 BlobStore
+#[async_trait]
+pub trait AttachmentStore: Send + Sync {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        uploader_id: u64,
+        attachment_fc: AttachmentForCreate,
+    ) -> Result<Attachment>;
+    async fn get(&self, id: u64) -> Result<Attachment>;
+    // 依上傳的順序（id）排序
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Attachment>>;
+    async fn delete(&self, id: u64) -> Result<Attachment>;
+}
+
+// ticket 的異動紀錄，與 ticket 由同一個物件實作，只會隨著 TicketStore 的異動新增，不提供修改與刪除
+// 永久刪除 ticket 時不會刪除紀錄
+#[async_trait]
+pub trait HistoryStore: Send + Sync {
+    // 依發生的順序（id）排序
+    async fn list(&self, ticket_id: u64) -> Result<Vec<TicketHistory>>;
+}
+
+// 附件的內容，key 以 "/" 分隔階層
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    // key 已經存在時覆蓋原本的內容
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    // key 不存在時不視為錯誤
+    async fn delete(&self, key: &str) -> Result<()>;
+    // 刪除 key 以 "[dir]/" 開頭的所有 blob
+    async fn delete_dir(&self, dir: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    // 由儲存層負責分配 id 並確保 username 不重複
+    async fn create(&self, user_fi: UserForInsert) -> Result<User>;
+    async fn get(&self, id: u64) -> Result<Option<User>>;
+    // username 完全相同的使用者，每個 request 驗證身分時都會查詢，儲存層需要以索引查詢
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>>;
+    // email 完全相同的使用者，email 可能重複，回傳 id 最小的一個
+    async fn get_by_email(&self, email: &str) -> Result<Option<User>>;
+    async fn list(&self) -> Result<Vec<User>>;
+    // 符合 filter 的使用者，依 id 排序
+    // 預設的實作在記憶體中篩選，SQL 儲存層覆寫成在查詢中完成
+    async fn list_by_filter(&self, filter: &UserFilter) -> Result<Vec<User>> {
+        let users = self.list().await?;
+        Ok(users.into_iter().filter(|u| filter.matches(u)).collect())
+    }
+    async fn update(&self, id: u64, user_fu: UserForUpdate) -> Result<User>;
+    // 與 TicketStore 相同，服務關閉前呼叫
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// 群組與使用者存放在同一個儲存層，由同一個物件實作
+// id 由遞增的序號分配，刪除後不會被重新使用，避免 ticket 指派給已刪除的群組後被新的群組接手
+#[async_trait]
+pub trait GroupStore: Send + Sync {
+    async fn create(&self, group_fc: GroupForCreate) -> Result<Group>;
+    async fn get(&self, id: u64) -> Result<Group>;
+    // 依 id 排序
+    async fn list(&self) -> Result<Vec<Group>>;
+    // 顯示名稱完全相同的群組，依 id 排序，預設的實作與 UserStore::list_by_filter 相同
+    async fn list_by_display_name(&self, display_name: &str) -> Result<Vec<Group>> {
+        let groups = self.list().await?;
+        Ok(groups
+            .into_iter()
+            .filter(|g| g.display_name == display_name)
+            .collect())
+    }
+    // 包含 user_id 這個成員的群組，依 id 排序
+    async fn list_by_member(&self, user_id: u64) -> Result<Vec<Group>> {
+        let groups = self.list().await?;
+        Ok(groups
+            .into_iter()
+            .filter(|g| g.members.contains(&user_id))
+            .collect())
+    }
+    async fn update(&self, id: u64, group_fu: GroupForUpdate) -> Result<Group>;
+    async fn delete(&self, id: u64) -> Result<Group>;
+}
+
+// 邀請同樣與使用者存放在同一個儲存層，id 由遞增的序號分配，不會被重新使用
+#[async_trait]
+pub trait InvitationStore: Send + Sync {
+    async fn create(&self, invitation_fi: InvitationForInsert) -> Result<Invitation>;
+    async fn get(&self, id: u64) -> Result<Invitation>;
+    // 依 id 排序
+    async fn list(&self) -> Result<Vec<Invitation>>;
+    // 目前的狀態為 expected 時才修改狀態與接受者，檢查與修改在同一個操作中完成
+    // 狀態不符時回傳 InvitationNotPending
+    async fn update_status(
+        &self,
+        id: u64,
+        expected: InvitationStatus,
+        status: InvitationStatus,
+        accepted_by: Option<u64>,
+    ) -> Result<Invitation>;
+}
+
+// 簽章請求已經使用過的簽章，與使用者存放在同一個儲存層
+// SQL 儲存層存放在資料庫中，多個服務實例共用，重送到另一個實例時同樣會被拒絕
+#[async_trait]
+pub trait ReplayStore: Send + Sync {
+    // 簽章第一次出現時記錄下來並回傳 true，已經出現過時回傳 false
+    // 同時清除 seen_at 早於 expire_before 的紀錄，超出時間範圍的簽章本來就會被拒絕
+    async fn insert_signature(
+        &self,
+        signature: &str,
+        seen_at: u64,
+        expire_before: u64,
+    ) -> Result<bool>;
+}
+
+pub struct Stores {
+    pub tickets: Arc<dyn TicketStore>,
+    pub comments: Arc<dyn CommentStore>,
+    pub attachments: Arc<dyn AttachmentStore>,
+    pub history: Arc<dyn HistoryStore>,
+    pub blobs: Arc<dyn BlobStore>,
+    pub users: Arc<dyn UserStore>,
+    pub groups: Arc<dyn GroupStore>,
+    pub invitations: Arc<dyn InvitationStore>,
+    pub replays: Arc<dyn ReplayStore>,
+}
+
+// ticket、留言、附件資訊與異動紀錄由同一個物件實作
+type TicketStores = (
+    Arc<dyn TicketStore>,
+    Arc<dyn CommentStore>,
+    Arc<dyn AttachmentStore>,
+    Arc<dyn HistoryStore>,
+);
+
+fn ticket_stores<S>(store: S) -> TicketStores
+where
+    S: TicketStore + CommentStore + AttachmentStore + HistoryStore + 'static,
+{
+    let store = Arc::new(store);
+    (store.clone(), store.clone(), store.clone(), store)
+}
+
+// 使用者、群組、邀請與簽章由同一個物件實作
+type UserStores = (
+    Arc<dyn UserStore>,
+    Arc<dyn GroupStore>,
+    Arc<dyn InvitationStore>,
+    Arc<dyn ReplayStore>,
+);
+
+fn user_stores<S>(store: S) -> UserStores
+where
+    S: UserStore + GroupStore + InvitationStore + ReplayStore + 'static,
+{
+    let store = Arc::new(store);
+    (store.clone(), store.clone(), store.clone(), store)
+}
+
+// 依照 SERVICE_TICKET_STORE、SERVICE_USER_STORE 與 SERVICE_BLOB_STORE 建立對應的儲存層
+// ticket 與使用者使用相同的儲存方式時共用同一個物件（同一個資料檔或 connection pool）
+// ticket 會保存下來但使用者只存在記憶體中時拒絕啟動，否則重新啟動後 ticket 與 cookie 中的 user id 會對應到不同的人
+pub async fn stores_from_config() -> Result<Stores> {
+    let mut backends = Backends::default();
+    let (tickets, comments, attachments, history) = match config().ticket_store.as_str() {
+        // 有設定 SERVICE_JOURNAL_DIR 時，記憶體中的資料會透過 journal 與 snapshot 保存下來
+        "memory" => match config().journal_dir.as_deref() {
+            Some(dir) => ticket_stores(MemTicketStore::with_journal(
+                dir,
+                config().journal_snapshot_every,
+            )?),
+            None => ticket_stores(MemTicketStore::default()),
+        },
+        "sqlite" => ticket_stores(backends.sqlite().await?),
+        "postgres" => ticket_stores(backends.postgres().await?),
+        "kv" => ticket_stores(backends.kv()?),
+        other => {
+            return Err(Error::ConfigInvalidStore {
+                store: other.to_string(),
+            })
+        }
+    };
+    let (users, groups, invitations, replays) = match config().user_store.as_str() {
+        // 使用者的 journal 放在 journal 目錄底下的 users 目錄，與 ticket 的 journal 分開
+        "memory" => match config().journal_dir.as_deref() {
+            Some(dir) => user_stores(MemUserStore::with_journal(
+                Path::new(dir).join("users"),
+                config().journal_snapshot_every,
+            )?),
+            None if config().ticket_store != "memory" => {
+                return Err(Error::ConfigUserStoreNotPersistent {
+                    ticket_store: config().ticket_store.clone(),
+                    user_store: config().user_store.clone(),
+                })
+            }
+            None => user_stores(MemUserStore::default()),
+        },
+        "sqlite" => user_stores(backends.sqlite().await?),
+        "postgres" => user_stores(backends.postgres().await?),
+        "kv" => user_stores(backends.kv()?),
+        other => {
+            return Err(Error::ConfigInvalidStore {
+                store: other.to_string(),
+            })
+        }
+    };
+    let blobs: Arc<dyn BlobStore> = match config().blob_store.as_str() {
+        "memory" => Arc::new(MemBlobStore::default()),
+        "local" => {
+            let dir = config()
+                .blob_dir
+                .as_deref()
+                .ok_or(Error::ConfigMissingEnv {
+                    name: "SERVICE_BLOB_DIR",
+                })?;
+            Arc::new(LocalBlobStore::new(dir)?)
+        }
+        other => {
+            return Err(Error::ConfigInvalidStore {
+                store: other.to_string(),
+            })
+        }
+    };
+    Ok(Stores {
+        tickets,
+        comments,
+        attachments,
+        history,
+        blobs,
+        users,
+        groups,
+        invitations,
+        replays,
+    })
+}
+
+// 已經開啟的資料庫，第二次使用時回傳同一個物件
+// redb 同一個檔案只能被開啟一次；SQL 資料庫共用 connection pool，也只需要執行一次 migrations
+#[derive(Default)]
+struct Backends {
+    kv: Option<KvStore>,
+    sqlite: Option<SqliteTicketStore>,
+    postgres: Option<PgTicketStore>,
+}
+
+impl Backends {
+    fn kv(&mut self) -> Result<KvStore> {
+        if let Some(kv) = &self.kv {
+            return Ok(kv.clone());
+        }
+        let path = config().kv_path.as_deref().ok_or(Error::ConfigMissingEnv {
+            name: "SERVICE_KV_PATH",
+        })?;
+        Ok(self.kv.insert(KvStore::open(path)?).clone())
+    }
+
+    async fn sqlite(&mut self) -> Result<SqliteTicketStore> {
+        if let Some(sqlite) = &self.sqlite {
+            return Ok(sqlite.clone());
+        }
+        let store = SqliteTicketStore::new(db_url()?).await?;
+        Ok(self.sqlite.insert(store).clone())
+    }
+
+    async fn postgres(&mut self) -> Result<PgTicketStore> {
+        if let Some(postgres) = &self.postgres {
+            return Ok(postgres.clone());
+        }
+        let store = PgTicketStore::new(db_url()?, config().db_max_connections).await?;
+        Ok(self.postgres.insert(store).clone())
+    }
+}
+
+fn db_url() -> Result<&'static str> {
+    config().db_url.as_deref().ok_or(Error::ConfigMissingEnv {
+        name: "SERVICE_DB_URL",
+    })
+}
+
+// 儲存層的錯誤（連線、SQL 等）統一轉換成 Error::StoreFail，細節只會出現在 server log
+fn store_error(e: impl std::fmt::Display) -> Error {
+    Error::StoreFail {
+        detail: e.to_string(),
+    }
+}
+
+// SQL 儲存層寫入使用者時，username 違反 UNIQUE 限制的錯誤與其他儲存層相同回傳 UserCreateFailUsernameTaken
+fn user_write_error(e: sqlx::Error, username: &str) -> Error {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::UserCreateFailUsernameTaken {
+                username: username.to_string(),
+            }
+        }
+        _ => store_error(e),
+    }
+}
@@ -0,0 +1,87 @@
+// User 與 Invitation 序列化成 JSON 時會略過密碼與 nonce 等欄位，寫入 kv 或 journal 時使用這邊定義的完整格式
+use serde::{Deserialize, Serialize};
+
+use crate::model::{AuthSource, Invitation, InvitationStatus, Role, User};
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct UserRecord {
+    id: u64,
+    username: String,
+    email: String,
+    role: Role,
+    active: bool,
+    // 加入這個欄位之前建立的帳號都是本地帳號
+    #[serde(default)]
+    auth_source: AuthSource,
+    pwd_hash: String,
+}
+
+impl From<&User> for UserRecord {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            role: user.role,
+            active: user.active,
+            auth_source: user.auth_source,
+            pwd_hash: user.pwd_hash.clone(),
+        }
+    }
+}
+
+impl From<UserRecord> for User {
+    fn from(record: UserRecord) -> Self {
+        Self {
+            id: record.id,
+            username: record.username,
+            email: record.email,
+            role: record.role,
+            active: record.active,
+            auth_source: record.auth_source,
+            pwd_hash: record.pwd_hash,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct InvitationRecord {
+    id: u64,
+    cid: u64,
+    email: String,
+    role: Role,
+    status: InvitationStatus,
+    expires_at: u64,
+    accepted_by: Option<u64>,
+    nonce: String,
+}
+
+impl From<&Invitation> for InvitationRecord {
+    fn from(invitation: &Invitation) -> Self {
+        Self {
+            id: invitation.id,
+            cid: invitation.cid,
+            email: invitation.email.clone(),
+            role: invitation.role,
+            status: invitation.status,
+            expires_at: invitation.expires_at,
+            accepted_by: invitation.accepted_by,
+            nonce: invitation.nonce.clone(),
+        }
+    }
+}
+
+impl From<InvitationRecord> for Invitation {
+    fn from(record: InvitationRecord) -> Self {
+        Self {
+            id: record.id,
+            cid: record.cid,
+            email: record.email,
+            role: record.role,
+            status: record.status,
+            expires_at: record.expires_at,
+            accepted_by: record.accepted_by,
+            nonce: record.nonce,
+        }
+    }
+}
@@ -0,0 +1,1094 @@
+// 使用 SQLite 儲存 ticket、使用者、群組與邀請，資料會保存在檔案中，服務重新啟動後仍然存在
+// 也可以直接使用 sqlite3 等工具查看資料
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Sqlite};
+use std::str::FromStr;
+
+use super::{
+    store_error, user_write_error, AttachmentStore, CommentStore, GroupStore, HistoryStore,
+    InvitationStore, ReplayStore, TicketStore, UserStore,
+};
+use crate::model::{
+    Attachment, AttachmentForCreate, Comment, CommentForCreate, CommentForUpdate, Group,
+    GroupForCreate, GroupForUpdate, Invitation, InvitationForInsert, InvitationStatus, SortOrder,
+    Ticket, TicketAssignee, TicketCursor, TicketFilter, TicketForCreate, TicketForUpdate,
+    TicketHistory, TicketHistoryForCreate, TicketHistoryForInsert, TicketSort, TicketSortField,
+    TicketSortKey, User, UserFilter, UserForInsert, UserForUpdate,
+};
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+// migrations 會在編譯時被嵌入執行檔中，不需要額外部署 sql 檔案
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/sqlite");
+
+// ticket 與使用者使用同一個資料庫時共用同一個物件（connection pool）
+#[derive(Clone)]
+pub struct SqliteTicketStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTicketStore {
+    // 連線並執行尚未套用的 migrations，已經套用過的版本會被略過
+    pub async fn new(db_url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(db_url)
+            .map_err(store_error)?
+            .create_if_missing(true)
+            // SQLite 預設不會檢查 foreign key，需要開啟才能在刪除 ticket 時一併刪除留言與附件資訊
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(store_error)?;
+        MIGRATOR.run(&pool).await.map_err(store_error)?;
+        Ok(Self { pool })
+    }
+
+    // UPDATE 沒有修改到任何一筆時，重新查詢以區分是找不到 ticket 還是版本不符
+    async fn write_miss(&self, id: u64, expected_version: Option<u64>) -> Error {
+        match TicketStore::get(self, id).await {
+            Ok(ticket) => ticket
+                .check_version(expected_version)
+                .err()
+                .unwrap_or(Error::TicketNotFound { id }),
+            Err(e) => e,
+        }
+    }
+}
+
+// 所有查詢回傳的欄位，與 TicketRow 的欄位對應
+const TICKET_COLUMNS: &str =
+    "id, cid, title, description, status, priority, labels, assignee_type, assignee_id, \
+     created_at, updated_at, updated_by, deleted_at, deleted_by, version";
+
+// SQLite 的整數為 i64，這邊統一轉換成 model 使用的 u64
+// status、priority 以文字儲存，labels 以 JSON 陣列的文字儲存
+#[derive(sqlx::FromRow)]
+struct TicketRow {
+    id: i64,
+    cid: i64,
+    title: String,
+    description: String,
+    status: String,
+    priority: String,
+    labels: String,
+    assignee_type: Option<String>,
+    assignee_id: Option<i64>,
+    created_at: i64,
+    updated_at: Option<i64>,
+    updated_by: Option<i64>,
+    deleted_at: Option<i64>,
+    deleted_by: Option<i64>,
+    version: i64,
+}
+
+fn ticket_from_row(row: TicketRow) -> Result<Ticket> {
+    Ok(Ticket {
+        id: row.id as u64,
+        cid: row.cid as u64,
+        title: row.title,
+        description: row.description,
+        status: row.status.parse().map_err(store_error)?,
+        priority: row.priority.parse().map_err(store_error)?,
+        labels: serde_json::from_str(&row.labels).map_err(store_error)?,
+        assignee: match (row.assignee_type, row.assignee_id) {
+            (Some(kind), Some(id)) => {
+                Some(TicketAssignee::from_parts(&kind, id as u64).map_err(store_error)?)
+            }
+            _ => None,
+        },
+        created_at: row.created_at as u64,
+        updated_at: row.updated_at.map(|t| t as u64),
+        updated_by: row.updated_by.map(|u| u as u64),
+        deleted_at: row.deleted_at.map(|t| t as u64),
+        deleted_by: row.deleted_by.map(|u| u as u64),
+        version: row.version as u64,
+    })
+}
+
+// 留言查詢回傳的欄位，與 CommentRow 的欄位對應
+const COMMENT_COLUMNS: &str = "id, ticket_id, author_id, body, created_at, updated_at";
+
+#[derive(sqlx::FromRow)]
+struct CommentRow {
+    id: i64,
+    ticket_id: i64,
+    author_id: i64,
+    body: String,
+    created_at: i64,
+    updated_at: Option<i64>,
+}
+
+impl From<CommentRow> for Comment {
+    fn from(row: CommentRow) -> Self {
+        Comment {
+            id: row.id as u64,
+            ticket_id: row.ticket_id as u64,
+            author_id: row.author_id as u64,
+            body: row.body,
+            created_at: row.created_at as u64,
+            updated_at: row.updated_at.map(|t| t as u64),
+        }
+    }
+}
+
+// 附件資訊查詢回傳的欄位，與 AttachmentRow 的欄位對應
+const ATTACHMENT_COLUMNS: &str =
+    "id, ticket_id, uploader_id, filename, content_type, size, created_at";
+
+#[derive(sqlx::FromRow)]
+struct AttachmentRow {
+    id: i64,
+    ticket_id: i64,
+    uploader_id: i64,
+    filename: String,
+    content_type: String,
+    size: i64,
+    created_at: i64,
+}
+
+impl From<AttachmentRow> for Attachment {
+    fn from(row: AttachmentRow) -> Self {
+        Attachment {
+            id: row.id as u64,
+            ticket_id: row.ticket_id as u64,
+            uploader_id: row.uploader_id as u64,
+            filename: row.filename,
+            content_type: row.content_type,
+            size: row.size as u64,
+            created_at: row.created_at as u64,
+        }
+    }
+}
+
+// 異動紀錄查詢回傳的欄位，與 HistoryRow 的欄位對應
+const HISTORY_COLUMNS: &str = "id, ticket_id, actor_id, action, changes, created_at";
+
+// action 以文字儲存，changes 以 JSON 陣列的文字儲存
+#[derive(sqlx::FromRow)]
+struct HistoryRow {
+    id: i64,
+    ticket_id: i64,
+    actor_id: Option<i64>,
+    action: String,
+    changes: String,
+    created_at: i64,
+}
+
+fn history_from_row(row: HistoryRow) -> Result<TicketHistory> {
+    Ok(TicketHistory {
+        id: row.id as u64,
+        ticket_id: row.ticket_id as u64,
+        actor_id: row.actor_id.map(|u| u as u64),
+        action: row.action.parse().map_err(store_error)?,
+        changes: serde_json::from_str(&row.changes).map_err(store_error)?,
+        created_at: row.created_at as u64,
+    })
+}
+
+// 篩選條件，接在 "WHERE deleted_at IS NULL" 之後
+// labels 以 JSON 陣列的文字儲存，透過 json_each 展開後比對
+fn push_filter<'a>(query: &mut QueryBuilder<'a, Sqlite>, filter: &'a TicketFilter) {
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status.as_ref());
+    }
+    if let Some(priority) = &filter.priority {
+        query.push(" AND priority = ").push_bind(priority.as_ref());
+    }
+    if let Some(label) = &filter.label {
+        query
+            .push(" AND EXISTS (SELECT 1 FROM json_each(ticket.labels) WHERE value = ")
+            .push_bind(label.as_str())
+            .push(")");
+    }
+    if let Some(cid) = filter.creator {
+        query.push(" AND cid = ").push_bind(cid as i64);
+    }
+    if let Some(from) = filter.created_from {
+        query.push(" AND created_at >= ").push_bind(from as i64);
+    }
+    if let Some(to) = filter.created_to {
+        query.push(" AND created_at <= ").push_bind(to as i64);
+    }
+}
+
+// 排序使用的運算式，priority 與 status 依宣告的順序排序，與 model 中的排序一致
+fn sort_expr(field: TicketSortField) -> &'static str {
+    match field {
+        TicketSortField::Id => "id",
+        TicketSortField::CreatedAt => "created_at",
+        TicketSortField::UpdatedAt => "COALESCE(updated_at, created_at)",
+        TicketSortField::Priority => {
+            "CASE priority WHEN 'Low' THEN 0 WHEN 'Normal' THEN 1 WHEN 'High' THEN 2 ELSE 3 END"
+        }
+        TicketSortField::Status => {
+            "CASE status WHEN 'Open' THEN 0 WHEN 'InProgress' THEN 1 WHEN 'Resolved' THEN 2 ELSE 3 END"
+        }
+        TicketSortField::Title => "title",
+    }
+}
+
+// 游標之後的 ticket，以 (排序的值, id) 的 row value 比較，與 ORDER BY 的順序一致
+fn push_after(query: &mut QueryBuilder<'_, Sqlite>, sort: TicketSort, cursor: &TicketCursor) {
+    let op = match sort.order {
+        SortOrder::Asc => ">",
+        SortOrder::Desc => "<",
+    };
+    query.push(format!(" AND ({}, id) {op} (", sort_expr(sort.field)));
+    match &cursor.key {
+        TicketSortKey::Number(n) => query.push_bind(*n as i64),
+        TicketSortKey::Text(s) => query.push_bind(s.clone()),
+    };
+    query.push(", ").push_bind(cursor.id as i64).push(")");
+}
+
+fn sort_order(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    }
+}
+
+fn labels_to_json(labels: &BTreeSet<String>) -> Result<String> {
+    serde_json::to_string(labels).map_err(store_error)
+}
+
+// 在 ticket 異動的 transaction 中寫入異動紀錄，沒有需要記錄的內容（None）時略過
+async fn insert_history(
+    conn: &mut SqliteConnection,
+    history_fc: Option<TicketHistoryForCreate>,
+) -> Result<()> {
+    let Some(history_fc) = history_fc else {
+        return Ok(());
+    };
+    let changes = serde_json::to_string(&history_fc.changes).map_err(store_error)?;
+    sqlx::query(
+        "INSERT INTO ticket_history (ticket_id, actor_id, action, changes, created_at) \
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(history_fc.ticket_id as i64)
+    .bind(history_fc.actor_id.map(|u| u as i64))
+    .bind(history_fc.action.as_ref())
+    .bind(changes)
+    .bind(now_unix_secs() as i64)
+    .execute(conn)
+    .await
+    .map_err(store_error)?;
+    Ok(())
+}
+
+#[async_trait]
+impl TicketStore for SqliteTicketStore {
+    async fn create(
+        &self,
+        cid: u64,
+        ticket_fc: TicketForCreate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        let row: TicketRow = sqlx::query_as(&format!(
+            "INSERT INTO ticket (cid, title, description, priority, labels, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?) RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(cid as i64)
+        .bind(ticket_fc.title)
+        .bind(ticket_fc.description)
+        .bind(ticket_fc.priority.as_ref())
+        .bind(labels_to_json(&ticket_fc.labels)?)
+        .bind(now_unix_secs() as i64)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let ticket = ticket_from_row(row)?;
+        insert_history(&mut tx, history_fi.for_ticket(ticket.id)).await?;
+        tx.commit().await.map_err(store_error)?;
+        Ok(ticket)
+    }
+
+    async fn get(&self, id: u64) -> Result<Ticket> {
+        let row: Option<TicketRow> = sqlx::query_as(&format!(
+            "SELECT {TICKET_COLUMNS} FROM ticket WHERE id = ? AND deleted_at IS NULL"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        ticket_from_row(row.ok_or(Error::TicketNotFound { id })?)
+    }
+
+    async fn list(&self) -> Result<Vec<Ticket>> {
+        let rows: Vec<TicketRow> = sqlx::query_as(&format!(
+            "SELECT {TICKET_COLUMNS} FROM ticket WHERE deleted_at IS NULL ORDER BY id"
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(ticket_from_row).collect()
+    }
+
+    // 總數與該頁的資料分成兩個查詢，篩選條件相同
+    async fn list_page(
+        &self,
+        filter: &TicketFilter,
+        sort: TicketSort,
+        after: Option<&TicketCursor>,
+        limit: u64,
+        offset: u64,
+    ) -> Result<(Vec<Ticket>, u64)> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM ticket WHERE deleted_at IS NULL");
+        push_filter(&mut count, filter);
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        let mut query = QueryBuilder::new(format!(
+            "SELECT {TICKET_COLUMNS} FROM ticket WHERE deleted_at IS NULL"
+        ));
+        push_filter(&mut query, filter);
+        if let Some(cursor) = after {
+            push_after(&mut query, sort, cursor);
+        }
+        let order = sort_order(sort.order);
+        query
+            .push(format!(
+                " ORDER BY {} {order}, id {order} LIMIT ",
+                sort_expr(sort.field)
+            ))
+            .push_bind(limit as i64)
+            .push(" OFFSET ")
+            .push_bind(offset as i64);
+        let rows: Vec<TicketRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)?;
+        let tickets = rows
+            .into_iter()
+            .map(ticket_from_row)
+            .collect::<Result<_>>()?;
+        Ok((tickets, total as u64))
+    }
+
+    // 被指派的群組可能有很多個，以 IN 列出
+    async fn list_by_assignee(&self, user_id: u64, group_ids: &[u64]) -> Result<Vec<Ticket>> {
+        let mut query = QueryBuilder::new(format!(
+            "SELECT {TICKET_COLUMNS} FROM ticket WHERE deleted_at IS NULL \
+             AND ((assignee_type = 'User' AND assignee_id = "
+        ));
+        query.push_bind(user_id as i64).push(")");
+        if !group_ids.is_empty() {
+            query.push(" OR (assignee_type = 'Group' AND assignee_id IN (");
+            let mut ids = query.separated(", ");
+            for id in group_ids {
+                ids.push_bind(*id as i64);
+            }
+            query.push("))");
+        }
+        query.push(") ORDER BY id");
+        let rows: Vec<TicketRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)?;
+        rows.into_iter().map(ticket_from_row).collect()
+    }
+
+    // 沒有提供的欄位以 COALESCE 保留原本的值
+    // 指派對象可以被清除（設為 NULL），所以另外以一個 bool 參數表示是否要更新
+    // 版本的確認放在 WHERE 中，與修改在同一個 statement 完成
+    async fn update(
+        &self,
+        id: u64,
+        updated_by: u64,
+        expected_version: Option<u64>,
+        ticket_fu: TicketForUpdate,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        let (assignee_type, assignee_id) = match ticket_fu.assignee.flatten() {
+            Some(assignee) => (Some(assignee.kind()), Some(assignee.id() as i64)),
+            None => (None, None),
+        };
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        let row: Option<TicketRow> = sqlx::query_as(&format!(
+            "UPDATE ticket SET title = COALESCE(?, title), description = COALESCE(?, description), \
+             status = COALESCE(?, status), priority = COALESCE(?, priority), \
+             labels = COALESCE(?, labels), \
+             assignee_type = CASE WHEN ? THEN ? ELSE assignee_type END, \
+             assignee_id = CASE WHEN ? THEN ? ELSE assignee_id END, \
+             updated_at = ?, updated_by = ?, version = version + 1 \
+             WHERE id = ? AND deleted_at IS NULL AND (? IS NULL OR version = ?) \
+             RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(ticket_fu.title)
+        .bind(ticket_fu.description)
+        .bind(ticket_fu.status.as_ref().map(AsRef::<str>::as_ref))
+        .bind(ticket_fu.priority.as_ref().map(AsRef::<str>::as_ref))
+        .bind(ticket_fu.labels.as_ref().map(labels_to_json).transpose()?)
+        .bind(ticket_fu.assignee.is_some())
+        .bind(assignee_type)
+        .bind(ticket_fu.assignee.is_some())
+        .bind(assignee_id)
+        .bind(now_unix_secs() as i64)
+        .bind(updated_by as i64)
+        .bind(id as i64)
+        .bind(expected_version.map(|v| v as i64))
+        .bind(expected_version.map(|v| v as i64))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let Some(row) = row else {
+            tx.rollback().await.map_err(store_error)?;
+            return Err(self.write_miss(id, expected_version).await);
+        };
+        let ticket = ticket_from_row(row)?;
+        insert_history(&mut tx, history_fi.for_ticket(id)).await?;
+        tx.commit().await.map_err(store_error)?;
+        Ok(ticket)
+    }
+
+    async fn delete(
+        &self,
+        id: u64,
+        deleted_by: u64,
+        expected_version: Option<u64>,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Ticket> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        let row: Option<TicketRow> = sqlx::query_as(&format!(
+            "UPDATE ticket SET deleted_at = ?, deleted_by = ?, version = version + 1 \
+             WHERE id = ? AND deleted_at IS NULL AND (? IS NULL OR version = ?) \
+             RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(now_unix_secs() as i64)
+        .bind(deleted_by as i64)
+        .bind(id as i64)
+        .bind(expected_version.map(|v| v as i64))
+        .bind(expected_version.map(|v| v as i64))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let Some(row) = row else {
+            tx.rollback().await.map_err(store_error)?;
+            return Err(self.write_miss(id, expected_version).await);
+        };
+        let ticket = ticket_from_row(row)?;
+        let history_fi = history_fi.with_lifecycle(&ticket.before_delete(), Some(&ticket));
+        insert_history(&mut tx, history_fi.for_ticket(id)).await?;
+        tx.commit().await.map_err(store_error)?;
+        Ok(ticket)
+    }
+
+    async fn list_deleted(&self) -> Result<Vec<Ticket>> {
+        let rows: Vec<TicketRow> = sqlx::query_as(&format!(
+            "SELECT {TICKET_COLUMNS} FROM ticket WHERE deleted_at IS NOT NULL ORDER BY id"
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(ticket_from_row).collect()
+    }
+
+    async fn get_deleted(&self, id: u64) -> Result<Ticket> {
+        let row: Option<TicketRow> = sqlx::query_as(&format!(
+            "SELECT {TICKET_COLUMNS} FROM ticket WHERE id = ? AND deleted_at IS NOT NULL"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        ticket_from_row(row.ok_or(Error::TicketNotFound { id })?)
+    }
+
+    // RETURNING 只看得到修改後的值，所以先遞增 version（同時取得寫入鎖）讀出原本的 deleted_at 與
+    // deleted_by，再於同一個 transaction 中清除
+    async fn restore(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        let row: Option<TicketRow> = sqlx::query_as(&format!(
+            "UPDATE ticket SET version = version + 1 \
+             WHERE id = ? AND deleted_at IS NOT NULL RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let mut before = ticket_from_row(row.ok_or(Error::TicketNotFound { id })?)?;
+        before.version -= 1;
+        let row: TicketRow = sqlx::query_as(&format!(
+            "UPDATE ticket SET deleted_at = NULL, deleted_by = NULL \
+             WHERE id = ? RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(id as i64)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let ticket = ticket_from_row(row)?;
+        let history_fi = history_fi.with_lifecycle(&before, Some(&ticket));
+        insert_history(&mut tx, history_fi.for_ticket(id)).await?;
+        tx.commit().await.map_err(store_error)?;
+        Ok(ticket)
+    }
+
+    // 留言與附件資訊透過 foreign key 的 ON DELETE CASCADE 一併刪除
+    async fn purge(&self, id: u64, history_fi: TicketHistoryForInsert) -> Result<Ticket> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        let row: Option<TicketRow> = sqlx::query_as(&format!(
+            "DELETE FROM ticket WHERE id = ? AND deleted_at IS NOT NULL \
+             RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let ticket = ticket_from_row(row.ok_or(Error::TicketNotFound { id })?)?;
+        let history_fi = history_fi.with_lifecycle(&ticket, None);
+        insert_history(&mut tx, history_fi.for_ticket(id)).await?;
+        tx.commit().await.map_err(store_error)?;
+        Ok(ticket)
+    }
+
+    async fn purge_deleted_before(
+        &self,
+        cutoff: u64,
+        history_fi: TicketHistoryForInsert,
+    ) -> Result<Vec<Ticket>> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        let rows: Vec<TicketRow> = sqlx::query_as(&format!(
+            "DELETE FROM ticket WHERE deleted_at IS NOT NULL AND deleted_at <= ? \
+             RETURNING {TICKET_COLUMNS}"
+        ))
+        .bind(cutoff as i64)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(store_error)?;
+        let tickets = rows
+            .into_iter()
+            .map(ticket_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        for ticket in &tickets {
+            let history_fi = history_fi.clone().with_lifecycle(ticket, None);
+            insert_history(&mut tx, history_fi.for_ticket(ticket.id)).await?;
+        }
+        tx.commit().await.map_err(store_error)?;
+        Ok(tickets)
+    }
+}
+
+#[async_trait]
+impl CommentStore for SqliteTicketStore {
+    // ticket 已經被刪除時，foreign key 會讓 INSERT 失敗，所以先確認 ticket 存在以回傳 TicketNotFound
+    async fn create(
+        &self,
+        ticket_id: u64,
+        author_id: u64,
+        comment_fc: CommentForCreate,
+    ) -> Result<Comment> {
+        TicketStore::get(self, ticket_id).await?;
+        let row: CommentRow = sqlx::query_as(&format!(
+            "INSERT INTO comment (ticket_id, author_id, body, created_at) \
+             VALUES (?, ?, ?, ?) RETURNING {COMMENT_COLUMNS}"
+        ))
+        .bind(ticket_id as i64)
+        .bind(author_id as i64)
+        .bind(comment_fc.body)
+        .bind(now_unix_secs() as i64)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.into())
+    }
+
+    async fn get(&self, id: u64) -> Result<Comment> {
+        let row: Option<CommentRow> = sqlx::query_as(&format!(
+            "SELECT {COMMENT_COLUMNS} FROM comment WHERE id = ?"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.ok_or(Error::CommentNotFound { id })?.into())
+    }
+
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Comment>> {
+        let rows: Vec<CommentRow> = sqlx::query_as(&format!(
+            "SELECT {COMMENT_COLUMNS} FROM comment WHERE ticket_id = ? ORDER BY id"
+        ))
+        .bind(ticket_id as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(rows.into_iter().map(Comment::from).collect())
+    }
+
+    async fn update(&self, id: u64, comment_fu: CommentForUpdate) -> Result<Comment> {
+        let row: Option<CommentRow> = sqlx::query_as(&format!(
+            "UPDATE comment SET body = ?, updated_at = ? WHERE id = ? \
+             RETURNING {COMMENT_COLUMNS}"
+        ))
+        .bind(comment_fu.body)
+        .bind(now_unix_secs() as i64)
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.ok_or(Error::CommentNotFound { id })?.into())
+    }
+
+    async fn delete(&self, id: u64) -> Result<Comment> {
+        let row: Option<CommentRow> = sqlx::query_as(&format!(
+            "DELETE FROM comment WHERE id = ? RETURNING {COMMENT_COLUMNS}"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.ok_or(Error::CommentNotFound { id })?.into())
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for SqliteTicketStore {
+    async fn create(
+        &self,
+        ticket_id: u64,
+        uploader_id: u64,
+        attachment_fc: AttachmentForCreate,
+    ) -> Result<Attachment> {
+        TicketStore::get(self, ticket_id).await?;
+        let row: AttachmentRow = sqlx::query_as(&format!(
+            "INSERT INTO attachment (ticket_id, uploader_id, filename, content_type, size, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?) RETURNING {ATTACHMENT_COLUMNS}"
+        ))
+        .bind(ticket_id as i64)
+        .bind(uploader_id as i64)
+        .bind(attachment_fc.filename)
+        .bind(attachment_fc.content_type)
+        .bind(attachment_fc.size as i64)
+        .bind(now_unix_secs() as i64)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.into())
+    }
+
+    async fn get(&self, id: u64) -> Result<Attachment> {
+        let row: Option<AttachmentRow> = sqlx::query_as(&format!(
+            "SELECT {ATTACHMENT_COLUMNS} FROM attachment WHERE id = ?"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.ok_or(Error::AttachmentNotFound { id })?.into())
+    }
+
+    async fn list(&self, ticket_id: u64) -> Result<Vec<Attachment>> {
+        let rows: Vec<AttachmentRow> = sqlx::query_as(&format!(
+            "SELECT {ATTACHMENT_COLUMNS} FROM attachment WHERE ticket_id = ? ORDER BY id"
+        ))
+        .bind(ticket_id as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(rows.into_iter().map(Attachment::from).collect())
+    }
+
+    async fn delete(&self, id: u64) -> Result<Attachment> {
+        let row: Option<AttachmentRow> = sqlx::query_as(&format!(
+            "DELETE FROM attachment WHERE id = ? RETURNING {ATTACHMENT_COLUMNS}"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(row.ok_or(Error::AttachmentNotFound { id })?.into())
+    }
+}
+
+// 永久刪除 ticket 時不會刪除 ticket_history 中的紀錄
+#[async_trait]
+impl HistoryStore for SqliteTicketStore {
+    async fn list(&self, ticket_id: u64) -> Result<Vec<TicketHistory>> {
+        let rows: Vec<HistoryRow> = sqlx::query_as(&format!(
+            "SELECT {HISTORY_COLUMNS} FROM ticket_history WHERE ticket_id = ? ORDER BY id"
+        ))
+        .bind(ticket_id as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(history_from_row).collect()
+    }
+}
+
+// 使用者查詢回傳的欄位，與 UserRow 的欄位對應
+const USER_COLUMNS: &str = "id, username, email, role, active, auth_source, pwd_hash";
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: i64,
+    username: String,
+    email: String,
+    role: String,
+    active: bool,
+    auth_source: String,
+    pwd_hash: String,
+}
+
+fn user_from_row(row: UserRow) -> Result<User> {
+    Ok(User {
+        id: row.id as u64,
+        username: row.username,
+        email: row.email,
+        role: row.role.parse().map_err(store_error)?,
+        active: row.active,
+        auth_source: row.auth_source.parse().map_err(store_error)?,
+        pwd_hash: row.pwd_hash,
+    })
+}
+
+// username 的唯一性由 UNIQUE 限制確保，不需要先查詢
+#[async_trait]
+impl UserStore for SqliteTicketStore {
+    async fn create(&self, user_fi: UserForInsert) -> Result<User> {
+        let row: UserRow = sqlx::query_as(&format!(
+            "INSERT INTO app_user (username, email, role, active, auth_source, pwd_hash) \
+             VALUES (?, ?, ?, TRUE, ?, ?) RETURNING {USER_COLUMNS}"
+        ))
+        .bind(&user_fi.username)
+        .bind(user_fi.email)
+        .bind(user_fi.role.as_ref())
+        .bind(user_fi.auth_source.as_ref())
+        .bind(user_fi.pwd_hash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| user_write_error(e, &user_fi.username))?;
+        user_from_row(row)
+    }
+
+    async fn get(&self, id: u64) -> Result<Option<User>> {
+        let row: Option<UserRow> =
+            sqlx::query_as(&format!("SELECT {USER_COLUMNS} FROM app_user WHERE id = ?"))
+                .bind(id as i64)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(store_error)?;
+        row.map(user_from_row).transpose()
+    }
+
+    async fn get_by_username(&self, username: &str) -> Result<Option<User>> {
+        let row: Option<UserRow> = sqlx::query_as(&format!(
+            "SELECT {USER_COLUMNS} FROM app_user WHERE username = ?"
+        ))
+        .bind(username)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        row.map(user_from_row).transpose()
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<Option<User>> {
+        let row: Option<UserRow> = sqlx::query_as(&format!(
+            "SELECT {USER_COLUMNS} FROM app_user WHERE email = ? ORDER BY id LIMIT 1"
+        ))
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        row.map(user_from_row).transpose()
+    }
+
+    async fn list(&self) -> Result<Vec<User>> {
+        let rows: Vec<UserRow> =
+            sqlx::query_as(&format!("SELECT {USER_COLUMNS} FROM app_user ORDER BY id"))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(store_error)?;
+        rows.into_iter().map(user_from_row).collect()
+    }
+
+    // 與 UserFilter::matches 相同不分大小寫，lower(username)、lower(email) 都有索引
+    async fn list_by_filter(&self, filter: &UserFilter) -> Result<Vec<User>> {
+        let (column, value) = match filter {
+            UserFilter::Username(username) => ("username", username),
+            UserFilter::Email(email) => ("email", email),
+        };
+        let rows: Vec<UserRow> = sqlx::query_as(&format!(
+            "SELECT {USER_COLUMNS} FROM app_user WHERE lower({column}) = lower(?) ORDER BY id"
+        ))
+        .bind(value)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(user_from_row).collect()
+    }
+
+    async fn update(&self, id: u64, user_fu: UserForUpdate) -> Result<User> {
+        let username = user_fu.username.clone().unwrap_or_default();
+        let row: Option<UserRow> = sqlx::query_as(&format!(
+            "UPDATE app_user SET username = COALESCE(?, username), email = COALESCE(?, email), \
+             role = COALESCE(?, role), active = COALESCE(?, active), \
+             pwd_hash = COALESCE(?, pwd_hash) \
+             WHERE id = ? RETURNING {USER_COLUMNS}"
+        ))
+        .bind(user_fu.username)
+        .bind(user_fu.email)
+        .bind(user_fu.role.as_ref().map(AsRef::<str>::as_ref))
+        .bind(user_fu.active)
+        .bind(user_fu.pwd_hash)
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| user_write_error(e, &username))?;
+        user_from_row(row.ok_or(Error::UserNotFound { id })?)
+    }
+}
+
+// 群組查詢回傳的欄位，與 GroupRow 的欄位對應
+const GROUP_COLUMNS: &str = "id, display_name, members";
+
+#[derive(sqlx::FromRow)]
+struct GroupRow {
+    id: i64,
+    display_name: String,
+    members: String,
+}
+
+fn group_from_row(row: GroupRow) -> Result<Group> {
+    Ok(Group {
+        id: row.id as u64,
+        display_name: row.display_name,
+        members: serde_json::from_str(&row.members).map_err(store_error)?,
+    })
+}
+
+fn members_to_json(members: &[u64]) -> Result<String> {
+    serde_json::to_string(members).map_err(store_error)
+}
+
+#[async_trait]
+impl GroupStore for SqliteTicketStore {
+    async fn create(&self, group_fc: GroupForCreate) -> Result<Group> {
+        // dedup 等整理由 into_group 處理，id 先以 0 代替，寫入後再以資料庫分配的 id 為準
+        let group = group_fc.into_group(0);
+        let row: GroupRow = sqlx::query_as(&format!(
+            "INSERT INTO user_group (display_name, members) VALUES (?, ?) \
+             RETURNING {GROUP_COLUMNS}"
+        ))
+        .bind(group.display_name)
+        .bind(members_to_json(&group.members)?)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(store_error)?;
+        group_from_row(row)
+    }
+
+    async fn get(&self, id: u64) -> Result<Group> {
+        let row: Option<GroupRow> = sqlx::query_as(&format!(
+            "SELECT {GROUP_COLUMNS} FROM user_group WHERE id = ?"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        group_from_row(row.ok_or(Error::GroupNotFound { id })?)
+    }
+
+    async fn list(&self) -> Result<Vec<Group>> {
+        let rows: Vec<GroupRow> = sqlx::query_as(&format!(
+            "SELECT {GROUP_COLUMNS} FROM user_group ORDER BY id"
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(group_from_row).collect()
+    }
+
+    async fn list_by_display_name(&self, display_name: &str) -> Result<Vec<Group>> {
+        let rows: Vec<GroupRow> = sqlx::query_as(&format!(
+            "SELECT {GROUP_COLUMNS} FROM user_group WHERE display_name = ? ORDER BY id"
+        ))
+        .bind(display_name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(group_from_row).collect()
+    }
+
+    // 成員以 JSON 陣列儲存，在查詢中展開比對
+    async fn list_by_member(&self, user_id: u64) -> Result<Vec<Group>> {
+        let rows: Vec<GroupRow> = sqlx::query_as(&format!(
+            "SELECT {GROUP_COLUMNS} FROM user_group WHERE EXISTS (SELECT 1 FROM json_each(members) WHERE value = ?) ORDER BY id"
+        ))
+        .bind(user_id as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(group_from_row).collect()
+    }
+
+    // 成員的增減依賴目前的成員，以讀出的內容當作 UPDATE 的條件
+    // 期間被其他人修改時不會寫入，重新讀取後再套用一次
+    async fn update(&self, id: u64, group_fu: GroupForUpdate) -> Result<Group> {
+        loop {
+            let mut group = GroupStore::get(self, id).await?;
+            let members = members_to_json(&group.members)?;
+            let display_name = group.display_name.clone();
+            group_fu.clone().apply_to(&mut group);
+            let updated = sqlx::query(
+                "UPDATE user_group SET display_name = ?, members = ? \
+                 WHERE id = ? AND display_name = ? AND members = ?",
+            )
+            .bind(&group.display_name)
+            .bind(members_to_json(&group.members)?)
+            .bind(id as i64)
+            .bind(display_name)
+            .bind(members)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+            if updated.rows_affected() > 0 {
+                return Ok(group);
+            }
+        }
+    }
+
+    async fn delete(&self, id: u64) -> Result<Group> {
+        let row: Option<GroupRow> = sqlx::query_as(&format!(
+            "DELETE FROM user_group WHERE id = ? RETURNING {GROUP_COLUMNS}"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        group_from_row(row.ok_or(Error::GroupNotFound { id })?)
+    }
+}
+
+// 邀請查詢回傳的欄位，與 InvitationRow 的欄位對應
+const INVITATION_COLUMNS: &str = "id, cid, email, role, status, expires_at, accepted_by, nonce";
+
+#[derive(sqlx::FromRow)]
+struct InvitationRow {
+    id: i64,
+    cid: i64,
+    email: String,
+    role: String,
+    status: String,
+    expires_at: i64,
+    accepted_by: Option<i64>,
+    nonce: String,
+}
+
+fn invitation_from_row(row: InvitationRow) -> Result<Invitation> {
+    Ok(Invitation {
+        id: row.id as u64,
+        cid: row.cid as u64,
+        email: row.email,
+        role: row.role.parse().map_err(store_error)?,
+        status: row.status.parse().map_err(store_error)?,
+        expires_at: row.expires_at as u64,
+        accepted_by: row.accepted_by.map(|u| u as u64),
+        nonce: row.nonce,
+    })
+}
+
+#[async_trait]
+impl InvitationStore for SqliteTicketStore {
+    async fn create(&self, invitation_fi: InvitationForInsert) -> Result<Invitation> {
+        let row: InvitationRow = sqlx::query_as(&format!(
+            "INSERT INTO invitation (cid, email, role, status, expires_at, nonce) \
+             VALUES (?, ?, ?, ?, ?, ?) RETURNING {INVITATION_COLUMNS}"
+        ))
+        .bind(invitation_fi.cid as i64)
+        .bind(invitation_fi.email)
+        .bind(invitation_fi.role.as_ref())
+        .bind(InvitationStatus::Pending.as_ref())
+        .bind(invitation_fi.expires_at as i64)
+        .bind(invitation_fi.nonce)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(store_error)?;
+        invitation_from_row(row)
+    }
+
+    async fn get(&self, id: u64) -> Result<Invitation> {
+        let row: Option<InvitationRow> = sqlx::query_as(&format!(
+            "SELECT {INVITATION_COLUMNS} FROM invitation WHERE id = ?"
+        ))
+        .bind(id as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        invitation_from_row(row.ok_or(Error::InvitationNotFound { id })?)
+    }
+
+    async fn list(&self) -> Result<Vec<Invitation>> {
+        let rows: Vec<InvitationRow> = sqlx::query_as(&format!(
+            "SELECT {INVITATION_COLUMNS} FROM invitation ORDER BY id"
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter().map(invitation_from_row).collect()
+    }
+
+    // 狀態的確認放在 WHERE 中，與修改在同一個 statement 完成
+    async fn update_status(
+        &self,
+        id: u64,
+        expected: InvitationStatus,
+        status: InvitationStatus,
+        accepted_by: Option<u64>,
+    ) -> Result<Invitation> {
+        let row: Option<InvitationRow> = sqlx::query_as(&format!(
+            "UPDATE invitation SET status = ?, accepted_by = ? WHERE id = ? AND status = ? \
+             RETURNING {INVITATION_COLUMNS}"
+        ))
+        .bind(status.as_ref())
+        .bind(accepted_by.map(|u| u as i64))
+        .bind(id as i64)
+        .bind(expected.as_ref())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(store_error)?;
+        match row {
+            Some(row) => invitation_from_row(row),
+            // 找不到或狀態不符，重新查詢以回傳對應的錯誤
+            None => Err(InvitationStore::get(self, id)
+                .await
+                .err()
+                .unwrap_or(Error::InvitationNotPending { id })),
+        }
+    }
+}
+
+// 先清除過期的紀錄，再以 ON CONFLICT DO NOTHING 寫入，有寫入一筆代表簽章第一次出現
+// signature 為 PRIMARY KEY，多個服務實例同時寫入同一個簽章時只有一個會成功
+#[async_trait]
+impl ReplayStore for SqliteTicketStore {
+    async fn insert_signature(
+        &self,
+        signature: &str,
+        seen_at: u64,
+        expire_before: u64,
+    ) -> Result<bool> {
+        sqlx::query("DELETE FROM request_signature WHERE seen_at < ?")
+            .bind(expire_before as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+        let result = sqlx::query(
+            "INSERT INTO request_signature (signature, seen_at) VALUES (?, ?) \
+             ON CONFLICT (signature) DO NOTHING",
+        )
+        .bind(signature)
+        .bind(seen_at as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(store_error)?;
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,923 @@
+// ticket 相關的資料定義與操作
+// 實際的資料存取交給 TicketStore，這邊負責與 Ctx 相關的邏輯
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::attachment::ticket_blob_dir;
+use super::history::{diff_tickets, TicketHistoryAction, TicketHistoryForInsert};
+use super::{require_admin, ModelController};
+use crate::config::config;
+use crate::crypt::{self, TokenKind};
+use crate::ctx::Ctx;
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+// ticket 目前的處理狀態，排序時依宣告的順序（處理的進度）
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    strum_macros::AsRefStr,
+)]
+pub enum TicketStatus {
+    #[default]
+    Open,
+    InProgress,
+    Resolved,
+    Closed,
+}
+
+// 排序時依宣告的順序，由低到高
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    strum_macros::AsRefStr,
+)]
+pub enum TicketPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+// 資料庫中以文字儲存，讀取時再轉換回來，與 JSON 中的表示方式相同
+impl FromStr for TicketStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "Open" => Ok(Self::Open),
+            "InProgress" => Ok(Self::InProgress),
+            "Resolved" => Ok(Self::Resolved),
+            "Closed" => Ok(Self::Closed),
+            _ => Err(format!("unknown ticket status: {s}")),
+        }
+    }
+}
+
+impl FromStr for TicketPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "Low" => Ok(Self::Low),
+            "Normal" => Ok(Self::Normal),
+            "High" => Ok(Self::High),
+            "Urgent" => Ok(Self::Urgent),
+            _ => Err(format!("unknown ticket priority: {s}")),
+        }
+    }
+}
+
+// ticket 可以指派給某個使用者，或是整個群組（群組中的所有成員都視為被指派）
+// JSON 的格式為 {"type": "User", "id": 2}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id")]
+pub enum TicketAssignee {
+    User(u64),
+    Group(u64),
+}
+
+impl TicketAssignee {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::User(_) => "User",
+            Self::Group(_) => "Group",
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::User(id) | Self::Group(id) => *id,
+        }
+    }
+
+    // 資料庫中分成 type 與 id 兩個欄位儲存，讀取時再組合回來
+    pub fn from_parts(kind: &str, id: u64) -> std::result::Result<Self, String> {
+        match kind {
+            "User" => Ok(Self::User(id)),
+            "Group" => Ok(Self::Group(id)),
+            _ => Err(format!("unknown assignee type: {kind}")),
+        }
+    }
+}
+
+// serde(default) 讓加入新欄位之前寫入的 journal、kv 資料仍然可以讀取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ticket {
+    pub id: u64,
+    pub cid: u64, // creator user_id
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub status: TicketStatus,
+    #[serde(default)]
+    pub priority: TicketPriority,
+    // 使用 BTreeSet，同一個 label 只會出現一次，輸出時也會依字母排序
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+    #[serde(default)]
+    pub assignee: Option<TicketAssignee>,
+    // 建立的時間（Unix 秒），加入這個欄位之前建立的 ticket 為 0
+    #[serde(default)]
+    pub created_at: u64,
+    // 最後一次修改的時間（Unix 秒）與修改者，從未修改過時為 None
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    #[serde(default)]
+    pub updated_by: Option<u64>,
+    // 移到垃圾桶的時間（Unix 秒）與刪除者，不在垃圾桶中時為 None
+    #[serde(default)]
+    pub deleted_at: Option<u64>,
+    #[serde(default)]
+    pub deleted_by: Option<u64>,
+    // 每次修改（包含移到垃圾桶與還原）都會遞增，web 層以 ETag 回傳，修改時透過 If-Match 確認沒有被其他人改過
+    #[serde(default = "first_version")]
+    pub version: u64,
+}
+
+// 新建立的 ticket 以及加入 version 之前寫入的資料都從 1 開始
+fn first_version() -> u64 {
+    1
+}
+
+impl Ticket {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    // 直接指派給 user_id，或指派給 group_ids 其中一個群組
+    pub(in crate::model) fn is_assigned_to(&self, user_id: u64, group_ids: &[u64]) -> bool {
+        match self.assignee {
+            Some(TicketAssignee::User(id)) => id == user_id,
+            Some(TicketAssignee::Group(id)) => group_ids.contains(&id),
+            None => false,
+        }
+    }
+
+    // 儲存層共用：移到垃圾桶與從垃圾桶還原
+    pub(in crate::model) fn mark_deleted(&mut self, deleted_by: u64) {
+        self.deleted_at = Some(now_unix_secs());
+        self.deleted_by = Some(deleted_by);
+        self.version += 1;
+    }
+
+    pub(in crate::model) fn mark_restored(&mut self) {
+        self.deleted_at = None;
+        self.deleted_by = None;
+        self.version += 1;
+    }
+
+    // 儲存層共用：SQL 的 RETURNING 只看得到移到垃圾桶之後的值，由此推回移動之前的狀態
+    // 條件中已經限定 deleted_at 為 NULL，所以修改前一定不在垃圾桶中
+    pub(in crate::model) fn before_delete(&self) -> Ticket {
+        Ticket {
+            deleted_at: None,
+            deleted_by: None,
+            version: self.version - 1,
+            ..self.clone()
+        }
+    }
+
+    // 儲存層共用：在修改前確認版本，expected 為 None 時不檢查
+    pub(in crate::model) fn check_version(&self, expected: Option<u64>) -> Result<()> {
+        match expected {
+            Some(expected) if expected != self.version => Err(Error::TicketFailVersionMismatch {
+                id: self.id,
+                expected,
+                actual: self.version,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// 新的 ticket 一律從 Open 開始，其餘欄位沒有提供時使用預設值
+#[derive(Clone, Deserialize)]
+pub struct TicketForCreate {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub priority: TicketPriority,
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+}
+
+// 狀態轉換使用，需要符合設定的流程
+#[derive(Deserialize)]
+pub struct TicketForTransition {
+    pub status: TicketStatus,
+}
+
+// 指派使用，傳入 {"type": "User", "id": 2} 或 {"type": "Group", "id": 1}
+pub type TicketForAssign = TicketAssignee;
+
+// 目前的狀態與可以轉換到的狀態，讓 client 知道有哪些操作可以使用
+#[derive(Serialize)]
+pub struct TicketTransitions {
+    pub status: TicketStatus,
+    pub allowed: Vec<TicketStatus>,
+}
+
+// PATCH 使用，只有 Some 的欄位會被更新，labels 為整批取代
+#[derive(Clone, Default, Deserialize)]
+pub struct TicketForUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<TicketStatus>,
+    pub priority: Option<TicketPriority>,
+    pub labels: Option<BTreeSet<String>>,
+    // Some(None) 代表取消指派，只能透過指派的 API 修改，PATCH 的 JSON 中不接受這個欄位
+    #[serde(skip)]
+    pub assignee: Option<Option<TicketAssignee>>,
+}
+
+// 列表的篩選條件，由 query string 取得，例如 /api/tickets?status=Open&label=billing&creator=2
+// created_from、created_to 為建立時間（Unix 秒）的範圍，兩端都包含在內，沒有提供的條件不篩選
+#[derive(Debug, Default, Deserialize)]
+pub struct TicketFilter {
+    pub status: Option<TicketStatus>,
+    pub priority: Option<TicketPriority>,
+    pub label: Option<String>,
+    pub creator: Option<u64>,
+    pub created_from: Option<u64>,
+    pub created_to: Option<u64>,
+}
+
+impl TicketFilter {
+    // 儲存層共用：在記憶體中篩選時使用
+    pub(in crate::model) fn matches(&self, ticket: &Ticket) -> bool {
+        self.status.is_none_or(|s| ticket.status == s)
+            && self.priority.is_none_or(|p| ticket.priority == p)
+            && self
+                .label
+                .as_ref()
+                .is_none_or(|l| ticket.labels.contains(l))
+            && self.creator.is_none_or(|cid| ticket.cid == cid)
+            && self.created_from.is_none_or(|t| ticket.created_at >= t)
+            && self.created_to.is_none_or(|t| ticket.created_at <= t)
+    }
+}
+
+// 列表可以排序的欄位，query string 中以 snake_case 表示，例如 sort=created_at
+// 從未修改過的 ticket，updated_at 以建立的時間排序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketSortField {
+    #[default]
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    Priority,
+    Status,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+// 排序方式，例如 /api/tickets?sort=priority&order=desc
+// 排序的值相同時再依 id 排序（方向相同），讓每次查詢的順序都是固定的
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TicketSort {
+    #[serde(default, rename = "sort")]
+    pub field: TicketSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+impl TicketSort {
+    // 儲存層共用：在記憶體中排序時使用
+    pub(in crate::model) fn compare(&self, a: &Ticket, b: &Ticket) -> Ordering {
+        let ordering = match self.field {
+            TicketSortField::Id => Ordering::Equal,
+            TicketSortField::CreatedAt => a.created_at.cmp(&b.created_at),
+            TicketSortField::UpdatedAt => a
+                .updated_at
+                .unwrap_or(a.created_at)
+                .cmp(&b.updated_at.unwrap_or(b.created_at)),
+            TicketSortField::Priority => a.priority.cmp(&b.priority),
+            TicketSortField::Status => a.status.cmp(&b.status),
+            TicketSortField::Title => a.title.cmp(&b.title),
+        }
+        .then(a.id.cmp(&b.id));
+        match self.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+
+    // 排序使用的值，priority 與 status 以宣告的順序表示，與 compare 的結果一致
+    fn sort_key(&self, ticket: &Ticket) -> TicketSortKey {
+        match self.field {
+            TicketSortField::Id => TicketSortKey::Number(ticket.id),
+            TicketSortField::CreatedAt => TicketSortKey::Number(ticket.created_at),
+            TicketSortField::UpdatedAt => {
+                TicketSortKey::Number(ticket.updated_at.unwrap_or(ticket.created_at))
+            }
+            TicketSortField::Priority => TicketSortKey::Number(ticket.priority as u64),
+            TicketSortField::Status => TicketSortKey::Number(ticket.status as u64),
+            TicketSortField::Title => TicketSortKey::Text(ticket.title.clone()),
+        }
+    }
+
+    // 儲存層共用：ticket 是否排在游標所指的位置之後
+    pub(in crate::model) fn is_after(&self, ticket: &Ticket, cursor: &TicketCursor) -> bool {
+        let ordering = (self.sort_key(ticket), ticket.id).cmp(&(cursor.key.clone(), cursor.id));
+        match self.order {
+            SortOrder::Asc => ordering == Ordering::Greater,
+            SortOrder::Desc => ordering == Ordering::Less,
+        }
+    }
+}
+
+// 分頁，例如 /api/tickets?limit=20&offset=40，limit 沒有提供時使用預設值，超過上限時以上限計算
+// 也可以改用上一頁回傳的 next_cursor，例如 /api/tickets?limit=20&cursor=...，這時不能同時指定 offset
+#[derive(Debug, Default, Deserialize)]
+pub struct TicketPageParams {
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub offset: u64,
+    pub cursor: Option<String>,
+}
+
+pub(in crate::model) const DEFAULT_PAGE_LIMIT: u64 = 50;
+pub(in crate::model) const MAX_PAGE_LIMIT: u64 = 200;
+// 沒有指定版本的修改在檢查期間被其他人搶先修改時，重新檢查的次數上限
+const UPDATE_MAX_RETRIES: usize = 3;
+
+impl TicketPageParams {
+    fn limit(&self) -> u64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+}
+
+// 列表的回應，total 為符合篩選條件的總數，沒有下一頁時 next_offset、next_cursor 為 None
+// 以游標查詢時 offset 與 next_offset 都是 None
+#[derive(Debug, Serialize)]
+pub struct TicketPage {
+    pub items: Vec<Ticket>,
+    pub total: u64,
+    pub limit: u64,
+    pub offset: Option<u64>,
+    pub next_offset: Option<u64>,
+    pub next_cursor: Option<String>,
+}
+
+// 排序的值，數字類的欄位（包含 priority、status 的順序）與文字分開表示
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TicketSortKey {
+    Number(u64),
+    Text(String),
+}
+
+// 游標指向上一頁的最後一個 ticket，下一頁從排序在它之後的 ticket 開始
+// 以排序的值與 id 定位，而不是位置，中間有 ticket 新增或刪除時也不會重複或遺漏
+// 游標只能搭配建立時的排序方式使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketCursor {
+    sort: TicketSort,
+    pub(in crate::model) key: TicketSortKey,
+    pub(in crate::model) id: u64,
+}
+
+impl TicketCursor {
+    fn after(sort: TicketSort, ticket: &Ticket) -> Self {
+        Self {
+            sort,
+            key: sort.sort_key(ticket),
+            id: ticket.id,
+        }
+    }
+
+    // 格式為 `[base64url(JSON)].[signature]`，對 client 來說是不透明的字串
+    fn encode(&self) -> Result<String> {
+        let json = serde_json::to_vec(self).map_err(|_| Error::TicketFailCursorInvalid)?;
+        let content = URL_SAFE_NO_PAD.encode(json);
+        let sign = crypt::sign(TokenKind::Cursor, &content);
+        Ok(format!("{content}.{sign}"))
+    }
+
+    // 先驗證簽章再解析內容，被竄改或格式不正確的游標都回傳 TicketFailCursorInvalid
+    fn decode(token: &str) -> Result<Self> {
+        let (content, sign) = token
+            .split_once('.')
+            .ok_or(Error::TicketFailCursorInvalid)?;
+        crypt::verify(TokenKind::Cursor, content, sign)
+            .map_err(|_| Error::TicketFailCursorInvalid)?;
+        let json = URL_SAFE_NO_PAD
+            .decode(content)
+            .map_err(|_| Error::TicketFailCursorInvalid)?;
+        serde_json::from_slice(&json).map_err(|_| Error::TicketFailCursorInvalid)
+    }
+}
+
+// 儲存層共用：以分配好的 id 建立 Ticket
+impl TicketForCreate {
+    pub(in crate::model) fn into_ticket(self, id: u64, cid: u64) -> Ticket {
+        Ticket {
+            id,
+            cid,
+            title: self.title,
+            description: self.description,
+            status: TicketStatus::Open,
+            priority: self.priority,
+            labels: self.labels,
+            assignee: None,
+            created_at: now_unix_secs(),
+            updated_at: None,
+            updated_by: None,
+            deleted_at: None,
+            deleted_by: None,
+            version: first_version(),
+        }
+    }
+}
+
+// 儲存層共用：將 TicketForUpdate 套用到既有的 Ticket 上
+impl TicketForUpdate {
+    pub(in crate::model) fn apply_to(self, ticket: &mut Ticket, updated_by: u64) {
+        if let Some(title) = self.title {
+            ticket.title = title;
+        }
+        if let Some(description) = self.description {
+            ticket.description = description;
+        }
+        if let Some(status) = self.status {
+            ticket.status = status;
+        }
+        if let Some(priority) = self.priority {
+            ticket.priority = priority;
+        }
+        if let Some(labels) = self.labels {
+            ticket.labels = labels;
+        }
+        if let Some(assignee) = self.assignee {
+            ticket.assignee = assignee;
+        }
+        ticket.updated_at = Some(now_unix_secs());
+        ticket.updated_by = Some(updated_by);
+        ticket.version += 1;
+    }
+}
+
+// CRUD Implementation
+// 將對資料的CRUD操作都定義在資料層，可以讓外部獲取資料的API統一，而內部運作的邏輯可以隨時更改，只要確保回傳數值一致就好
+// 建立、修改、刪除等異動都會連同一筆異動紀錄交給儲存層一起寫入，完成後同步更新全文檢索的索引
+impl ModelController {
+    pub async fn create_ticket(&self, ctx: Ctx, ticket_fc: TicketForCreate) -> Result<Ticket> {
+        // id 由儲存層分配，比較的欄位不包含 id，先以 0 建立要寫入的內容
+        let created = ticket_fc.clone().into_ticket(0, ctx.user_id());
+        let history_fi = TicketHistoryForInsert {
+            actor_id: Some(ctx.user_id()),
+            action: TicketHistoryAction::Create,
+            changes: diff_tickets(None, &created),
+        };
+        let ticket = self
+            .tickets_store
+            .create(ctx.user_id(), ticket_fc, history_fi)
+            .await?;
+        self.index_ticket(&ticket)?;
+        Ok(ticket)
+    }
+    // 讀取不限制可見範圍：所有登入的使用者都可以查詢、列出與檢索所有不在垃圾桶中的 ticket
+    // 登入由 mw_require_auth 確保，所以讀取不需要 Ctx；修改、刪除等異動才依 Ctx 檢查權限
+    pub async fn get_ticket(&self, id: u64) -> Result<Ticket> {
+        self.tickets_store.get(id).await
+    }
+    // 篩選、排序與分頁都交給儲存層處理，資料庫可以直接在查詢中完成
+    // 多取一筆來判斷是否還有下一頁，有的話以這一頁的最後一筆建立 next_cursor
+    pub async fn list_tickets(
+        &self,
+        filter: TicketFilter,
+        sort: TicketSort,
+        page: TicketPageParams,
+    ) -> Result<TicketPage> {
+        let limit = page.limit();
+        let cursor = match &page.cursor {
+            Some(_) if page.offset > 0 => return Err(Error::TicketFailCursorWithOffset),
+            Some(token) => {
+                let cursor = TicketCursor::decode(token)?;
+                if cursor.sort != sort {
+                    return Err(Error::TicketFailCursorSortMismatch);
+                }
+                Some(cursor)
+            }
+            None => None,
+        };
+        let offset = if cursor.is_some() { 0 } else { page.offset };
+        let (mut items, total) = self
+            .tickets_store
+            .list_page(&filter, sort, cursor.as_ref(), limit + 1, offset)
+            .await?;
+        let has_more = items.len() as u64 > limit;
+        items.truncate(limit as usize);
+        let next_cursor = match items.last() {
+            Some(last) if has_more => Some(TicketCursor::after(sort, last).encode()?),
+            _ => None,
+        };
+        let offset = cursor.is_none().then_some(offset);
+        Ok(TicketPage {
+            total,
+            limit,
+            offset,
+            next_offset: offset.filter(|_| has_more).map(|o| o + items.len() as u64),
+            next_cursor,
+            items,
+        })
+    }
+    // 指派給自己，或是指派給自己所屬群組的 ticket
+    pub async fn list_assigned_tickets(&self, ctx: Ctx) -> Result<Vec<Ticket>> {
+        let user_id = ctx.user_id();
+        let group_ids: Vec<u64> = self
+            .groups_of_user(user_id)
+            .await?
+            .iter()
+            .map(|g| g.id)
+            .collect();
+        self.tickets_store
+            .list_by_assignee(user_id, &group_ids)
+            .await
+    }
+    // 建立者、被指派的人（包含被指派群組的成員）以及管理者可以修改 ticket
+    // 狀態的改變需要符合設定的流程
+    // expected_version 為 Some 時，只有在目前的版本相同時才會修改（由 web 層的 If-Match 取得）
+    // 儲存層一律以檢查權限與流程時讀到的版本修改，期間被其他人修改時不會套用在新的狀態上
+    // 沒有指定版本時以最新的狀態重新檢查，多次仍然衝突時回傳錯誤
+    pub async fn update_ticket(
+        &self,
+        ctx: Ctx,
+        id: u64,
+        expected_version: Option<u64>,
+        ticket_fu: TicketForUpdate,
+    ) -> Result<Ticket> {
+        let mut retries = 0;
+        let updated = loop {
+            match self
+                .try_update_ticket(&ctx, id, expected_version, ticket_fu.clone())
+                .await
+            {
+                Err(Error::TicketFailVersionMismatch { .. })
+                    if expected_version.is_none() && retries < UPDATE_MAX_RETRIES =>
+                {
+                    retries += 1;
+                }
+                Err(Error::TicketFailVersionMismatch { .. }) if expected_version.is_none() => {
+                    return Err(Error::TicketFailUpdateConflict { id });
+                }
+                result => break result?,
+            }
+        };
+        self.index_ticket(&updated)?;
+        Ok(updated)
+    }
+    // 儲存層以讀到的版本修改，修改後的內容與在讀到的 ticket 上套用 ticket_fu 相同，以此記錄異動
+    async fn try_update_ticket(
+        &self,
+        ctx: &Ctx,
+        id: u64,
+        expected_version: Option<u64>,
+        ticket_fu: TicketForUpdate,
+    ) -> Result<Ticket> {
+        let ticket = self.tickets_store.get(id).await?;
+        // 先確認一次版本，已經過期時不需要再檢查權限與流程
+        ticket.check_version(expected_version)?;
+        if !self.can_update_ticket(ctx, &ticket).await {
+            return Err(Error::TicketUpdateFailNotOwner {
+                id,
+                user_id: ctx.user_id(),
+            });
+        }
+        if let Some(Some(assignee)) = ticket_fu.assignee {
+            self.check_assignee_exists(assignee).await?;
+        }
+        if let Some(status) = ticket_fu.status {
+            self.workflow.check(id, ticket.status, status)?;
+        }
+        let mut updated = ticket.clone();
+        ticket_fu.clone().apply_to(&mut updated, ctx.user_id());
+        let history_fi = TicketHistoryForInsert {
+            actor_id: Some(ctx.user_id()),
+            action: TicketHistoryAction::Update,
+            changes: diff_tickets(Some(&ticket), &updated),
+        };
+        self.tickets_store
+            .update(
+                id,
+                ctx.user_id(),
+                Some(ticket.version),
+                ticket_fu,
+                history_fi,
+            )
+            .await
+    }
+    pub async fn transition_ticket(
+        &self,
+        ctx: Ctx,
+        id: u64,
+        expected_version: Option<u64>,
+        ticket_ft: TicketForTransition,
+    ) -> Result<Ticket> {
+        let ticket_fu = TicketForUpdate {
+            status: Some(ticket_ft.status),
+            ..Default::default()
+        };
+        self.update_ticket(ctx, id, expected_version, ticket_fu)
+            .await
+    }
+    pub async fn assign_ticket(
+        &self,
+        ctx: Ctx,
+        id: u64,
+        expected_version: Option<u64>,
+        assignee: TicketForAssign,
+    ) -> Result<Ticket> {
+        let ticket_fu = TicketForUpdate {
+            assignee: Some(Some(assignee)),
+            ..Default::default()
+        };
+        self.update_ticket(ctx, id, expected_version, ticket_fu)
+            .await
+    }
+    pub async fn unassign_ticket(
+        &self,
+        ctx: Ctx,
+        id: u64,
+        expected_version: Option<u64>,
+    ) -> Result<Ticket> {
+        let ticket_fu = TicketForUpdate {
+            assignee: Some(None),
+            ..Default::default()
+        };
+        self.update_ticket(ctx, id, expected_version, ticket_fu)
+            .await
+    }
+    pub async fn ticket_transitions(&self, id: u64) -> Result<TicketTransitions> {
+        let ticket = self.get_ticket(id).await?;
+        Ok(TicketTransitions {
+            status: ticket.status,
+            allowed: self.workflow.allowed_from(ticket.status),
+        })
+    }
+    // 刪除只會將 ticket 移到垃圾桶並記錄刪除者，之後仍然可以還原
+    // 與修改相同，只有建立者、被指派的人以及管理者可以刪除
+    pub async fn delete_ticket(
+        &self,
+        ctx: Ctx,
+        id: u64,
+        expected_version: Option<u64>,
+    ) -> Result<Ticket> {
+        let ticket = self.tickets_store.get(id).await?;
+        ticket.check_version(expected_version)?;
+        if !self.can_update_ticket(&ctx, &ticket).await {
+            return Err(Error::TicketUpdateFailNotOwner {
+                id,
+                user_id: ctx.user_id(),
+            });
+        }
+        // 以檢查權限時的版本刪除，期間被其他人修改（例如改變指派）時不會略過權限檢查
+        let ticket = self
+            .tickets_store
+            .delete(
+                id,
+                ctx.user_id(),
+                Some(ticket.version),
+                action_history(&ctx, TicketHistoryAction::Delete),
+            )
+            .await?;
+        self.unindex_ticket(&ticket)?;
+        Ok(ticket)
+    }
+}
+
+// -- Trash
+// 垃圾桶中的 ticket 不會出現在一般的查詢中，只能還原或永久刪除
+impl ModelController {
+    // 只列出自己可以修改（也就是可以還原）的 ticket
+    pub async fn list_deleted_tickets(&self, ctx: Ctx) -> Result<Vec<Ticket>> {
+        let mut tickets = Vec::new();
+        for ticket in self.tickets_store.list_deleted().await? {
+            if self.can_update_ticket(&ctx, &ticket).await {
+                tickets.push(ticket);
+            }
+        }
+        Ok(tickets)
+    }
+    // 還原需要與修改相同的權限
+    pub async fn restore_ticket(&self, ctx: Ctx, id: u64) -> Result<Ticket> {
+        let ticket = self.tickets_store.get_deleted(id).await?;
+        if !self.can_update_ticket(&ctx, &ticket).await {
+            return Err(Error::TicketUpdateFailNotOwner {
+                id,
+                user_id: ctx.user_id(),
+            });
+        }
+        let ticket = self
+            .tickets_store
+            .restore(id, action_history(&ctx, TicketHistoryAction::Restore))
+            .await?;
+        self.reindex_ticket(&ticket).await?;
+        Ok(ticket)
+    }
+    // 永久刪除無法復原，只有管理者可以執行
+    // 附件資訊由儲存層一併刪除，附件內容則在 ticket 刪除後從 BlobStore 刪除
+    pub async fn purge_ticket(&self, ctx: Ctx, id: u64) -> Result<Ticket> {
+        require_admin(&ctx)?;
+        let ticket = self
+            .tickets_store
+            .purge(id, action_history(&ctx, TicketHistoryAction::Purge))
+            .await?;
+        self.unindex_ticket(&ticket)?;
+        self.blob_store.delete_dir(&ticket_blob_dir(id)).await?;
+        Ok(ticket)
+    }
+    // 永久刪除在垃圾桶中超過保留期限的 ticket，由背景工作定期呼叫，異動紀錄中沒有執行者
+    pub async fn purge_expired_tickets(&self) -> Result<Vec<Ticket>> {
+        let cutoff = now_unix_secs().saturating_sub(config().trash_retention_secs);
+        let history_fi = TicketHistoryForInsert {
+            actor_id: None,
+            action: TicketHistoryAction::Purge,
+            changes: vec![],
+        };
+        let tickets = self
+            .tickets_store
+            .purge_deleted_before(cutoff, history_fi)
+            .await?;
+        for ticket in &tickets {
+            self.unindex_ticket(ticket)?;
+            self.blob_store
+                .delete_dir(&ticket_blob_dir(ticket.id))
+                .await?;
+        }
+        Ok(tickets)
+    }
+}
+
+// 刪除、還原與永久刪除的 changes 由儲存層依修改前後的 ticket 填入（見 TicketHistoryForInsert::with_lifecycle）
+fn action_history(ctx: &Ctx, action: TicketHistoryAction) -> TicketHistoryForInsert {
+    TicketHistoryForInsert {
+        actor_id: Some(ctx.user_id()),
+        action,
+        changes: vec![],
+    }
+}
+
+impl ModelController {
+    // 垃圾桶中的 ticket 與其異動紀錄也只有具備這個權限的人可以看到
+    pub(super) async fn can_update_ticket(&self, ctx: &Ctx, ticket: &Ticket) -> bool {
+        let user_id = ctx.user_id();
+        if ctx.is_admin() || ticket.cid == user_id {
+            return true;
+        }
+        match ticket.assignee {
+            Some(TicketAssignee::User(id)) => id == user_id,
+            Some(TicketAssignee::Group(id)) => self
+                .get_group(id)
+                .await
+                .is_ok_and(|g| g.members.contains(&user_id)),
+            None => false,
+        }
+    }
+
+    async fn check_assignee_exists(&self, assignee: TicketAssignee) -> Result<()> {
+        match assignee {
+            TicketAssignee::User(id) => {
+                self.get_user(id).await?.ok_or(Error::UserNotFound { id })?;
+            }
+            TicketAssignee::Group(id) => {
+                self.get_group(id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 游標的簽章使用 config 中的金鑰，第一次讀取 config 之前先設定好
+    fn init() {
+        std::env::set_var("SERVICE_TOKEN_KEY", "unit-test-token-key");
+        crate::config::init_config().unwrap();
+    }
+
+    fn ticket(id: u64, title: &str) -> Ticket {
+        TicketForCreate {
+            title: title.to_string(),
+            description: String::new(),
+            priority: TicketPriority::High,
+            labels: BTreeSet::new(),
+        }
+        .into_ticket(id, 1)
+    }
+
+    fn sort(field: TicketSortField, order: SortOrder) -> TicketSort {
+        TicketSort { field, order }
+    }
+
+    fn is_invalid(token: &str) -> bool {
+        matches!(
+            TicketCursor::decode(token),
+            Err(Error::TicketFailCursorInvalid)
+        )
+    }
+
+    #[test]
+    fn cursor_roundtrip() {
+        init();
+        for (sort, key) in [
+            (
+                sort(TicketSortField::Title, SortOrder::Desc),
+                TicketSortKey::Text("登入失敗".to_string()),
+            ),
+            (
+                sort(TicketSortField::Priority, SortOrder::Asc),
+                TicketSortKey::Number(TicketPriority::High as u64),
+            ),
+        ] {
+            let token = TicketCursor::after(sort, &ticket(7, "登入失敗"))
+                .encode()
+                .unwrap();
+            let cursor = TicketCursor::decode(&token).unwrap();
+            assert_eq!(cursor.sort, sort);
+            assert_eq!(cursor.key, key);
+            assert_eq!(cursor.id, 7);
+        }
+    }
+
+    #[test]
+    fn cursor_tampered() {
+        init();
+        let sort = sort(TicketSortField::Id, SortOrder::Asc);
+        let token = TicketCursor::after(sort, &ticket(7, "a")).encode().unwrap();
+        let (content, sign) = token.split_once('.').unwrap();
+
+        // 換掉內容但沿用原本的簽章，例如把游標改指向其他 ticket
+        let forged = TicketCursor::after(sort, &ticket(1, "a")).encode().unwrap();
+        let (forged_content, _) = forged.split_once('.').unwrap();
+        assert!(is_invalid(&format!("{forged_content}.{sign}")));
+
+        // 簽章被改動、缺少簽章或格式不正確
+        let flipped = if sign.starts_with('A') { "B" } else { "A" };
+        assert!(is_invalid(&format!("{content}.{flipped}{}", &sign[1..])));
+        assert!(is_invalid(content));
+        assert!(is_invalid(&format!("{content}.")));
+        assert!(is_invalid(&format!("x{token}")));
+        assert!(is_invalid(""));
+
+        // 其他用途的簽章（例如 auth cookie）不能當成游標使用
+        let auth_sign = crypt::sign(TokenKind::Auth, content);
+        assert!(is_invalid(&format!("{content}.{auth_sign}")));
+
+        // 簽章正確但內容不是游標
+        let content = URL_SAFE_NO_PAD.encode(b"{\"id\": 1}");
+        let sign = crypt::sign(TokenKind::Cursor, &content);
+        assert!(is_invalid(&format!("{content}.{sign}")));
+    }
+
+    #[test]
+    fn cursor_is_after_follows_order() {
+        let first = ticket(1, "b");
+        let second = ticket(2, "a");
+        let asc = sort(TicketSortField::Title, SortOrder::Asc);
+        let cursor = TicketCursor::after(asc, &second);
+        assert!(asc.is_after(&first, &cursor));
+        assert!(!asc.is_after(&second, &cursor));
+        let desc = sort(TicketSortField::Title, SortOrder::Desc);
+        let cursor = TicketCursor::after(desc, &first);
+        assert!(desc.is_after(&second, &cursor));
+        assert!(!desc.is_after(&first, &cursor));
+    }
+}
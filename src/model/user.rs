@@ -0,0 +1,222 @@
+// 使用者相關的資料定義與操作
+// 與 ticket 一樣透過儲存層（UserStore）存取資料，這邊負責密碼雜湊、登入驗證等邏輯
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::ModelController;
+use crate::crypt;
+use crate::{Error, Result};
+
+// 使用者在組織中的角色，Admin 可以管理邀請，Member 為一般使用者
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum_macros::AsRefStr)]
+pub enum Role {
+    Admin,
+    Member,
+}
+
+// 帳號的來源，決定可以用哪一種方式登入
+// Local 為本地帳號（預設帳號、接受邀請、SCIM 建立），以本地密碼登入
+// Ldap 為第一次以 LDAP 登入時自動建立的帳號，只能透過 LDAP 登入，同名的本地帳號不會被 LDAP 接管
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, strum_macros::AsRefStr,
+)]
+pub enum AuthSource {
+    #[default]
+    Local,
+    Ldap,
+}
+
+// 資料庫中以文字儲存，讀取時再轉換回來，與 JSON 中的表示方式相同
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "Admin" => Ok(Self::Admin),
+            "Member" => Ok(Self::Member),
+            _ => Err(format!("unknown role: {s}")),
+        }
+    }
+}
+
+impl FromStr for AuthSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "Local" => Ok(Self::Local),
+            "Ldap" => Ok(Self::Ldap),
+            _ => Err(format!("unknown auth source: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: u64,
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    // 停用的帳號無法登入，既有的 auth token 也會立即失效（例如 SCIM 將離職員工停用）
+    pub active: bool,
+    pub auth_source: AuthSource,
+    // 密碼相關的欄位不應該出現在任何回傳給外部的 JSON 中，只有 model 層可以存取
+    // pwd_hash 為 argon2 的 PHC 字串，salt 已經包含在其中
+    #[serde(skip)]
+    pub(in crate::model) pwd_hash: String,
+}
+
+pub struct UserForCreate {
+    pub username: String,
+    pub email: String,
+    pub pwd: String,
+    pub role: Role,
+    pub auth_source: AuthSource,
+}
+
+// 寫入儲存層時使用，密碼已經雜湊過
+pub struct UserForInsert {
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    pub auth_source: AuthSource,
+    pub pwd_hash: String,
+}
+
+// 只有 Some 的欄位會被更新
+#[derive(Default)]
+pub struct UserForUpdate {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub role: Option<Role>,
+    pub active: Option<bool>,
+    // 以 crypt::hash_pwd 雜湊過的密碼
+    pub pwd_hash: Option<String>,
+}
+
+// 以單一欄位查詢使用者，SCIM 的 userName 與 email 都不分大小寫
+#[derive(Debug, Clone)]
+pub enum UserFilter {
+    Username(String),
+    Email(String),
+}
+
+impl UserFilter {
+    // 儲存層沒有索引時，以這個函數在記憶體中篩選
+    pub fn matches(&self, user: &User) -> bool {
+        match self {
+            Self::Username(username) => user.username.eq_ignore_ascii_case(username),
+            Self::Email(email) => user.email.eq_ignore_ascii_case(email),
+        }
+    }
+}
+
+impl ModelController {
+    pub async fn create_user(&self, user_fc: UserForCreate) -> Result<User> {
+        let user_fi = UserForInsert {
+            username: user_fc.username,
+            email: user_fc.email,
+            role: user_fc.role,
+            auth_source: user_fc.auth_source,
+            pwd_hash: hash_pwd(user_fc.pwd).await?,
+        };
+        self.users_store.create(user_fi).await
+    }
+
+    pub async fn get_user(&self, id: u64) -> Result<Option<User>> {
+        self.users_store.get(id).await
+    }
+
+    pub async fn list_users(&self) -> Result<Vec<User>> {
+        self.users_store.list().await
+    }
+
+    pub async fn list_users_by_filter(&self, filter: &UserFilter) -> Result<Vec<User>> {
+        self.users_store.list_by_filter(filter).await
+    }
+
+    pub async fn first_user_by_username(&self, username: &str) -> Result<Option<User>> {
+        self.users_store.get_by_username(username).await
+    }
+
+    pub async fn first_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        self.users_store.get_by_email(email).await
+    }
+
+    // 驗證本地帳號的密碼，成功時回傳該使用者，其他來源的帳號不能以本地密碼登入
+    pub async fn check_login(&self, username: &str, pwd: &str) -> Result<User> {
+        let user = self
+            .first_user_by_username(username)
+            .await?
+            .filter(|u| u.active && u.auth_source == AuthSource::Local)
+            .ok_or(Error::LoginFail)?;
+        let (pwd, pwd_hash) = (pwd.to_string(), user.pwd_hash.clone());
+        let verified = tokio::task::spawn_blocking(move || crypt::verify_pwd(&pwd, &pwd_hash))
+            .await
+            .map_err(|e| Error::CryptFailPwdHash {
+                detail: e.to_string(),
+            })?;
+        if !verified {
+            return Err(Error::LoginFail);
+        }
+        Ok(user)
+    }
+
+    pub async fn update_user_role(&self, id: u64, role: Role) -> Result<User> {
+        let user_fu = UserForUpdate {
+            role: Some(role),
+            ..Default::default()
+        };
+        self.users_store.update(id, user_fu).await
+    }
+
+    pub async fn update_user(&self, id: u64, user_fu: UserForUpdate) -> Result<User> {
+        self.users_store.update(id, user_fu).await
+    }
+}
+
+// 儲存層共用：以分配好的 id 建立 User，新帳號預設為啟用
+impl UserForInsert {
+    pub(in crate::model) fn into_user(self, id: u64) -> User {
+        User {
+            id,
+            username: self.username,
+            email: self.email,
+            role: self.role,
+            active: true,
+            auth_source: self.auth_source,
+            pwd_hash: self.pwd_hash,
+        }
+    }
+}
+
+// 儲存層共用：將 UserForUpdate 套用到既有的 User 上
+impl UserForUpdate {
+    pub(in crate::model) fn apply_to(self, user: &mut User) {
+        if let Some(username) = self.username {
+            user.username = username;
+        }
+        if let Some(email) = self.email {
+            user.email = email;
+        }
+        if let Some(role) = self.role {
+            user.role = role;
+        }
+        if let Some(active) = self.active {
+            user.active = active;
+        }
+        if let Some(pwd_hash) = self.pwd_hash {
+            user.pwd_hash = pwd_hash;
+        }
+    }
+}
+
+// argon2 的計算刻意設計得很慢，放到 blocking thread 上執行，避免佔住 tokio 的 worker thread
+async fn hash_pwd(pwd: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || crypt::hash_pwd(&pwd))
+        .await
+        .map_err(|e| Error::CryptFailPwdHash {
+            detail: e.to_string(),
+        })?
+}
@@ -0,0 +1,52 @@
+// ticket 狀態的流程定義，只有列在流程中的狀態轉換是允許的，例如 Open -> InProgress
+// 流程由 SERVICE_TICKET_WORKFLOW 設定，每個組織可以依照自己的處理方式調整
+use super::TicketStatus;
+use crate::config::config;
+use crate::{Error, Result};
+
+pub struct TicketWorkflow {
+    transitions: Vec<(TicketStatus, TicketStatus)>,
+}
+
+impl TicketWorkflow {
+    // 設定的格式為 "Open->InProgress,InProgress->Resolved,..."，無法辨識的狀態會讓服務啟動失敗
+    pub fn from_config() -> Result<Self> {
+        let mut transitions = Vec::new();
+        for transition in &config().ticket_workflow {
+            let parsed = transition
+                .split_once("->")
+                .and_then(|(from, to)| Some((from.trim().parse().ok()?, to.trim().parse().ok()?)));
+            match parsed {
+                Some(pair) => transitions.push(pair),
+                None => {
+                    return Err(Error::ConfigInvalidWorkflow {
+                        transition: transition.clone(),
+                    })
+                }
+            }
+        }
+        Ok(Self { transitions })
+    }
+
+    // 目前的狀態可以轉換到的所有狀態
+    pub fn allowed_from(&self, from: TicketStatus) -> Vec<TicketStatus> {
+        self.transitions
+            .iter()
+            .filter(|(f, _)| *f == from)
+            .map(|(_, to)| *to)
+            .collect()
+    }
+
+    // 狀態沒有改變時不需要檢查
+    pub fn check(&self, id: u64, from: TicketStatus, to: TicketStatus) -> Result<()> {
+        if from == to || self.transitions.contains(&(from, to)) {
+            return Ok(());
+        }
+        Err(Error::TicketTransitionNotAllowed {
+            id,
+            from,
+            to,
+            allowed: self.allowed_from(from),
+        })
+    }
+}
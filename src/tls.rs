@@ -0,0 +1,136 @@
+// 以 TLS 啟動服務時的設定，以及 client certificate（mutual TLS）的處理
+// 內部機器可以透過 client certificate 驗證身份，不需要先登入取得 cookie
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufReader};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use axum::middleware::AddExtension;
+use axum::Extension;
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient};
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::config::TlsConfig;
+use crate::{Error, Result};
+
+// 連線時通過驗證的 client certificate，會被放進每個 request 的 extension 中
+#[derive(Debug, Clone)]
+pub struct ClientCert {
+    // 憑證 subject 的 CN，會被當作 username 對應到本地的使用者
+    pub subject_cn: String,
+}
+
+// 依照設定建立 rustls 的 ServerConfig
+// 有設定 client CA 時會向 client 要求憑證，並只接受該 CA 簽發的憑證
+pub fn rustls_config(tls_config: &TlsConfig) -> Result<RustlsConfig> {
+    let certs = load_certs(&tls_config.cert_path)?;
+    let key = load_key(&tls_config.key_path)?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match &tls_config.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots.add(&cert).map_err(tls_error)?;
+            }
+            // required 時沒有憑證的連線會在 handshake 就被拒絕，否則交由 cookie 驗證
+            if tls_config.client_cert_required {
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            } else {
+                builder.with_client_cert_verifier(
+                    AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed(),
+                )
+            }
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder.with_single_cert(certs, key).map_err(tls_error)?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(RustlsConfig::from_config(Arc::new(server_config)))
+}
+
+// 包裝 RustlsAcceptor，在 TLS handshake 完成後取出 client certificate
+// 並透過 AddExtension 讓這條連線上的所有 request 都帶有 Option<ClientCert>
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, Option<ClientCert>>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(client_cert_from_der);
+            Ok((stream, Extension(client_cert).layer(service)))
+        })
+    }
+}
+
+fn client_cert_from_der(cert: &Certificate) -> Option<ClientCert> {
+    let (_rest, x509) = X509Certificate::from_der(&cert.0).ok()?;
+    let subject_cn = x509
+        .subject()
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())?
+        .to_string();
+    Some(ClientCert { subject_cn })
+}
+
+fn load_certs(path: &str) -> Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(File::open(path).map_err(tls_error)?);
+    let certs = rustls_pemfile::certs(&mut reader).map_err(tls_error)?;
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &str) -> Result<PrivateKey> {
+    let mut reader = BufReader::new(File::open(path).map_err(tls_error)?);
+    // 依序支援 PKCS#8、PKCS#1 (RSA)、SEC1 (EC) 格式的私鑰
+    while let Some(item) = rustls_pemfile::read_one(&mut reader).map_err(tls_error)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    Err(Error::ConfigInvalidTls {
+        detail: format!("no private key found in {path}"),
+    })
+}
+
+fn tls_error(e: impl std::fmt::Display) -> Error {
+    Error::ConfigInvalidTls {
+        detail: e.to_string(),
+    }
+}
@@ -1,6 +1,11 @@
 // 將這邊有引入的module視為同一個module
 pub mod mw_auth;
+pub mod mw_signature;
+pub mod routes_attachments;
+pub mod routes_comments;
+pub mod routes_invitations;
 pub mod routes_login;
+pub mod routes_scim;
 pub mod routes_tickets;
 // 定義module共用的常數
 pub const AUTH_TOKEN: &str = "auth-token";
@@ -1,6 +1,7 @@
 // 定義middleware，處理權限驗證
 use async_trait::async_trait;
 use axum::extract::{FromRequestParts, State};
+use axum::http::header::AUTHORIZATION;
 use axum::http::request::Parts;
 use axum::http::Request;
 use axum::middleware::Next;
@@ -9,8 +10,13 @@ use axum::RequestPartsExt;
 use lazy_regex::regex_captures;
 use tower_cookies::{Cookie, Cookies};
 
+use crate::config::config;
+use crate::crypt::{self, TokenKind};
 use crate::ctx::Ctx;
-use crate::model::ModelController;
+use crate::model::{ModelController, User};
+use crate::tls::ClientCert;
+use crate::utils::now_unix_secs;
+use crate::web::mw_signature::RequestSigner;
 use crate::web::AUTH_TOKEN;
 use crate::{Error, Result};
 
@@ -30,21 +36,65 @@ pub async fn mw_require_auth<B>(
 
     Ok(next.run(req).await)
 }
+// SCIM 是由 IdP 呼叫，沒有使用者的 cookie，改為檢查 Authorization: Bearer [token]
+pub async fn mw_require_scim_token<B>(req: Request<B>, next: Next<B>) -> Result<Response> {
+    println!("->> {:<12} - mw_require_scim_token", "MIDDLEWARE");
+
+    let expected = config()
+        .scim_token
+        .as_deref()
+        .ok_or(Error::ScimFailNotConfigured)?;
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(Error::ScimFailNoBearerToken)?;
+    // 透過 crypt::verify 比對兩者的簽章，比對過程為 constant time，避免 timing attack
+    crypt::verify(
+        TokenKind::Scim,
+        token,
+        &crypt::sign(TokenKind::Scim, expected),
+    )
+    .map_err(|_| Error::ScimFailTokenInvalid)?;
+
+    Ok(next.run(req).await)
+}
+
 // middleware，處理cookie查看是否過期，如果不是“沒有Token的錯誤”，將cookie刪除
+// 沒有cookie但連線帶有client certificate（mutual TLS）時，改用憑證的subject找出使用者
+// cookie 無效（例如過期或被竄改）但連線帶有憑證時，同樣以憑證為準，憑證已經在 handshake 時驗證過
 pub async fn mw_ctx_resolver<B>(
-    _mc: State<ModelController>,
+    State(mc): State<ModelController>,
     cookies: Cookies,
     mut req: Request<B>,
     next: Next<B>,
 ) -> Result<Response> {
     println!("->> {:<12} - mw_ctx_resolver", "MIDDLEWARE");
     let auth_token = cookies.get(AUTH_TOKEN).map(|c| c.value().to_string());
-    let result_ctx = match auth_token
-        .ok_or(Error::AuthFailNoAuthTokenCookie)
-        .and_then(parse_token)
-    {
-        Ok((user_id, _exp, _sign)) => Ok(Ctx::new(user_id)),
-        Err(e) => Err(e),
+    let client_cert = req
+        .extensions()
+        .get::<Option<ClientCert>>()
+        .cloned()
+        .flatten();
+    // 帶有簽章的請求由 mw_signature_verifier 先行驗證，這邊只需要取出結果
+    let signer = req.extensions_mut().remove::<Result<RequestSigner>>();
+    let result_ctx = match (signer, auth_token, client_cert) {
+        (Some(signer), _, _) => match signer {
+            Ok(signer) => ctx_from_username(&mc, &signer.username).await,
+            Err(e) => Err(e),
+        },
+        (None, Some(auth_token), client_cert) => {
+            match (ctx_from_token(&mc, auth_token).await, client_cert) {
+                (Err(_), Some(client_cert)) => {
+                    cookies.remove(Cookie::named(AUTH_TOKEN));
+                    ctx_from_client_cert(&mc, client_cert).await
+                }
+                (result_ctx, _) => result_ctx,
+            }
+        }
+        (None, None, Some(client_cert)) => ctx_from_client_cert(&mc, client_cert).await,
+        (None, None, None) => Err(Error::AuthFailNoAuthTokenCookie),
     };
     if result_ctx.is_err() && !matches!(result_ctx, Err(Error::AuthFailNoAuthTokenCookie)) {
         cookies.remove(Cookie::named(AUTH_TOKEN))
@@ -53,6 +103,45 @@ pub async fn mw_ctx_resolver<B>(
     Ok(next.run(req).await)
 }
 
+// 先驗證簽章與到期時間，通過之後才讀取使用者與角色
+async fn ctx_from_token(mc: &ModelController, auth_token: String) -> Result<Ctx> {
+    let user_id = parse_token(&auth_token)?;
+    let user = mc
+        .get_user(user_id)
+        .await?
+        .ok_or(Error::AuthFailUserNotFound { user_id })?;
+    ctx_from_user(user)
+}
+
+// 憑證已經在 TLS handshake 時由設定的 CA 驗證過，這邊只需要將 CN 對應到 username
+async fn ctx_from_client_cert(mc: &ModelController, client_cert: ClientCert) -> Result<Ctx> {
+    let user = mc
+        .first_user_by_username(&client_cert.subject_cn)
+        .await?
+        .ok_or(Error::AuthFailClientCertUnknownUser {
+            subject_cn: client_cert.subject_cn,
+        })?;
+    ctx_from_user(user)
+}
+
+async fn ctx_from_username(mc: &ModelController, username: &str) -> Result<Ctx> {
+    let user =
+        mc.first_user_by_username(username)
+            .await?
+            .ok_or(Error::AuthFailSignatureUnknownUser {
+                username: username.to_string(),
+            })?;
+    ctx_from_user(user)
+}
+
+// 角色以資料庫中的為準，而不是寫在 token 裡，這樣權限變更可以馬上生效
+fn ctx_from_user(user: User) -> Result<Ctx> {
+    if !user.active {
+        return Err(Error::AuthFailUserInactive { user_id: user.id });
+    }
+    Ok(Ctx::new(user.id, user.role))
+}
+
 // Ctx作為參數時，系統會執行這段對其進行轉換，目的是確認Request裡面是否存在Ctx，我們在這個案例所定義的Ctx比較單純，其中只定義user_id
 // 也就是說，這段轉換會檢查Request裡面是否有user_id，若沒有將回傳錯誤
 #[async_trait]
@@ -72,15 +161,22 @@ where
     }
 }
 
-/// Parse a token of format `user-[user-id].[expiration].[signature]`
-/// Returns (user_id, expiration, signature)
-fn parse_token(token: String) -> Result<(u64, String, String)> {
+/// Parse and validate a token of format `user-[user-id].[expiration].[signature]`
+/// Returns the user id
+fn parse_token(token: &str) -> Result<u64> {
     // 這邊使用lazy_regex這個套件，讓我們可以解析regex表達式一次，並在之後可以反覆使用
-    let (_whole, user_id, exp, sign) = regex_captures!(r#"^user-(\d+)\.(.+)\.(.+)"#, &token)
+    let (_whole, user_id, exp, sign) = regex_captures!(r#"^user-(\d+)\.(\d+)\.(.+)$"#, token)
         .ok_or(Error::AuthFailTokenWrongFormat)?;
 
+    crypt::verify(TokenKind::Auth, &format!("user-{user_id}.{exp}"), sign)
+        .map_err(|_| Error::AuthFailTokenSignatureInvalid)?;
+
     let user_id: u64 = user_id
         .parse()
         .map_err(|_| Error::AuthFailTokenWrongFormat)?;
-    Ok((user_id, exp.to_string(), sign.to_string()))
+    let exp: u64 = exp.parse().map_err(|_| Error::AuthFailTokenWrongFormat)?;
+    if exp <= now_unix_secs() {
+        return Err(Error::AuthFailTokenExpired { user_id });
+    }
+    Ok(user_id)
 }
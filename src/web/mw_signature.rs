@@ -0,0 +1,153 @@
+// 簽章請求（HMAC request signing）的驗證
+// 給不適合使用 bearer token 的整合使用：client 以 shared secret 對請求內容簽章，伺服器重新計算並比對
+// 簽章內容為 "[key-id]\n[METHOD]\n[path-and-query]\n[timestamp]\n[body-sha256-hex]"，使用 HMAC-SHA256 並以 base64url 編碼
+// 內容包含 key id，多個 client 共用同一個 secret 時，一個 client 的簽章不能以另一個 client 的身份重送
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::header::CONTENT_LENGTH;
+use axum::http::{HeaderMap, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::config::config;
+use crate::crypt;
+use crate::model::ModelController;
+use crate::utils::now_unix_secs;
+use crate::{Error, Result};
+
+pub const HEADER_KEY_ID: &str = "x-api-key";
+pub const HEADER_TIMESTAMP: &str = "x-timestamp";
+pub const HEADER_SIGNATURE: &str = "x-signature";
+
+// 為了計算 body 的雜湊必須將整個 body 讀進記憶體，因此限制大小
+const MAX_SIGNED_BODY_BYTES: usize = 1024 * 1024;
+
+// 驗證通過的簽章者，mw_ctx_resolver 會依此找到對應的使用者
+#[derive(Debug, Clone)]
+pub struct RequestSigner {
+    pub username: String,
+}
+
+// 只處理帶有 X-Signature 的請求，驗證結果以 Result<RequestSigner> 放進 extension，交給 mw_ctx_resolver 建立 Ctx
+// 這個 middleware 需要在 mw_ctx_resolver 之前執行
+// 這裡直接回傳的錯誤不會經過 main_response_mapper，所以所有的錯誤（包含 body 過大）都放進 extension，由之後的流程回應
+pub async fn mw_signature_verifier(
+    State(mc): State<ModelController>,
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response> {
+    if !req.headers().contains_key(HEADER_SIGNATURE) {
+        return Ok(next.run(req).await);
+    }
+    println!("->> {:<12} - mw_signature_verifier", "MIDDLEWARE");
+
+    // 讀取 body 計算雜湊後，再以同樣的內容重新組回 request 交給後面的 handler
+    // body 過大時不再讀取，以空的 body 繼續，請求會因為沒有 Ctx 而回應錯誤
+    let (parts, body) = req.into_parts();
+    let (body, result_signer) = match read_body(&parts.headers, body).await {
+        Ok(body) => {
+            let result_signer = verify_request(
+                &mc,
+                parts.method.as_str(),
+                parts
+                    .uri
+                    .path_and_query()
+                    .map(|p| p.as_str())
+                    .unwrap_or("/"),
+                &parts.headers,
+                &body,
+            )
+            .await;
+            (body, result_signer)
+        }
+        Err(e) => (Bytes::new(), Err(e)),
+    };
+
+    let mut req = Request::from_parts(parts, Body::from(body));
+    req.extensions_mut().insert(result_signer);
+    Ok(next.run(req).await)
+}
+
+async fn verify_request(
+    mc: &ModelController,
+    method: &str,
+    path_and_query: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<RequestSigner> {
+    let key_id = header_str(headers, HEADER_KEY_ID)?;
+    let timestamp = header_str(headers, HEADER_TIMESTAMP)?;
+    let signature = header_str(headers, HEADER_SIGNATURE)?;
+
+    let client = config()
+        .hmac_clients
+        .iter()
+        .find(|c| c.key_id == key_id)
+        .ok_or(Error::AuthFailSignatureUnknownKey {
+            key_id: key_id.to_string(),
+        })?;
+
+    // -- Replay window
+    let timestamp: u64 = timestamp
+        .parse()
+        .map_err(|_| Error::AuthFailSignatureWrongFormat)?;
+    let now = now_unix_secs();
+    if timestamp.abs_diff(now) > config().hmac_max_skew_secs {
+        return Err(Error::AuthFailSignatureClockSkew {
+            timestamp,
+            server_time: now,
+        });
+    }
+
+    // -- Signature
+    let content = format!(
+        "{key_id}\n{method}\n{path_and_query}\n{timestamp}\n{}",
+        crypt::sha256_hex(body)
+    );
+    crypt::verify_with_key(client.secret.as_bytes(), &content, signature)
+        .map_err(|_| Error::AuthFailSignatureInvalid)?;
+
+    // 簽章正確後才記錄，避免任意的錯誤簽章塞滿紀錄
+    // 同一個簽章不能被重送第二次，紀錄存放在儲存層，多個服務實例共用資料庫時也會被擋下
+    // 超出時間範圍（前後各 max_skew）的簽章本來就會被拒絕，紀錄只需要保留這段時間
+    if !mc
+        .record_signature(signature, now, 2 * config().hmac_max_skew_secs)
+        .await?
+    {
+        return Err(Error::AuthFailSignatureReplayed);
+    }
+
+    Ok(RequestSigner {
+        username: client.username.clone(),
+    })
+}
+
+async fn read_body(headers: &HeaderMap, body: Body) -> Result<Bytes> {
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_SIGNED_BODY_BYTES {
+        return Err(Error::AuthFailSignatureBodyTooLarge);
+    }
+    // Content-Length 可能不存在（chunked），所以讀取時也要限制大小
+    // 只有超過大小限制才是 413，client 中途斷線等其他讀取錯誤另外回應
+    let body = http_body::Limited::new(body, MAX_SIGNED_BODY_BYTES);
+    hyper::body::to_bytes(body).await.map_err(|e| {
+        if e.is::<http_body::LengthLimitError>() {
+            Error::AuthFailSignatureBodyTooLarge
+        } else {
+            Error::AuthFailSignatureBodyRead {
+                detail: e.to_string(),
+            }
+        }
+    })
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(Error::AuthFailSignatureWrongFormat)
+}
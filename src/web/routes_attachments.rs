@@ -0,0 +1,152 @@
+use axum::extract::multipart::MultipartError;
+use axum::extract::{DefaultBodyLimit, Multipart, Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::model::{Attachment, AttachmentForUpload, ModelController};
+use crate::{Error, Result};
+
+// multipart 的 boundary、欄位標頭等額外的大小，request body 的上限為附件上限再加上這個值
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+// ticket 的附件，需要登入，會被 nest 在 /api 底下
+// axum 預設的 request body 上限為 2 MB，這邊改為依照附件的大小上限設定
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route(
+            "/tickets/:id/attachments",
+            get(list_attachments).post(upload_attachment),
+        )
+        .route(
+            "/tickets/:id/attachments/:attachment_id",
+            get(download_attachment).delete(delete_attachment),
+        )
+        .layer(DefaultBodyLimit::max(
+            config().attachment_max_bytes + MULTIPART_OVERHEAD_BYTES,
+        ))
+        .with_state(mc)
+}
+
+// --- REST Handlers
+// 檔案放在名為 file 的欄位，其他欄位會被略過
+// 邊讀取邊檢查大小，超過上限時馬上停止，不會把整個過大的檔案讀進記憶體
+async fn upload_attachment(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(ticket_id): Path<u64>,
+    mut multipart: Multipart,
+) -> Result<Json<Attachment>> {
+    println!("->> {:<12} - upload_attachment", "HANDLER");
+
+    let max_bytes = config().attachment_max_bytes;
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or_default().to_string();
+        // 只保留 MIME type 本身，去掉 charset 等參數
+        let content_type = field
+            .content_type()
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let mut data = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+            if data.len() + chunk.len() > max_bytes {
+                return Err(Error::AttachmentFailTooLarge { max_bytes });
+            }
+            data.extend_from_slice(&chunk);
+        }
+        let upload = AttachmentForUpload {
+            filename,
+            content_type,
+            data,
+        };
+        let attachment = mc.create_attachment(ctx, ticket_id, upload).await?;
+        return Ok(Json(attachment));
+    }
+    Err(Error::AttachmentFailNoFile)
+}
+
+async fn list_attachments(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(ticket_id): Path<u64>,
+) -> Result<Json<Vec<Attachment>>> {
+    println!("->> {:<12} - list_attachments", "HANDLER");
+
+    let attachments = mc.list_attachments(ctx, ticket_id).await?;
+    Ok(Json(attachments))
+}
+
+// 以上傳時的 content type 回傳，並透過 Content-Disposition 讓瀏覽器以原本的檔名下載
+// nosniff 避免瀏覽器自行猜測類型，把上傳的檔案當成 HTML 等內容執行
+async fn download_attachment(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path((ticket_id, id)): Path<(u64, u64)>,
+) -> Result<impl IntoResponse> {
+    println!("->> {:<12} - download_attachment", "HANDLER");
+
+    let (attachment, data) = mc.get_attachment_content(ctx, ticket_id, id).await?;
+    let headers = [
+        (header::CONTENT_TYPE, attachment.content_type),
+        (
+            header::CONTENT_DISPOSITION,
+            content_disposition(&attachment.filename),
+        ),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    Ok((headers, data))
+}
+
+async fn delete_attachment(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path((ticket_id, id)): Path<(u64, u64)>,
+) -> Result<Json<Attachment>> {
+    println!("->> {:<12} - delete_attachment", "HANDLER");
+
+    let attachment = mc.delete_attachment(ctx, ticket_id, id).await?;
+    Ok(Json(attachment))
+}
+
+// 超過 DefaultBodyLimit 時 multer 也會回傳錯誤，這種情況與檔案過大視為同一種錯誤
+fn multipart_error(e: MultipartError) -> Error {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        Error::AttachmentFailTooLarge {
+            max_bytes: config().attachment_max_bytes,
+        }
+    } else {
+        Error::AttachmentFailMultipart {
+            detail: e.body_text(),
+        }
+    }
+}
+
+// filename 給只支援 ASCII 的 client 使用，非 ASCII 與特殊字元以 "_" 取代
+// filename* 以 RFC 5987 的格式保留完整的 UTF-8 檔名
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => (b as char).to_string(),
+            b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.' | b'^' | b'_' | b'`' | b'|' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
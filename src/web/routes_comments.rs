@@ -0,0 +1,68 @@
+use axum::extract::{Path, State};
+use axum::routing::{get, patch};
+use axum::{Json, Router};
+
+use crate::ctx::Ctx;
+use crate::model::{Comment, CommentForCreate, CommentForUpdate, ModelController};
+use crate::Result;
+
+// ticket 底下的留言，需要登入，會被 nest 在 /api 底下
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route(
+            "/tickets/:id/comments",
+            get(list_comments).post(create_comment),
+        )
+        .route(
+            "/tickets/:id/comments/:comment_id",
+            patch(update_comment).delete(delete_comment),
+        )
+        .with_state(mc)
+}
+
+// --- REST Handlers
+async fn create_comment(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(ticket_id): Path<u64>,
+    Json(comment_fc): Json<CommentForCreate>,
+) -> Result<Json<Comment>> {
+    println!("->> {:<12} - create_comment", "HANDLER");
+
+    let comment = mc.create_comment(ctx, ticket_id, comment_fc).await?;
+    Ok(Json(comment))
+}
+
+async fn list_comments(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(ticket_id): Path<u64>,
+) -> Result<Json<Vec<Comment>>> {
+    println!("->> {:<12} - list_comments", "HANDLER");
+
+    let comments = mc.list_comments(ctx, ticket_id).await?;
+    Ok(Json(comments))
+}
+
+async fn update_comment(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path((ticket_id, id)): Path<(u64, u64)>,
+    Json(comment_fu): Json<CommentForUpdate>,
+) -> Result<Json<Comment>> {
+    println!("->> {:<12} - update_comment", "HANDLER");
+
+    let comment = mc.update_comment(ctx, ticket_id, id, comment_fu).await?;
+    Ok(Json(comment))
+}
+
+async fn delete_comment(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path((ticket_id, id)): Path<(u64, u64)>,
+) -> Result<Json<Comment>> {
+    println!("->> {:<12} - delete_comment", "HANDLER");
+
+    let comment = mc.delete_comment(ctx, ticket_id, id).await?;
+    Ok(Json(comment))
+}
@@ -0,0 +1,139 @@
+use axum::extract::{Path, State};
+use axum::routing::{delete, post};
+use axum::{Json, Router};
+use lazy_regex::regex_captures;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tower_cookies::Cookies;
+
+use crate::crypt::{self, TokenKind};
+use crate::ctx::Ctx;
+use crate::model::{
+    Invitation, InvitationClaims, InvitationForAccept, InvitationForCreate, ModelController,
+};
+use crate::utils::now_unix_secs;
+use crate::web::routes_login::set_token_cookie;
+use crate::{Error, Result};
+
+// 管理邀請的 API，需要登入且為 Admin，會被 nest 在 /api 底下
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route(
+            "/invitations",
+            post(create_invitation).get(list_invitations),
+        )
+        .route("/invitations/:id", delete(revoke_invitation))
+        .with_state(mc)
+}
+
+// 接受邀請的人還沒有帳號，所以這個 API 不能放在需要權限驗證的路由底下
+pub fn routes_accept(mc: ModelController) -> Router {
+    Router::new()
+        .route("/api/invitations/accept", post(accept_invitation))
+        .with_state(mc)
+}
+
+// --- REST Handlers
+// 建立邀請後回傳 token，由管理者自行透過 email 等方式交給被邀請者
+async fn create_invitation(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Json(invitation_fc): Json<InvitationForCreate>,
+) -> Result<Json<Value>> {
+    println!("->> {:<12} - create_invitation", "HANDLER");
+
+    let invitation = mc.create_invitation(ctx, invitation_fc).await?;
+    let token = invitation_token(&invitation);
+    Ok(Json(json!({"invitation": invitation, "token": token})))
+}
+
+async fn list_invitations(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+) -> Result<Json<Vec<Invitation>>> {
+    println!("->> {:<12} - list_invitations", "HANDLER");
+
+    let invitations = mc.list_invitations(ctx).await?;
+    Ok(Json(invitations))
+}
+
+async fn revoke_invitation(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+) -> Result<Json<Invitation>> {
+    println!("->> {:<12} - revoke_invitation", "HANDLER");
+
+    let invitation = mc.revoke_invitation(ctx, id).await?;
+    Ok(Json(invitation))
+}
+
+// 接受邀請成功後直接幫使用者登入，與 api_login 使用同一個 cookie
+async fn accept_invitation(
+    State(mc): State<ModelController>,
+    cookies: Cookies,
+    Json(payload): Json<AcceptPayload>,
+) -> Result<Json<Value>> {
+    println!("->> {:<12} - accept_invitation", "HANDLER");
+
+    let claims = parse_invitation_token(&payload.token)?;
+    let user = mc
+        .accept_invitation(
+            claims,
+            InvitationForAccept {
+                username: payload.username,
+                pwd: payload.pwd,
+            },
+        )
+        .await?;
+    set_token_cookie(&cookies, user.id);
+
+    Ok(Json(json!({"result": {"success": true, "user": user}})))
+}
+
+#[derive(Debug, Deserialize)]
+struct AcceptPayload {
+    token: String,
+    username: String,
+    pwd: String,
+}
+
+// -- Invitation token
+// 格式與 auth token 類似：`inv-[invitation-id].[expiration].[nonce].[signature]`
+// 簽章涵蓋 id、到期時間與 nonce，任何一個被竄改都會驗證失敗
+// 接受時另外確認到期時間與 nonce 與儲存的邀請相同，id 被重新分配給其他邀請時舊的 token 也不能使用
+fn invitation_token(invitation: &Invitation) -> String {
+    let content = format!(
+        "inv-{}.{}.{}",
+        invitation.id,
+        invitation.expires_at,
+        invitation.nonce()
+    );
+    let sign = crypt::sign(TokenKind::Invitation, &content);
+    format!("{content}.{sign}")
+}
+
+/// Parse and validate a token of format `inv-[invitation-id].[expiration].[nonce].[signature]`
+/// Returns the signed claims, which still have to match the stored invitation
+fn parse_invitation_token(token: &str) -> Result<InvitationClaims> {
+    let (_whole, id, exp, nonce, sign) =
+        regex_captures!(r#"^inv-(\d+)\.(\d+)\.([0-9a-f]+)\.(.+)$"#, token)
+            .ok_or(Error::InvitationTokenWrongFormat)?;
+
+    crypt::verify(
+        TokenKind::Invitation,
+        &format!("inv-{id}.{exp}.{nonce}"),
+        sign,
+    )?;
+
+    let id: u64 = id.parse().map_err(|_| Error::InvitationTokenWrongFormat)?;
+    let exp: u64 = exp.parse().map_err(|_| Error::InvitationTokenWrongFormat)?;
+    if exp <= now_unix_secs() {
+        return Err(Error::InvitationExpired { id });
+    }
+    Ok(InvitationClaims {
+        id,
+        expires_at: exp,
+        nonce: nonce.to_string(),
+    })
+}
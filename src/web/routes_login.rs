@@ -1,29 +1,68 @@
-use crate::{web::AUTH_TOKEN, Error, Result};
+use std::sync::Arc;
+
+use crate::config::config;
+use crate::crypt::{self, TokenKind};
+use crate::utils::now_unix_secs;
+use crate::{auth::Authenticator, model::ModelController, web::AUTH_TOKEN, Result};
 use axum::{
+    extract::{FromRef, State},
     routing::{post, Route},
     Json, Router,
 };
 use serde::Deserialize;
 use serde_json::{json, Value};
+use tower_cookies::cookie::SameSite;
 use tower_cookies::{Cookie, Cookies};
 
+// 登入需要 ModelController 以及驗證帳密的 Authenticator
+// 透過 FromRef，handler 可以只取出自己需要的部分
+#[derive(Clone, FromRef)]
+struct LoginState {
+    mc: ModelController,
+    authenticator: Arc<dyn Authenticator>,
+}
+
 // 建立一個子藍圖，底下包括跟登入有關的部分
-pub fn routes() -> Router {
-    Router::new().route("/api/login", post(api_login))
+pub fn routes(mc: ModelController, authenticator: Arc<dyn Authenticator>) -> Router {
+    Router::new()
+        .route("/api/login", post(api_login))
+        .with_state(LoginState { mc, authenticator })
 }
 
 // 登入，幫使用者加上cookie
-async fn api_login(cookies: Cookies, payload: Json<LoginPayload>) -> Result<Json<Value>> {
+async fn api_login(
+    State(mc): State<ModelController>,
+    State(authenticator): State<Arc<dyn Authenticator>>,
+    cookies: Cookies,
+    payload: Json<LoginPayload>,
+) -> Result<Json<Value>> {
     println!("->> {:<12} - api_login", "HANDLER");
-    if payload.username != "demo1" || payload.pwd != "welcome" {
-        return Err(Error::LoginFail);
-    }
-    cookies.add(Cookie::new(AUTH_TOKEN, "user-1.exp.sign"));
+    let user = authenticator
+        .authenticate(&mc, &payload.username, &payload.pwd)
+        .await?;
+    set_token_cookie(&cookies, user.id);
     let body = Json(json!({"result": {"success": true}}));
 
     Ok(body)
 }
 
+// 所有登入成功的流程（帳密登入、接受邀請）都透過這邊發放 auth token
+// 格式為 `user-[user-id].[expiration].[signature]`，簽章涵蓋 id 與到期時間，任何一個被竄改都會驗證失敗
+// path 設為 "/"，否則瀏覽器會以發出請求的路徑作為 cookie 的範圍
+// token 等同於登入的憑證：HttpOnly 讓頁面上的 script 讀不到，SameSite=Lax 讓其他網站發出的 POST 不會帶上
+// 以 HTTPS 啟動時加上 Secure，瀏覽器不會在 HTTP 連線中送出
+pub fn set_token_cookie(cookies: &Cookies, user_id: u64) {
+    let exp = now_unix_secs() + config().token_duration_secs;
+    let content = format!("user-{user_id}.{exp}");
+    let sign = crypt::sign(TokenKind::Auth, &content);
+    let mut cookie = Cookie::new(AUTH_TOKEN, format!("{content}.{sign}"));
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_secure(config().tls.is_some());
+    cookies.add(cookie);
+}
+
 #[derive(Debug, Deserialize)]
 struct LoginPayload {
     username: String,
@@ -0,0 +1,460 @@
+// SCIM 2.0 (RFC 7643/7644) 的使用者與群組同步 API，提供給外部的 Identity Provider 使用
+// 這邊只負責 SCIM 格式與 model 之間的轉換，實際的資料操作仍然交給 ModelController
+use axum::extract::{Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use lazy_regex::regex_captures;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::model::{
+    AuthSource, Group, GroupForCreate, GroupForUpdate, ModelController, Role, User, UserFilter,
+    UserForCreate, UserForUpdate,
+};
+use crate::web::mw_auth::mw_require_scim_token;
+use crate::{Error, Result};
+
+const SCHEMA_USER: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const SCHEMA_GROUP: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+const SCHEMA_LIST: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const SCHEMA_ERROR: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const CONTENT_TYPE_SCIM: &str = "application/scim+json";
+
+// 這組路由不使用 cookie，而是由 mw_require_scim_token 驗證 Bearer token
+// 錯誤（包含 token 驗證失敗）由 scim_error_mapper 轉換成 SCIM 的錯誤格式，所以 mapper 要放在 token 驗證的外層
+pub fn routes(mc: ModelController) -> Router {
+    Router::new()
+        .route("/scim/v2/Users", get(list_users).post(create_user))
+        .route(
+            "/scim/v2/Users/:id",
+            get(get_user).patch(patch_user).delete(deactivate_user),
+        )
+        .route("/scim/v2/Groups", get(list_groups).post(create_group))
+        .route(
+            "/scim/v2/Groups/:id",
+            get(get_group).patch(patch_group).delete(delete_group),
+        )
+        .route_layer(middleware::from_fn(mw_require_scim_token))
+        .layer(middleware::map_response(scim_error_mapper))
+        .with_state(mc)
+}
+
+// main_response_mapper 看到這個標記時保留 SCIM 的錯誤格式，只記錄 log
+#[derive(Clone)]
+pub struct ScimErrorMapped;
+
+// RFC 7644 3.12：錯誤以 Error schema 回傳，status 為字串，scimType 只有特定的錯誤才有
+// HTTP status 與 main_response_mapper 相同，由 client_status_and_error 決定，內部的錯誤細節一樣不會回傳
+async fn scim_error_mapper(mut res: Response) -> Response {
+    let Some(error) = res.extensions_mut().remove::<Error>() else {
+        return res;
+    };
+    println!("->> {:<12} - scim_error_mapper", "RES_MAPPER");
+    let (status, _client_error) = error.client_status_and_error();
+    let mut body = json!({
+        "schemas": [SCHEMA_ERROR],
+        "status": status.as_u16().to_string(),
+    });
+    if let Some(scim_type) = scim_type(&error) {
+        body["scimType"] = json!(scim_type);
+    }
+    let mut res = (
+        status,
+        [(CONTENT_TYPE, CONTENT_TYPE_SCIM)],
+        body.to_string(),
+    )
+        .into_response();
+    res.extensions_mut().insert(error);
+    res.extensions_mut().insert(ScimErrorMapped);
+    res
+}
+
+// RFC 7644 3.12 定義的 scimType
+fn scim_type(error: &Error) -> Option<&'static str> {
+    match error {
+        Error::ScimFilterUnsupported { .. } => Some("invalidFilter"),
+        Error::ScimPatchFailUnsupported { .. } => Some("invalidValue"),
+        Error::UserCreateFailUsernameTaken { .. } => Some("uniqueness"),
+        _ => None,
+    }
+}
+
+// --- Users
+async fn list_users(
+    State(mc): State<ModelController>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Value>> {
+    println!("->> {:<12} - scim_list_users", "HANDLER");
+
+    let users = match params.filter.as_deref().map(user_filter).transpose()? {
+        Some(filter) => mc.list_users_by_filter(&filter).await?,
+        None => mc.list_users().await?,
+    };
+    let users = users.iter().map(user_to_scim).collect();
+    Ok(Json(list_response(users, &params)))
+}
+
+async fn create_user(
+    State(mc): State<ModelController>,
+    Json(payload): Json<ScimUser>,
+) -> Result<(StatusCode, Json<Value>)> {
+    println!("->> {:<12} - scim_create_user", "HANDLER");
+
+    let user = mc
+        .create_user(UserForCreate {
+            email: primary_email(&payload.emails).unwrap_or_default(),
+            username: payload.user_name,
+            // 沒有提供密碼的帳號只能透過其他方式登入，這邊給一個隨機的密碼讓本地登入無法使用
+            pwd: payload
+                .password
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            role: Role::Member,
+            auth_source: AuthSource::Local,
+        })
+        .await?;
+    let user = match payload.active {
+        Some(false) => {
+            let user_fu = UserForUpdate {
+                active: Some(false),
+                ..Default::default()
+            };
+            mc.update_user(user.id, user_fu).await?
+        }
+        _ => user,
+    };
+    Ok((StatusCode::CREATED, Json(user_to_scim(&user))))
+}
+
+async fn get_user(State(mc): State<ModelController>, Path(id): Path<u64>) -> Result<Json<Value>> {
+    println!("->> {:<12} - scim_get_user", "HANDLER");
+
+    let user = mc.get_user(id).await?.ok_or(Error::UserNotFound { id })?;
+    Ok(Json(user_to_scim(&user)))
+}
+
+async fn patch_user(
+    State(mc): State<ModelController>,
+    Path(id): Path<u64>,
+    Json(payload): Json<ScimPatch>,
+) -> Result<Json<Value>> {
+    println!("->> {:<12} - scim_patch_user", "HANDLER");
+
+    let mut user_fu = UserForUpdate::default();
+    for op in payload.operations {
+        let kind = op.op.to_ascii_lowercase();
+        if kind != "replace" && kind != "add" {
+            return Err(Error::ScimPatchFailUnsupported { op: op.op });
+        }
+        match (op.path, op.value) {
+            (Some(path), Some(value)) => apply_user_attr(&mut user_fu, &path, value)?,
+            // 沒有 path 時，value 是一個包含多個屬性的物件
+            (None, Some(Value::Object(attrs))) => {
+                for (path, value) in attrs {
+                    apply_user_attr(&mut user_fu, &path, value)?;
+                }
+            }
+            _ => return Err(Error::ScimPatchFailUnsupported { op: op.op }),
+        }
+    }
+    let user = mc.update_user(id, user_fu).await?;
+    Ok(Json(user_to_scim(&user)))
+}
+
+// SCIM 的 DELETE 在這邊只會停用帳號，保留 ticket 等資料的建立者紀錄
+async fn deactivate_user(
+    State(mc): State<ModelController>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode> {
+    println!("->> {:<12} - scim_deactivate_user", "HANDLER");
+
+    let user_fu = UserForUpdate {
+        active: Some(false),
+        ..Default::default()
+    };
+    mc.update_user(id, user_fu).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Groups
+async fn list_groups(
+    State(mc): State<ModelController>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Value>> {
+    println!("->> {:<12} - scim_list_groups", "HANDLER");
+
+    let groups = match params.filter.as_deref().map(group_filter).transpose()? {
+        Some(display_name) => mc.list_groups_by_display_name(&display_name).await?,
+        None => mc.list_groups().await?,
+    };
+    let mut resources = Vec::new();
+    for group in &groups {
+        resources.push(group_to_scim(&mc, group).await);
+    }
+    Ok(Json(list_response(resources, &params)))
+}
+
+async fn create_group(
+    State(mc): State<ModelController>,
+    Json(payload): Json<ScimGroup>,
+) -> Result<(StatusCode, Json<Value>)> {
+    println!("->> {:<12} - scim_create_group", "HANDLER");
+
+    let group = mc
+        .create_group(GroupForCreate {
+            display_name: payload.display_name,
+            members: member_ids(&payload.members)?,
+        })
+        .await?;
+    Ok((StatusCode::CREATED, Json(group_to_scim(&mc, &group).await)))
+}
+
+async fn get_group(State(mc): State<ModelController>, Path(id): Path<u64>) -> Result<Json<Value>> {
+    println!("->> {:<12} - scim_get_group", "HANDLER");
+
+    let group = mc.get_group(id).await?;
+    Ok(Json(group_to_scim(&mc, &group).await))
+}
+
+async fn patch_group(
+    State(mc): State<ModelController>,
+    Path(id): Path<u64>,
+    Json(payload): Json<ScimPatch>,
+) -> Result<Json<Value>> {
+    println!("->> {:<12} - scim_patch_group", "HANDLER");
+
+    let mut group_fu = GroupForUpdate::default();
+    for op in payload.operations {
+        let kind = op.op.to_ascii_lowercase();
+        let path = op.path.as_deref().map(|p| p.to_ascii_lowercase());
+        match (kind.as_str(), path.as_deref(), op.value) {
+            ("replace", Some("displayname"), Some(Value::String(name))) => {
+                group_fu.display_name = Some(name);
+            }
+            ("replace", Some("members"), Some(value)) => {
+                let members: Vec<ScimMember> = from_value(value)?;
+                group_fu.members = Some(member_ids(&members)?);
+            }
+            ("add", Some("members"), Some(value)) => {
+                let members: Vec<ScimMember> = from_value(value)?;
+                group_fu.add_members.extend(member_ids(&members)?);
+            }
+            ("remove", Some("members"), Some(value)) => {
+                let members: Vec<ScimMember> = from_value(value)?;
+                group_fu.remove_members.extend(member_ids(&members)?);
+            }
+            // 例如：members[value eq "2"]
+            ("remove", Some(path), _) if path.starts_with("members[") => {
+                let (_whole, id) = regex_captures!(r#"^members\[value eq "(\d+)"\]$"#, path)
+                    .ok_or(Error::ScimPatchFailUnsupported { op: op.op.clone() })?;
+                group_fu.remove_members.push(parse_id(id)?);
+            }
+            ("replace", None, Some(Value::Object(attrs))) => {
+                if let Some(Value::String(name)) = attrs.get("displayName") {
+                    group_fu.display_name = Some(name.clone());
+                }
+                if let Some(members) = attrs.get("members") {
+                    let members: Vec<ScimMember> = from_value(members.clone())?;
+                    group_fu.members = Some(member_ids(&members)?);
+                }
+            }
+            _ => return Err(Error::ScimPatchFailUnsupported { op: op.op }),
+        }
+    }
+    let group = mc.update_group(id, group_fu).await?;
+    Ok(Json(group_to_scim(&mc, &group).await))
+}
+
+async fn delete_group(
+    State(mc): State<ModelController>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode> {
+    println!("->> {:<12} - scim_delete_group", "HANDLER");
+
+    mc.delete_group(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- SCIM payloads
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    filter: Option<String>,
+    #[serde(rename = "startIndex")]
+    start_index: Option<usize>,
+    count: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimUser {
+    user_name: String,
+    #[serde(default)]
+    emails: Vec<ScimEmail>,
+    active: Option<bool>,
+    password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScimEmail {
+    value: String,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimGroup {
+    display_name: String,
+    #[serde(default)]
+    members: Vec<ScimMember>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScimMember {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScimPatch {
+    #[serde(rename = "Operations")]
+    operations: Vec<ScimPatchOp>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScimPatchOp {
+    op: String,
+    path: Option<String>,
+    value: Option<Value>,
+}
+
+// --- Conversions
+fn user_to_scim(user: &User) -> Value {
+    json!({
+        "schemas": [SCHEMA_USER],
+        "id": user.id.to_string(),
+        "userName": user.username,
+        "active": user.active,
+        "emails": [{"value": user.email, "primary": true}],
+        "meta": {
+            "resourceType": "User",
+            "location": format!("/scim/v2/Users/{}", user.id),
+        },
+    })
+}
+
+async fn group_to_scim(mc: &ModelController, group: &Group) -> Value {
+    let mut members = Vec::new();
+    for &id in &group.members {
+        let display = mc.get_user(id).await.ok().flatten().map(|u| u.username);
+        members.push(json!({"value": id.to_string(), "display": display}));
+    }
+    json!({
+        "schemas": [SCHEMA_GROUP],
+        "id": group.id.to_string(),
+        "displayName": group.display_name,
+        "members": members,
+        "meta": {
+            "resourceType": "Group",
+            "location": format!("/scim/v2/Groups/{}", group.id),
+        },
+    })
+}
+
+// SCIM 的 startIndex 從 1 開始
+fn list_response(resources: Vec<Value>, params: &ListParams) -> Value {
+    let total = resources.len();
+    let start_index = params.start_index.unwrap_or(1).max(1);
+    let page: Vec<Value> = resources
+        .into_iter()
+        .skip(start_index - 1)
+        .take(params.count.unwrap_or(usize::MAX))
+        .collect();
+    json!({
+        "schemas": [SCHEMA_LIST],
+        "totalResults": total,
+        "startIndex": start_index,
+        "itemsPerPage": page.len(),
+        "Resources": page,
+    })
+}
+
+fn from_value<T: serde::de::DeserializeOwned>(value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|e| Error::ScimPatchFailUnsupported { op: e.to_string() })
+}
+
+fn primary_email(emails: &[ScimEmail]) -> Option<String> {
+    emails
+        .iter()
+        .find(|e| e.primary)
+        .or_else(|| emails.first())
+        .map(|e| e.value.clone())
+}
+
+fn member_ids(members: &[ScimMember]) -> Result<Vec<u64>> {
+    members.iter().map(|m| parse_id(&m.value)).collect()
+}
+
+fn parse_id(id: &str) -> Result<u64> {
+    id.parse().map_err(|_| Error::ScimPatchFailUnsupported {
+        op: format!("member {id}"),
+    })
+}
+
+fn apply_user_attr(user_fu: &mut UserForUpdate, path: &str, value: Value) -> Result<()> {
+    let path = path.to_ascii_lowercase();
+    match (path.as_str(), value) {
+        ("active", Value::Bool(active)) => user_fu.active = Some(active),
+        // 有些 IdP（例如 Azure AD）會將布林值以字串 "True"/"False" 送出
+        ("active", Value::String(active)) => {
+            user_fu.active = Some(active.eq_ignore_ascii_case("true"))
+        }
+        ("username", Value::String(username)) => user_fu.username = Some(username),
+        (path, Value::String(email)) if path.starts_with("emails") => user_fu.email = Some(email),
+        ("emails", emails @ Value::Array(_)) => {
+            let emails: Vec<ScimEmail> = from_value(emails)?;
+            user_fu.email = primary_email(&emails);
+        }
+        (path, _) => {
+            return Err(Error::ScimPatchFailUnsupported {
+                op: format!("path {path}"),
+            })
+        }
+    }
+    Ok(())
+}
+
+// --- Filter
+/// Parse a filter of format `[attribute] eq "[value]"`
+/// Returns (lowercased attribute, value)
+fn parse_filter(filter: &str) -> Result<(String, String)> {
+    let (_whole, attr, value) = regex_captures!(r#"^\s*([\w.]+)\s+eq\s+"(.*)"\s*$"#, filter)
+        .ok_or(Error::ScimFilterUnsupported {
+            filter: filter.to_string(),
+        })?;
+    Ok((attr.to_ascii_lowercase(), value.to_string()))
+}
+
+// 使用者可以依 userName 或 email 篩選，查詢之前先確認，不支援的屬性不論有沒有資料都回傳 invalidFilter
+fn user_filter(filter: &str) -> Result<UserFilter> {
+    match parse_filter(filter)? {
+        (attr, value) if attr == "username" => Ok(UserFilter::Username(value)),
+        (attr, value) if attr == "emails" || attr == "emails.value" => Ok(UserFilter::Email(value)),
+        _ => Err(Error::ScimFilterUnsupported {
+            filter: filter.to_string(),
+        }),
+    }
+}
+
+// 群組只能依 displayName 篩選，回傳要查詢的名稱
+fn group_filter(filter: &str) -> Result<String> {
+    match parse_filter(filter)? {
+        (attr, value) if attr == "displayname" => Ok(value),
+        _ => Err(Error::ScimFilterUnsupported {
+            filter: filter.to_string(),
+        }),
+    }
+}
@@ -1,13 +1,21 @@
 use std::net::ToSocketAddrs;
 
-use axum::extract::{FromRef, Path, State};
-use axum::routing::{delete, post};
+use async_trait::async_trait;
+use axum::extract::{FromRef, FromRequestParts, Path, Query, State};
+use axum::http::header::{self, HeaderName};
+use axum::http::request::Parts;
+use axum::routing::{delete, get, post, put};
 use axum::{Json, Router};
 
+use crate::config::config;
 use crate::ctx::Ctx;
 // 此檔案負責 MVC 的 controller layer
-use crate::model::{ModelController, Ticket, TicketForCreate};
-use crate::Result;
+use crate::model::{
+    ModelController, Ticket, TicketFilter, TicketForAssign, TicketForCreate, TicketForTransition,
+    TicketForUpdate, TicketHistory, TicketPage, TicketPageParams, TicketSearchPage,
+    TicketSearchParams, TicketSort, TicketTransitions,
+};
+use crate::{Error, Result};
 
 // 這邊是一種Dependency Injection的技巧，意味著你物件所需的子物件，是由外部“放”進去，而非自行生成
 // #[derive(Clone, FromRef)]
@@ -20,10 +28,72 @@ pub fn routes(mc: ModelController) -> Router {
     // let app_state = AppState { mc };
     Router::new()
         .route("/tickets", post(create_ticket).get(list_tickets))
-        .route("/tickets/:id", delete(delete_ticket))
+        .route("/tickets/assigned", get(list_assigned_tickets))
+        .route("/tickets/search", get(search_tickets))
+        .route(
+            "/tickets/:id",
+            get(get_ticket).patch(update_ticket).delete(delete_ticket),
+        )
+        .route(
+            "/tickets/:id/transitions",
+            get(list_ticket_transitions).post(transition_ticket),
+        )
+        .route(
+            "/tickets/:id/assignee",
+            put(assign_ticket).delete(unassign_ticket),
+        )
+        .route("/tickets/:id/history", get(list_ticket_history))
+        // 垃圾桶：列出、還原、永久刪除
+        .route("/tickets/trash", get(list_deleted_tickets))
+        .route("/tickets/trash/:id", delete(purge_ticket))
+        .route("/tickets/trash/:id/restore", post(restore_ticket))
         .with_state(mc)
 }
 
+// 單一 ticket 的回應，以 ETag 帶上目前的版本，client 修改時再透過 If-Match 送回來
+type TicketResponse = ([(HeaderName, String); 1], Json<Ticket>);
+
+fn ticket_response(ticket: Ticket) -> TicketResponse {
+    (
+        [(header::ETAG, format!("\"{}\"", ticket.version))],
+        Json(ticket),
+    )
+}
+
+// 修改與刪除 ticket 時的 If-Match，"*" 或沒有提供時為 None（不檢查版本）
+// 只支援單一個 strong ETag，例如 If-Match: "3"
+struct IfMatch(Option<u64>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            if config().ticket_require_if_match {
+                return Err(Error::TicketFailIfMatchRequired);
+            }
+            return Ok(Self(None));
+        };
+        let wrong_format = || Error::TicketFailIfMatchWrongFormat {
+            value: String::from_utf8_lossy(value.as_bytes()).to_string(),
+        };
+        let value = value.to_str().map_err(|_| wrong_format())?.trim();
+        if value == "*" {
+            return Ok(Self(None));
+        }
+        let version = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(wrong_format)?;
+        Ok(Self(Some(version)))
+    }
+}
+
 // state 是 application level 的，他的 scope 更廣，可以讓所有 handler 共用
 // 這邊主要處理邏輯層的部分，並依據需求調用資料層的部分
 // --- REST Handlers
@@ -31,27 +101,171 @@ async fn create_ticket(
     State(mc): State<ModelController>,
     ctx: Ctx,
     Json(ticket_fc): Json<TicketForCreate>,
-) -> Result<Json<Ticket>> {
+) -> Result<TicketResponse> {
     println!("->> {:<12} - create_ticket", "HANDLER");
 
     let ticket = mc.create_ticket(ctx, ticket_fc).await?;
-    Ok(Json(ticket))
+    Ok(ticket_response(ticket))
+}
+
+async fn list_tickets(
+    State(mc): State<ModelController>,
+    Query(filter): Query<TicketFilter>,
+    Query(sort): Query<TicketSort>,
+    Query(page): Query<TicketPageParams>,
+) -> Result<Json<TicketPage>> {
+    println!(
+        "->> {:<12} - list_tickets - {filter:?} {sort:?} {page:?}",
+        "HANDLER"
+    );
+
+    let page = mc.list_tickets(filter, sort, page).await?;
+    Ok(Json(page))
+}
+
+// 全文檢索標題、描述與留言，例如 /api/tickets/search?q=登入失敗
+async fn search_tickets(
+    State(mc): State<ModelController>,
+    Query(params): Query<TicketSearchParams>,
+) -> Result<Json<TicketSearchPage>> {
+    println!("->> {:<12} - search_tickets - {params:?}", "HANDLER");
+
+    let page = mc.search_tickets(params).await?;
+    Ok(Json(page))
 }
 
-async fn list_tickets(State(mc): State<ModelController>, ctx: Ctx) -> Result<Json<Vec<Ticket>>> {
-    println!("->> {:<12} - list_tickets", "HANDLER");
+async fn list_assigned_tickets(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+) -> Result<Json<Vec<Ticket>>> {
+    println!("->> {:<12} - list_assigned_tickets", "HANDLER");
 
-    let tickets = mc.list_tickets(ctx).await?;
+    let tickets = mc.list_assigned_tickets(ctx).await?;
     Ok(Json(tickets))
 }
 
+async fn get_ticket(
+    State(mc): State<ModelController>,
+    Path(id): Path<u64>,
+) -> Result<TicketResponse> {
+    println!("->> {:<12} - get_ticket", "HANDLER");
+
+    let ticket = mc.get_ticket(id).await?;
+    Ok(ticket_response(ticket))
+}
+
+async fn update_ticket(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+    IfMatch(if_match): IfMatch,
+    Json(ticket_fu): Json<TicketForUpdate>,
+) -> Result<TicketResponse> {
+    println!("->> {:<12} - update_ticket", "HANDLER");
+
+    let ticket = mc.update_ticket(ctx, id, if_match, ticket_fu).await?;
+    Ok(ticket_response(ticket))
+}
+
+async fn list_ticket_transitions(
+    State(mc): State<ModelController>,
+    Path(id): Path<u64>,
+) -> Result<Json<TicketTransitions>> {
+    println!("->> {:<12} - list_ticket_transitions", "HANDLER");
+
+    let transitions = mc.ticket_transitions(id).await?;
+    Ok(Json(transitions))
+}
+
+async fn transition_ticket(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+    IfMatch(if_match): IfMatch,
+    Json(ticket_ft): Json<TicketForTransition>,
+) -> Result<TicketResponse> {
+    println!("->> {:<12} - transition_ticket", "HANDLER");
+
+    let ticket = mc.transition_ticket(ctx, id, if_match, ticket_ft).await?;
+    Ok(ticket_response(ticket))
+}
+
+async fn assign_ticket(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+    IfMatch(if_match): IfMatch,
+    Json(assignee): Json<TicketForAssign>,
+) -> Result<TicketResponse> {
+    println!("->> {:<12} - assign_ticket", "HANDLER");
+
+    let ticket = mc.assign_ticket(ctx, id, if_match, assignee).await?;
+    Ok(ticket_response(ticket))
+}
+
+async fn unassign_ticket(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+    IfMatch(if_match): IfMatch,
+) -> Result<TicketResponse> {
+    println!("->> {:<12} - unassign_ticket", "HANDLER");
+
+    let ticket = mc.unassign_ticket(ctx, id, if_match).await?;
+    Ok(ticket_response(ticket))
+}
+
 async fn delete_ticket(
     State(mc): State<ModelController>,
     ctx: Ctx,
     Path(id): Path<u64>,
-) -> Result<Json<Ticket>> {
+    IfMatch(if_match): IfMatch,
+) -> Result<TicketResponse> {
     println!(">>> {:<12} - delete_ticket", "HANDLER");
 
-    let ticket = mc.delete_ticket(ctx, id).await?;
+    let ticket = mc.delete_ticket(ctx, id, if_match).await?;
+    Ok(ticket_response(ticket))
+}
+
+async fn list_ticket_history(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+) -> Result<Json<Vec<TicketHistory>>> {
+    println!("->> {:<12} - list_ticket_history", "HANDLER");
+
+    let history = mc.list_ticket_history(ctx, id).await?;
+    Ok(Json(history))
+}
+
+async fn list_deleted_tickets(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+) -> Result<Json<Vec<Ticket>>> {
+    println!("->> {:<12} - list_deleted_tickets", "HANDLER");
+
+    let tickets = mc.list_deleted_tickets(ctx).await?;
+    Ok(Json(tickets))
+}
+
+async fn restore_ticket(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+) -> Result<TicketResponse> {
+    println!("->> {:<12} - restore_ticket", "HANDLER");
+
+    let ticket = mc.restore_ticket(ctx, id).await?;
+    Ok(ticket_response(ticket))
+}
+
+async fn purge_ticket(
+    State(mc): State<ModelController>,
+    ctx: Ctx,
+    Path(id): Path<u64>,
+) -> Result<Json<Ticket>> {
+    println!("->> {:<12} - purge_ticket", "HANDLER");
+
+    let ticket = mc.purge_ticket(ctx, id).await?;
     Ok(Json(ticket))
 }
@@ -0,0 +1,88 @@
+#![allow(unused)]
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+
+mod common;
+use common::{Backend, Service, TEST_TOKEN_KEY};
+
+// auth token 有簽章與到期時間，自行組出的 token 或過期的 token 都不能使用
+#[tokio::test]
+async fn auth_token_signed_and_expiring() -> Result<()> {
+    let svc = Service::start(&Backend::Memory).await?;
+    let hc = login!(svc);
+    let token = hc.cookie_value("auth-token").unwrap();
+    assert_eq!(get_tickets(&svc, &token).await?, 200);
+
+    // script 讀不到、其他網站的 POST 不會帶上；以 HTTP 啟動時沒有 Secure
+    let res = reqwest::Client::new()
+        .post(svc.url("/api/login"))
+        .json(&json!({"username": "demo1", "pwd": "welcome"}))
+        .send()
+        .await?;
+    let set_cookie = res.headers()["set-cookie"].to_str()?;
+    for attr in ["HttpOnly", "SameSite=Lax", "Path=/"] {
+        assert!(set_cookie.contains(attr), "{set_cookie}");
+    }
+    assert!(!set_cookie.contains("Secure"), "{set_cookie}");
+
+    // 竄改 id、到期時間或簽章，以及舊格式的 token 都會被拒絕
+    let (content, sign) = token.rsplit_once('.').unwrap();
+    let (_, exp) = content.split_once('.').unwrap();
+    for forged in [
+        "user-1.x.y".to_string(),
+        "user-1.exp.sign".to_string(),
+        format!("user-2.{exp}.{sign}"),
+        format!("user-1.{}.{sign}", exp.parse::<u64>()? + 1),
+        format!("{content}.{sign}x"),
+        // 以正確的金鑰簽章，但沒有加上 auth token 的前綴，例如拿其他種類 token 的簽章來冒充
+        format!("{content}.{}", hmac_sign(content)),
+    ] {
+        assert_eq!(get_tickets(&svc, &forged).await?, 403, "{forged}");
+    }
+
+    // 有效期限為 0 時，登入後拿到的 token 已經過期
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[("SERVICE_TOKEN_DURATION_SECS", "0".into())],
+    )
+    .await?;
+    let hc = login!(svc);
+    let token = hc.cookie_value("auth-token").unwrap();
+    assert_eq!(get_tickets(&svc, &token).await?, 403);
+    Ok(())
+}
+
+// 沒有設定 SERVICE_TOKEN_KEY（或設定為空字串）時拒絕啟動，不會以公開的開發用金鑰簽章
+#[tokio::test]
+async fn token_key_required() -> Result<()> {
+    common::assert_start_refused(&Backend::Memory, &[("SERVICE_TOKEN_KEY", String::new())]).await?;
+    // 明確允許時才使用開發用的金鑰
+    Service::start_with(
+        &Backend::Memory,
+        &[
+            ("SERVICE_TOKEN_KEY", String::new()),
+            ("SERVICE_ALLOW_DEV_TOKEN_KEY", "true".into()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+async fn get_tickets(svc: &Service, token: &str) -> Result<u16> {
+    let res = reqwest::Client::new()
+        .get(svc.url("/api/tickets"))
+        .header("cookie", format!("auth-token={token}"))
+        .send()
+        .await?;
+    Ok(res.status().as_u16())
+}
+
+fn hmac_sign(content: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(TEST_TOKEN_KEY.as_bytes()).unwrap();
+    mac.update(content.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
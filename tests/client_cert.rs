@@ -0,0 +1,132 @@
+#![allow(unused)]
+use std::path::Path;
+
+use anyhow::Result;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+};
+use serde_json::json;
+
+mod common;
+use common::{temp_path, Backend, Service};
+
+// 以測試用的 CA 簽發伺服器與 client 的憑證，並以 mutual TLS 啟動服務
+// 帶有有效 client certificate 的連線不需要 cookie；cookie 無效時仍然以憑證的身份存取
+#[tokio::test]
+async fn client_cert_auth() -> Result<()> {
+    let dir = temp_path("tls");
+    std::fs::create_dir(&dir)?;
+    let result = client_cert_scenario(&dir).await;
+    std::fs::remove_dir_all(&dir)?;
+    result
+}
+
+async fn client_cert_scenario(dir: &Path) -> Result<()> {
+    let ca = new_ca()?;
+    let server = new_cert(&["localhost"], "localhost", None)?;
+    let client = new_cert(&[], "demo1", Some(ExtendedKeyUsagePurpose::ClientAuth))?;
+    let unknown = new_cert(&[], "nobody", Some(ExtendedKeyUsagePurpose::ClientAuth))?;
+
+    let write = |name: &str, pem: String| -> Result<String> {
+        let path = dir.join(name);
+        std::fs::write(&path, pem)?;
+        Ok(path.display().to_string())
+    };
+    let ca_pem = ca.serialize_pem()?;
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[
+            (
+                "SERVICE_TLS_CERT",
+                write("server.pem", server.serialize_pem_with_signer(&ca)?)?,
+            ),
+            (
+                "SERVICE_TLS_KEY",
+                write("server.key", server.serialize_private_key_pem())?,
+            ),
+            ("SERVICE_TLS_CLIENT_CA", write("ca.pem", ca_pem.clone())?),
+        ],
+    )
+    .await?;
+    let port = svc.addr.rsplit_once(':').unwrap().1;
+    let url = format!("https://localhost:{port}/api/tickets");
+
+    let https_client = |identity: Option<&Certificate>| -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(ca_pem.as_bytes())?);
+        if let Some(cert) = identity {
+            builder = builder.identity(reqwest::Identity::from_pkcs8_pem(
+                cert.serialize_pem_with_signer(&ca)?.as_bytes(),
+                cert.serialize_private_key_pem().as_bytes(),
+            )?);
+        }
+        Ok(builder.build()?)
+    };
+    let with_cert = https_client(Some(&client))?;
+    let without_cert = https_client(None)?;
+    let forged_cookie = "auth-token=user-1.9999999999.forged";
+
+    // 只有憑證
+    let res = with_cert.get(&url).send().await?;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // 憑證加上無效的 cookie，仍然以憑證的身份存取，並清除無效的 cookie
+    let res = with_cert
+        .get(&url)
+        .header("cookie", forged_cookie)
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 200);
+    assert!(res.headers()["set-cookie"]
+        .to_str()?
+        .starts_with("auth-token=;"));
+
+    // 沒有憑證時，無效的 cookie 與沒有 cookie 都不能存取
+    let res = without_cert
+        .get(&url)
+        .header("cookie", forged_cookie)
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = without_cert.get(&url).send().await?;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // 以 HTTPS 啟動時，登入發放的 cookie 加上 Secure
+    let login_url = format!("https://localhost:{port}/api/login");
+    let res = without_cert
+        .post(&login_url)
+        .json(&json!({"username": "demo1", "pwd": "welcome"}))
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 200);
+    let set_cookie = res.headers()["set-cookie"].to_str()?;
+    assert!(set_cookie.contains("Secure"), "{set_cookie}");
+    assert!(set_cookie.contains("HttpOnly"), "{set_cookie}");
+
+    // 憑證的 CN 沒有對應的使用者
+    let res = https_client(Some(&unknown))?.get(&url).send().await?;
+    assert_eq!(res.status().as_u16(), 403);
+    Ok(())
+}
+
+fn new_ca() -> Result<Certificate> {
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Test CA");
+    Ok(Certificate::from_params(params)?)
+}
+
+fn new_cert(
+    san: &[&str],
+    common_name: &str,
+    usage: Option<ExtendedKeyUsagePurpose>,
+) -> Result<Certificate> {
+    let mut params = CertificateParams::new(san.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+    params
+        .distinguished_name
+        .push(DnType::CommonName, common_name);
+    params.extended_key_usages = usage.into_iter().collect();
+    Ok(Certificate::from_params(params)?)
+}
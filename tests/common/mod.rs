@@ -0,0 +1,294 @@
+// 整合測試共用的工具：以指定的設定啟動服務，並提供已登入的 client
+// 每個服務使用各自的 port 與資料目錄，不同的測試可以同時執行
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use anyhow::Result;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::Executor;
+
+// 服務使用的儲存層，資料存放在各自的暫存目錄（或暫時的 postgres database）中
+#[derive(Clone)]
+pub enum Backend {
+    Memory,
+    // 記憶體儲存層加上 journal 與 snapshot
+    Journal(PathBuf),
+    Sqlite(PathBuf),
+    // ticket 與使用者都存放在 redb
+    Kv(PathBuf),
+    Postgres(String),
+}
+
+impl Backend {
+    pub fn journal() -> Self {
+        Self::Journal(temp_path("journal"))
+    }
+
+    pub fn sqlite() -> Self {
+        Self::Sqlite(temp_path("sqlite.db"))
+    }
+
+    pub fn kv() -> Self {
+        Self::Kv(temp_path("kv.redb"))
+    }
+
+    // 需要一個本地的 PostgreSQL，預設連線到 postgres://postgres@localhost:5432
+    // 可以透過 PG_TEST_URL 修改（不包含 database 名稱），連不上時回傳 None，由呼叫端略過測試
+    // 會建立一個暫時的 database，測試結束後由 cleanup 刪除
+    pub async fn postgres() -> Result<Option<Self>> {
+        let pg_url = std::env::var("PG_TEST_URL")
+            .unwrap_or_else(|_| "postgres://postgres@localhost:5432".into());
+        let admin = match PgPoolOptions::new()
+            .acquire_timeout(Duration::from_secs(2))
+            .connect(&format!("{pg_url}/postgres"))
+            .await
+        {
+            Ok(pool) => pool,
+            Err(e) => {
+                println!("PostgreSQL not available ({e}), skipping");
+                return Ok(None);
+            }
+        };
+        let db_name = format!("tickets_test_{}", uuid::Uuid::new_v4().simple());
+        admin
+            .execute(format!("CREATE DATABASE {db_name}").as_str())
+            .await?;
+        Ok(Some(Self::Postgres(format!("{pg_url}/{db_name}"))))
+    }
+
+    // 測試結束後刪除資料檔、目錄或暫時的 database
+    pub async fn cleanup(&self) -> Result<()> {
+        match self {
+            Self::Memory => {}
+            Self::Journal(dir) => std::fs::remove_dir_all(dir)?,
+            Self::Sqlite(path) | Self::Kv(path) => std::fs::remove_file(path)?,
+            Self::Postgres(db_url) => {
+                let (pg_url, db_name) = db_url.rsplit_once('/').unwrap();
+                let admin = PgPoolOptions::new()
+                    .connect(&format!("{pg_url}/postgres"))
+                    .await?;
+                admin
+                    .execute(format!("DROP DATABASE {db_name} WITH (FORCE)").as_str())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // 重新啟動後資料是否還在
+    pub fn is_persistent(&self) -> bool {
+        !matches!(self, Self::Memory)
+    }
+
+    fn envs(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Memory => vec![("SERVICE_TICKET_STORE", "memory".into())],
+            Self::Journal(dir) => vec![
+                ("SERVICE_TICKET_STORE", "memory".into()),
+                ("SERVICE_JOURNAL_DIR", dir.display().to_string()),
+            ],
+            Self::Sqlite(path) => vec![
+                ("SERVICE_TICKET_STORE", "sqlite".into()),
+                (
+                    "SERVICE_DB_URL",
+                    format!("sqlite://{}?mode=rwc", path.display()),
+                ),
+            ],
+            Self::Kv(path) => vec![
+                ("SERVICE_TICKET_STORE", "kv".into()),
+                ("SERVICE_USER_STORE", "kv".into()),
+                ("SERVICE_KV_PATH", path.display().to_string()),
+            ],
+            Self::Postgres(db_url) => vec![
+                ("SERVICE_TICKET_STORE", "postgres".into()),
+                ("SERVICE_DB_URL", db_url.clone()),
+            ],
+        }
+    }
+}
+
+// 服務一定要設定 SERVICE_TOKEN_KEY 才能啟動，測試共用這把金鑰，個別的測試可以再覆蓋
+pub const TEST_TOKEN_KEY: &str = "test-token-key";
+
+// 暫存目錄底下不會重複的路徑
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{name}", uuid::Uuid::new_v4().simple()))
+}
+
+// 執行中的服務，結束（drop）時關閉
+pub struct Service {
+    child: Child,
+    pub addr: String,
+}
+
+impl Service {
+    pub async fn start(backend: &Backend) -> Result<Self> {
+        Self::start_with(backend, &[]).await
+    }
+
+    // 除了儲存層之外，另外指定其他的設定，例如 SERVICE_HMAC_CLIENTS
+    pub async fn start_with(backend: &Backend, envs: &[(&str, String)]) -> Result<Self> {
+        Self::spawn(command(backend, envs)).await
+    }
+
+    // 不設定 SERVICE_SEED_DEMO_USER，以服務的預設值決定是否建立 demo1
+    pub async fn start_unseeded(backend: &Backend) -> Result<Self> {
+        let mut command = command(backend, &[]);
+        command.env_remove("SERVICE_SEED_DEMO_USER");
+        Self::spawn(command).await
+    }
+
+    // 限制服務可以寫入的檔案大小（RLIMIT_FSIZE），模擬磁碟空間不足等寫入失敗
+    // 忽略 SIGXFSZ，超過限制時 write 回傳錯誤（EFBIG），而不是直接結束服務，ulimit -f 的單位是 512 bytes
+    pub async fn start_with_file_limit(
+        backend: &Backend,
+        envs: &[(&str, String)],
+        max_bytes: u64,
+    ) -> Result<Self> {
+        let mut sh = Command::new("sh");
+        sh.arg("-c")
+            .arg(format!(
+                "trap '' XFSZ; ulimit -f {}; exec \"$0\"",
+                max_bytes / 512
+            ))
+            .arg(env!("CARGO_BIN_EXE_my_first_axum"));
+        Self::spawn(configure(sh, backend, envs)).await
+    }
+
+    async fn spawn(mut command: Command) -> Result<Self> {
+        let addr = free_addr()?;
+        let child = command
+            .env("SERVICE_ADDR", &addr)
+            .stdout(Stdio::null())
+            .spawn()?;
+        let service = Service { child, addr };
+        for _ in 0..100 {
+            if tokio::net::TcpStream::connect(&service.addr).await.is_ok() {
+                return Ok(service);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        anyhow::bail!("service did not start on {}", service.addr)
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    // 以 SIGTERM 關閉，讓服務完成 graceful shutdown（例如寫入 snapshot）
+    pub async fn stop(mut self) -> Result<()> {
+        Command::new("kill")
+            .arg(self.child.id().to_string())
+            .status()?;
+        for _ in 0..100 {
+            if self.child.try_wait()?.is_some() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        anyhow::bail!("service on {} did not stop", self.addr)
+    }
+}
+
+// 設定有誤時服務應該在啟動時就結束，仍在執行時視為失敗（drop 時不會自動結束，所以先 kill）
+pub async fn assert_start_refused(backend: &Backend, envs: &[(&str, String)]) -> Result<()> {
+    let mut child = command(backend, envs)
+        .env("SERVICE_ADDR", "127.0.0.1:0")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut status = None;
+    for _ in 0..100 {
+        status = child.try_wait()?;
+        if status.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let _ = child.kill();
+    anyhow::ensure!(
+        matches!(status, Some(status) if !status.success()),
+        "service started with {envs:?}"
+    );
+    Ok(())
+}
+
+// 執行測試的環境中的 SERVICE_* 不會傳給服務，避免影響測試結果
+// 測試都以 demo1 登入，所以每一種儲存層都建立 demo1，個別的測試可以再覆蓋
+fn command(backend: &Backend, envs: &[(&str, String)]) -> Command {
+    configure(
+        Command::new(env!("CARGO_BIN_EXE_my_first_axum")),
+        backend,
+        envs,
+    )
+}
+
+fn configure(mut command: Command, backend: &Backend, envs: &[(&str, String)]) -> Command {
+    for (name, _) in std::env::vars().filter(|(name, _)| name.starts_with("SERVICE_")) {
+        command.env_remove(name);
+    }
+    command
+        .env("SERVICE_TOKEN_KEY", TEST_TOKEN_KEY)
+        .env("SERVICE_SEED_DEMO_USER", "true")
+        .envs(backend.envs())
+        .envs(envs.iter().map(|(name, value)| (name, value)));
+    command
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// 建立一個已登入的 httpc_test client，沒有指定帳號時使用預設的管理者帳號（demo1）
+// httpc_test 沒有公開 Client 的型別，無法寫成回傳 Client 的函數，所以以 macro 實作
+#[macro_export]
+macro_rules! login {
+    ($svc:expr) => {
+        login!($svc, "demo1", "welcome")
+    };
+    ($svc:expr, $username:expr, $pwd:expr) => {{
+        let hc = httpc_test::new_client($svc.url(""))?;
+        let res = hc
+            .do_post(
+                "/api/login",
+                serde_json::json!({"username": $username, "pwd": $pwd}),
+            )
+            .await?;
+        anyhow::ensure!(res.status().is_success(), "login as {} failed", $username);
+        hc
+    }};
+}
+
+// 以管理者（demo1）的邀請建立一個一般成員，回傳成員的 user id，之後以 login! 登入
+pub async fn create_member(svc: &Service, username: &str, pwd: &str) -> Result<u64> {
+    let admin = login!(svc);
+    let res = admin
+        .do_post(
+            "/api/invitations",
+            serde_json::json!({"email": format!("{username}@example.com"), "role": "Member"}),
+        )
+        .await?;
+    let token = res.json_body()?["token"].as_str().unwrap().to_string();
+    let res = httpc_test::new_client(svc.url(""))?
+        .do_post(
+            "/api/invitations/accept",
+            serde_json::json!({"token": token, "username": username, "pwd": pwd}),
+        )
+        .await?;
+    anyhow::ensure!(
+        res.status().is_success(),
+        "accept invitation for {username} failed"
+    );
+    Ok(res.json_body()?["result"]["user"]["id"].as_u64().unwrap())
+}
+
+// 由系統分配一個目前沒有被使用的 port
+fn free_addr() -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.to_string())
+}
@@ -0,0 +1,94 @@
+#![allow(unused)]
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+mod common;
+use common::{Backend, Service};
+
+const TOKEN_KEY: &str = "test-token-key";
+
+// 邀請 token 簽進 id、到期時間與 nonce，即使簽章正確，內容與儲存的邀請不符時也會被拒絕
+// 邀請的 id 由儲存層分配，重新啟動後也不會重複
+#[tokio::test]
+async fn invitation_token_bound_to_stored_invitation() -> Result<()> {
+    let backend = Backend::kv();
+    let svc = Service::start_with(&backend, &[("SERVICE_TOKEN_KEY", TOKEN_KEY.into())]).await?;
+    let hc = login!(svc);
+    let auth_token = hc.cookie_value("auth-token").unwrap();
+
+    let (id, token) = create_invitation(&svc, &auth_token, "demo2@example.com").await?;
+    let (content, _sign) = token.rsplit_once('.').unwrap();
+    let parts: Vec<&str> = content.split('.').collect();
+    let [_, exp, nonce] = parts[..] else {
+        panic!("unexpected token format: {token}");
+    };
+    let exp: u64 = exp.parse()?;
+
+    // 以同一把金鑰重新簽章，但到期時間、nonce 與儲存的不同，或是不含 nonce 的舊格式
+    for forged in [
+        format!("inv-{id}.{}.{nonce}", exp + 60),
+        format!("inv-{id}.{exp}.{}", "0".repeat(nonce.len())),
+        format!("inv-{id}.{exp}"),
+    ] {
+        let forged = format!("{forged}.{}", sign(&forged));
+        let res = accept(&svc, &forged, "demo2").await?;
+        assert_eq!(res.status().as_u16(), 403, "{forged}");
+        let body: Value = res.json().await?;
+        assert_eq!(body["error"]["type"], "INVALID_INVITATION");
+    }
+
+    // 原本的 token 仍然可以使用，而且只能使用一次
+    let res = accept(&svc, &token, "demo2").await?;
+    assert!(res.status().is_success());
+    let res = accept(&svc, &token, "demo3").await?;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // 重新啟動後邀請仍然存在，新的邀請使用新的 id
+    svc.stop().await?;
+    let svc = Service::start_with(&backend, &[("SERVICE_TOKEN_KEY", TOKEN_KEY.into())]).await?;
+    let hc = login!(svc);
+    let auth_token = hc.cookie_value("auth-token").unwrap();
+    let (next_id, _) = create_invitation(&svc, &auth_token, "demo3@example.com").await?;
+    assert!(next_id > id);
+    let res = hc.do_get("/api/invitations").await?;
+    let invitations = res.json_body()?;
+    assert_eq!(invitations.as_array().unwrap().len(), 2);
+    assert_eq!(invitations[0]["status"], "Accepted");
+    assert!(invitations[0].get("nonce").is_none());
+
+    drop(svc);
+    backend.cleanup().await?;
+    Ok(())
+}
+
+// httpc-test 沒有公開 Client 的型別，以 reqwest 帶上 auth-token 送出
+async fn create_invitation(svc: &Service, auth_token: &str, email: &str) -> Result<(u64, String)> {
+    let body: Value = reqwest::Client::new()
+        .post(svc.url("/api/invitations"))
+        .header("cookie", format!("auth-token={auth_token}"))
+        .json(&json!({"email": email, "role": "Member"}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let id = body["invitation"]["id"].as_u64().unwrap();
+    Ok((id, body["token"].as_str().unwrap().to_string()))
+}
+
+async fn accept(svc: &Service, token: &str, username: &str) -> Result<reqwest::Response> {
+    Ok(reqwest::Client::new()
+        .post(svc.url("/api/invitations/accept"))
+        .json(&json!({"token": token, "username": username, "pwd": "welcome2"}))
+        .send()
+        .await?)
+}
+
+fn sign(content: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(TOKEN_KEY.as_bytes()).unwrap();
+    mac.update(content.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
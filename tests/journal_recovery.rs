@@ -0,0 +1,190 @@
+#![allow(unused)]
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// 當機時 journal 的最後一行可能不完整，重新啟動時應該從 snapshot 與完整的 journal 還原，並忽略不完整的那一行
+// 之後的異動也要能正常寫入，下一次啟動時仍然可以讀取
+#[tokio::test]
+async fn journal_truncated_last_line() -> Result<()> {
+    let backend = Backend::journal();
+    let result = truncated_last_line(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn truncated_last_line(backend: &Backend) -> Result<()> {
+    let Backend::Journal(dir) = backend else {
+        unreachable!()
+    };
+
+    // 正常關閉時寫入 snapshot，journal 清空
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    for title in ["Ticket 1", "Ticket 2"] {
+        hc.do_post("/api/tickets", json!({ "title": title }))
+            .await?;
+    }
+    svc.stop().await?;
+    assert!(dir.join("snapshot.json").exists());
+    assert_eq!(std::fs::metadata(dir.join("journal.jsonl"))?.len(), 0);
+
+    // 之後的異動只在 journal 中，直接結束（不寫 snapshot）模擬當機，並留下寫到一半的最後一行
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    hc.do_post("/api/tickets", json!({"title": "Ticket 3"}))
+        .await?;
+    drop(svc);
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join("journal.jsonl"))?
+        .write_all(br#"{"Create":{"id":99,"title":"Ticket"#)?;
+
+    // snapshot 加上完整的 journal 還原出三個 ticket，新的異動可以繼續寫入
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    assert_eq!(
+        titles(hc.do_get("/api/tickets").await?.json_body()?),
+        ["Ticket 1", "Ticket 2", "Ticket 3"]
+    );
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Ticket 4"}))
+        .await?;
+    assert_eq!(res.json_body()?["id"], 4);
+    drop(svc);
+
+    // 不完整的那一行已經被截掉，新的異動沒有接在它後面
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    assert_eq!(
+        titles(hc.do_get("/api/tickets").await?.json_body()?),
+        ["Ticket 1", "Ticket 2", "Ticket 3", "Ticket 4"]
+    );
+    Ok(())
+}
+
+// 累積的 journal 達到設定的筆數時在背景寫入 snapshot，之後的異動寫到新的 journal
+// 沒有正常關閉時，由背景寫入的 snapshot 加上之後的 journal 還原
+#[tokio::test]
+async fn journal_background_snapshot() -> Result<()> {
+    let backend = Backend::journal();
+    let result = background_snapshot(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn background_snapshot(backend: &Backend) -> Result<()> {
+    let Backend::Journal(dir) = backend else {
+        unreachable!()
+    };
+    let envs = [("SERVICE_JOURNAL_SNAPSHOT_EVERY", "3".to_string())];
+
+    let svc = Service::start_with(backend, &envs).await?;
+    let hc = login!(svc);
+    let expected: Vec<String> = (1..=5).map(|i| format!("Ticket {i}")).collect();
+    for title in &expected {
+        hc.do_post("/api/tickets", json!({ "title": title }))
+            .await?;
+    }
+    // 等背景的 snapshot 寫完，pending 的 journal 會被刪除
+    for _ in 0..50 {
+        if !dir.join("journal.pending.jsonl").exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(dir.join("snapshot.json").exists());
+    assert!(!dir.join("journal.pending.jsonl").exists());
+    drop(svc);
+
+    let svc = Service::start_with(backend, &envs).await?;
+    let hc = login!(svc);
+    assert_eq!(
+        titles(hc.do_get("/api/tickets").await?.json_body()?),
+        expected
+    );
+    Ok(())
+}
+
+// journal 寫入失敗（例如磁碟空間不足）時，檔案中不應該留下寫到一半的一行
+// 失敗的異動回傳 500，之後 journal 停用，記憶體中的資料也不再使用
+// 重新啟動後還原到失敗之前的狀態，新的異動可以繼續寫入
+#[tokio::test]
+async fn journal_write_failure() -> Result<()> {
+    let backend = Backend::journal();
+    let result = write_failure(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn write_failure(backend: &Backend) -> Result<()> {
+    let Backend::Journal(dir) = backend else {
+        unreachable!()
+    };
+
+    // 檔案最多 32 KB，超過的 ticket 只會寫入前面的一部分
+    let svc = Service::start_with_file_limit(backend, &[], 32 * 1024).await?;
+    let hc = login!(svc);
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Ticket 1"}))
+        .await?;
+    assert!(res.status().is_success());
+    let res = hc
+        .do_post(
+            "/api/tickets",
+            json!({"title": "Ticket 2", "description": "x".repeat(64 * 1024)}),
+        )
+        .await?;
+    assert_eq!(res.status(), 500);
+
+    // 截回最後一筆完整的異動
+    let journal = std::fs::read(dir.join("journal.jsonl"))?;
+    assert!(journal.ends_with(b"\n"));
+    let lines: Vec<serde_json::Value> = journal
+        .split_inclusive(|b| *b == b'\n')
+        .map(serde_json::from_slice)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(lines.len(), 1);
+
+    // journal 停用之後，讀寫都以 500 結束
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Ticket 3"}))
+        .await?;
+    assert_eq!(res.status(), 500);
+    assert_eq!(hc.do_get("/api/tickets").await?.status(), 500);
+    drop(svc);
+
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    assert_eq!(
+        titles(hc.do_get("/api/tickets").await?.json_body()?),
+        ["Ticket 1"]
+    );
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Ticket 3"}))
+        .await?;
+    assert_eq!(res.json_body()?["id"], 2);
+    drop(svc);
+
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    assert_eq!(
+        titles(hc.do_get("/api/tickets").await?.json_body()?),
+        ["Ticket 1", "Ticket 3"]
+    );
+    Ok(())
+}
+
+fn titles(page: serde_json::Value) -> Vec<String> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap().to_string())
+        .collect()
+}
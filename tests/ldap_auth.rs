@@ -0,0 +1,281 @@
+#![allow(unused)]
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+mod common;
+use common::{Backend, Service};
+
+// 不需要外部的 LDAP 伺服器：測試中啟動一個只支援 simple bind 與 search 的 LDAP 伺服器（FakeLdap）
+// 服務透過真正的 LDAP 連線驗證帳密，涵蓋 bind、查詢 email 與群組，以及群組對應到角色
+#[tokio::test]
+async fn ldap_login() -> Result<()> {
+    let ldap = FakeLdap::start(vec![
+        FakeUser::new("alice", "alice-pwd", &["admins"]),
+        FakeUser::new("bob", "bob-pwd", &["staff"]),
+    ])
+    .await?;
+    let svc = Service::start_with(&Backend::Memory, &ldap.envs("ldap,local")).await?;
+
+    // LDAP 帳密正確時應該登入成功並拿到 auth-token，admins 群組對應到 Admin
+    let hc = login!(svc, "alice", "alice-pwd");
+    assert!(hc.cookie_value("auth-token").is_some());
+    assert_eq!(hc.do_get("/api/tickets").await?.status(), 200);
+    assert_eq!(hc.do_get("/api/invitations").await?.status(), 200);
+
+    // 沒有對應到角色的群組為 Member
+    let hc = login!(svc, "bob", "bob-pwd");
+    assert_eq!(hc.do_get("/api/invitations").await?.status(), 403);
+
+    // 錯誤的密碼與空密碼都不能登入（空密碼在 LDAP 中是 unauthenticated bind）
+    let hc = httpc_test::new_client(svc.url(""))?;
+    for pwd in ["alice-wrong", ""] {
+        let res = hc
+            .do_post("/api/login", json!({"username": "alice", "pwd": pwd}))
+            .await?;
+        assert_eq!(res.status(), 403);
+    }
+
+    // LDAP 中沒有的帳號，由下一個 Authenticator（local）驗證
+    login!(svc);
+    Ok(())
+}
+
+// LDAP 連不上時，其他 Authenticator 判定帳密錯誤仍然是 403，而不是服務錯誤
+// 只有 LDAP 一個 Authenticator 時沒有人能判定帳密，才回傳服務錯誤
+#[tokio::test]
+async fn ldap_unreachable() -> Result<()> {
+    let ldap = FakeLdap::unreachable()?;
+
+    let svc = Service::start_with(&Backend::Memory, &ldap.envs("ldap,local")).await?;
+    login!(svc);
+    let hc = httpc_test::new_client(svc.url(""))?;
+    let res = hc
+        .do_post("/api/login", json!({"username": "demo1", "pwd": "wrong"}))
+        .await?;
+    assert_eq!(res.status(), 403);
+    drop(svc);
+
+    let svc = Service::start_with(&Backend::Memory, &ldap.envs("ldap")).await?;
+    let hc = httpc_test::new_client(svc.url(""))?;
+    let res = hc
+        .do_post("/api/login", json!({"username": "demo1", "pwd": "welcome"}))
+        .await?;
+    assert_eq!(res.status(), 500);
+    Ok(())
+}
+
+// 群組與角色的對應有誤時拒絕啟動，而不是把該群組當成 Member
+#[tokio::test]
+async fn ldap_invalid_group_roles_refused() -> Result<()> {
+    let ldap = FakeLdap::unreachable()?;
+    for group_roles in [
+        "admins=Root",
+        "admins",
+        "=Admin",
+        "staff=Member,admins:Admin",
+    ] {
+        let mut envs = ldap.envs("ldap,local");
+        envs.push(("SERVICE_LDAP_GROUP_ROLES", group_roles.to_string()));
+        common::assert_start_refused(&Backend::Memory, &envs).await?;
+    }
+    Ok(())
+}
+
+const PEOPLE_DN: &str = "ou=people,dc=example,dc=org";
+const GROUPS_DN: &str = "ou=groups,dc=example,dc=org";
+
+// LDAP 的 result code
+const RC_SUCCESS: u8 = 0;
+const RC_INVALID_CREDENTIALS: u8 = 49;
+
+struct FakeUser {
+    dn: String,
+    pwd: String,
+    mail: String,
+    groups: Vec<String>,
+}
+
+impl FakeUser {
+    fn new(uid: &str, pwd: &str, groups: &[&str]) -> Self {
+        Self {
+            dn: format!("uid={uid},{PEOPLE_DN}"),
+            pwd: pwd.to_string(),
+            mail: format!("{uid}@example.org"),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+}
+
+struct FakeLdap {
+    addr: String,
+}
+
+impl FakeLdap {
+    async fn start(users: Vec<FakeUser>) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        let users = Arc::new(users);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, users.clone()));
+            }
+        });
+        Ok(Self { addr })
+    }
+
+    // 取得一個沒有在 listen 的 port，連線會被拒絕
+    fn unreachable() -> Result<Self> {
+        let addr = TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string();
+        Ok(Self { addr })
+    }
+
+    fn envs(&self, authenticators: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("SERVICE_AUTHENTICATORS", authenticators.to_string()),
+            ("SERVICE_LDAP_URL", format!("ldap://{}", self.addr)),
+            (
+                "SERVICE_LDAP_BIND_DN_TEMPLATE",
+                format!("uid={{username}},{PEOPLE_DN}"),
+            ),
+            ("SERVICE_LDAP_GROUP_BASE_DN", GROUPS_DN.to_string()),
+            ("SERVICE_LDAP_GROUP_ROLES", "admins=Admin".to_string()),
+        ]
+    }
+}
+
+// 一條連線：依序處理 LDAPMessage，bind 成功之後的 search 回傳該使用者的資料
+// base 的查詢回傳 email，subtree 的查詢回傳所屬的群組，收到 unbind 時結束
+async fn serve(mut stream: TcpStream, users: Arc<Vec<FakeUser>>) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut bound: Option<usize> = None;
+    loop {
+        let Some((_, message, rest)) = read_tlv(&buf) else {
+            let mut chunk = [0; 4096];
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            continue;
+        };
+        let (_, id, message) = read_tlv(message).unwrap();
+        let (op, body, _) = read_tlv(message).unwrap();
+        let id = read_int(id);
+        let rest = rest.to_vec();
+        match op {
+            // BindRequest：version、name、simple password
+            0x60 => {
+                let (_, _version, body) = read_tlv(body).unwrap();
+                let (_, dn, body) = read_tlv(body).unwrap();
+                let (_, pwd, _) = read_tlv(body).unwrap();
+                bound = users
+                    .iter()
+                    .position(|u| u.dn.as_bytes() == dn && u.pwd.as_bytes() == pwd);
+                let rc = match bound {
+                    Some(_) => RC_SUCCESS,
+                    None => RC_INVALID_CREDENTIALS,
+                };
+                stream
+                    .write_all(&message_tlv(id, ldap_result(0x61, rc)))
+                    .await?;
+            }
+            // SearchRequest：baseObject、scope（0 為 base，2 為 subtree）
+            0x63 => {
+                let (_, _base, body) = read_tlv(body).unwrap();
+                let (_, scope, _) = read_tlv(body).unwrap();
+                if let Some(user) = bound.map(|i| &users[i]) {
+                    let entries = match scope {
+                        [0] => vec![search_entry(&user.dn, "mail", &user.mail)],
+                        _ => user
+                            .groups
+                            .iter()
+                            .map(|g| search_entry(&format!("cn={g},{GROUPS_DN}"), "cn", g))
+                            .collect(),
+                    };
+                    for entry in entries {
+                        stream.write_all(&message_tlv(id, entry)).await?;
+                    }
+                }
+                stream
+                    .write_all(&message_tlv(id, ldap_result(0x65, RC_SUCCESS)))
+                    .await?;
+            }
+            // UnbindRequest
+            0x42 => return Ok(()),
+            _ => {}
+        }
+        buf = rest;
+    }
+}
+
+// -- BER 編碼：LDAP 的訊息都是 tag、length、content，這裡只處理測試用到的部分
+
+// 回傳 tag、content 與剩下的資料，資料不完整時回傳 None
+fn read_tlv(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, buf) = buf.split_first()?;
+    let (&first, buf) = buf.split_first()?;
+    let (len, buf) = if first < 0x80 {
+        (first as usize, buf)
+    } else {
+        let n = (first & 0x7f) as usize;
+        let bytes = buf.get(..n)?;
+        let len = bytes.iter().fold(0, |len, b| (len << 8) | *b as usize);
+        (len, &buf[n..])
+    };
+    let content = buf.get(..len)?;
+    Some((tag, content, &buf[len..]))
+}
+
+fn read_int(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, b| (n << 8) | *b as u32)
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match content.len() {
+        len if len < 0x80 => out.push(len as u8),
+        len if len < 0x100 => out.extend([0x81, len as u8]),
+        len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn message_tlv(id: u32, op: Vec<u8>) -> Vec<u8> {
+    // INTEGER 是有號數，最高位元為 1 時前面要補 0
+    let mut id_bytes: Vec<u8> = id
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    if id_bytes.first().is_none_or(|b| b & 0x80 != 0) {
+        id_bytes.insert(0, 0);
+    }
+    tlv(0x30, &[tlv(0x02, &id_bytes), op].concat())
+}
+
+// BindResponse、SearchResultDone 等：resultCode、matchedDN、diagnosticMessage
+fn ldap_result(tag: u8, rc: u8) -> Vec<u8> {
+    tlv(
+        tag,
+        &[tlv(0x0a, &[rc]), tlv(0x04, b""), tlv(0x04, b"")].concat(),
+    )
+}
+
+// SearchResultEntry：objectName 與只有一個值的屬性
+fn search_entry(dn: &str, attr: &str, value: &str) -> Vec<u8> {
+    let attr = tlv(
+        0x30,
+        &[
+            tlv(0x04, attr.as_bytes()),
+            tlv(0x31, &tlv(0x04, value.as_bytes())),
+        ]
+        .concat(),
+    );
+    tlv(0x64, &[tlv(0x04, dn.as_bytes()), tlv(0x30, &attr)].concat())
+}
@@ -0,0 +1,66 @@
+#![allow(unused)]
+use my_first_axum::auth::{bind_dn, group_filter, role_for_groups};
+use my_first_axum::model::Role;
+
+// 不需要 LDAP 伺服器：DN 與 filter 的跳脫，以及群組對應到角色的規則
+
+#[test]
+fn bind_dn_escapes_username() {
+    let template = "cn={username},ou=people,dc=example,dc=org";
+    assert_eq!(
+        bind_dn(template, "alice"),
+        "cn=alice,ou=people,dc=example,dc=org"
+    );
+    // "," 與 "=" 不能讓使用者名稱變成另一個 RDN
+    assert_eq!(
+        bind_dn(template, "alice,ou=admins"),
+        "cn=alice\\2cou\\3dadmins,ou=people,dc=example,dc=org"
+    );
+    // 開頭的 "#" 與空白、結尾的空白也需要跳脫
+    assert_eq!(
+        bind_dn(template, "#bob "),
+        "cn=\\23bob\\20,ou=people,dc=example,dc=org"
+    );
+}
+
+#[test]
+fn group_filter_escapes_special_chars() {
+    assert_eq!(
+        group_filter("cn=alice,dc=example,dc=org", "alice"),
+        "(|(member=cn=alice,dc=example,dc=org)(uniqueMember=cn=alice,dc=example,dc=org)(memberUid=alice))"
+    );
+    // "*" 與括號不能變成 filter 的萬用字元或新的條件
+    let filter = group_filter("cn=a,dc=org", "*)(cn=*");
+    assert!(
+        filter.ends_with("(memberUid=\\2a\\29\\28cn=\\2a))"),
+        "{filter}"
+    );
+    let filter = group_filter("cn=a\\5cb,dc=org", "a");
+    assert!(filter.contains("(member=cn=a\\5c5cb,dc=org)"), "{filter}");
+}
+
+#[test]
+fn role_for_groups_picks_highest_role() {
+    let group_roles = vec![
+        ("admins".to_string(), Role::Admin),
+        ("staff".to_string(), Role::Member),
+    ];
+    let groups = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(role_for_groups(&group_roles, &groups(&[])), Role::Member);
+    assert_eq!(
+        role_for_groups(&group_roles, &groups(&["staff"])),
+        Role::Member
+    );
+    // 屬於多個群組時取權限最高的，群組名稱不分大小寫
+    assert_eq!(
+        role_for_groups(&group_roles, &groups(&["staff", "Admins"])),
+        Role::Admin
+    );
+    // 沒有對應設定的群組不影響角色
+    assert_eq!(
+        role_for_groups(&group_roles, &groups(&["admins-old", "guests"])),
+        Role::Member
+    );
+    assert_eq!(role_for_groups(&[], &groups(&["admins"])), Role::Member);
+}
@@ -0,0 +1,59 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+use sqlx::sqlite::SqlitePoolOptions;
+
+mod common;
+use common::{Backend, Service};
+
+// 密碼以 argon2id 雜湊，儲存的是包含 salt 與參數的 PHC 字串
+#[tokio::test]
+async fn pwd_hashed_with_argon2() -> Result<()> {
+    let backend = Backend::sqlite();
+    let result = argon2_hash(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn argon2_hash(backend: &Backend) -> Result<()> {
+    let Backend::Sqlite(path) = backend else {
+        unreachable!()
+    };
+    let db_url = format!("sqlite://{}", path.display());
+    let svc = Service::start(backend).await?;
+    let pool = SqlitePoolOptions::new().connect(&db_url).await?;
+    let hash = pwd_hash(&pool, "demo1").await?;
+    assert!(hash.starts_with("$argon2id$"), "{hash}");
+
+    // 密碼錯誤時不能登入，也不會改變雜湊
+    let res = reqwest::Client::new()
+        .post(svc.url("/api/login"))
+        .json(&json!({"username": "demo1", "pwd": "wrong"}))
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+    login!(svc);
+    assert_eq!(pwd_hash(&pool, "demo1").await?, hash);
+
+    // 不是 PHC 格式的雜湊一律驗證失敗
+    sqlx::query("UPDATE app_user SET pwd_hash = 'not-a-phc-string' WHERE username = 'demo1'")
+        .execute(&pool)
+        .await?;
+    let res = reqwest::Client::new()
+        .post(svc.url("/api/login"))
+        .json(&json!({"username": "demo1", "pwd": "welcome"}))
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+    pool.close().await;
+    Ok(())
+}
+
+async fn pwd_hash(pool: &sqlx::SqlitePool, username: &str) -> Result<String> {
+    Ok(
+        sqlx::query_scalar("SELECT pwd_hash FROM app_user WHERE username = ?")
+            .bind(username)
+            .fetch_one(pool)
+            .await?,
+    )
+}
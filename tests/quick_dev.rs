@@ -11,8 +11,8 @@ async fn quick_dev() -> Result<()> {
     hc.do_get("/hello?name=allen").await?.print().await?;
     // 嘗試path query是否成功
     hc.do_get("/hello2/allen").await?.print().await?;
-    // 嘗試fallback_service是否成功，是否將可以獲取目錄底下的資源
-    hc.do_get("/src/main.rs").await?.print().await?;
+    // 嘗試fallback_service是否成功，是否將可以獲取 ./static 底下的資源
+    hc.do_get("/index.html").await?.print().await?;
     // 嘗試登入api是否成功
     let req_login = hc.do_post("/api/login", json!({"username": "demo1", "pwd": "welcome"}));
     req_login.await?.print().await?;
@@ -24,9 +24,50 @@ async fn quick_dev() -> Result<()> {
     // 嘗試是否可以正常添加ticket至資料庫
     let req_create_ticket = hc.do_post("/api/tickets", json!({"title": "Ticket AAA"}));
     req_create_ticket.await?.print().await?;
+    // 嘗試取得單一ticket
+    hc.do_get("/api/tickets/1").await?.print().await?;
+    // 嘗試在ticket底下留言，並列出該ticket的留言
+    let req_create_comment = hc.do_post("/api/tickets/1/comments", json!({"body": "Comment AAA"}));
+    req_create_comment.await?.print().await?;
+    hc.do_get("/api/tickets/1/comments").await?.print().await?;
+    // 全文檢索標題、描述與留言
+    hc.do_get("/api/tickets/search?q=comment")
+        .await?
+        .print()
+        .await?;
+    // 查看ticket的異動紀錄
+    hc.do_get("/api/tickets/1/history").await?.print().await?;
     // 嘗試將添加的ticket刪除
     hc.do_delete("/api/tickets/1").await?.print().await?;
     // 檢查我們添加的ticket，是否有成功添加
     hc.do_get("/api/tickets").await?.print().await?;
+    // 以管理者身份建立邀請，並確認可以在列表中看到
+    let res = hc
+        .do_post(
+            "/api/invitations",
+            json!({"email": "demo2@example.com", "role": "Member"}),
+        )
+        .await?;
+    res.print().await?;
+    let token = res.json_body()?["token"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    hc.do_get("/api/invitations").await?.print().await?;
+    // 被邀請者使用另一個client（沒有登入的cookie）接受邀請，成功後應該會拿到auth-token
+    let hc2 = httpc_test::new_client("http://localhost:8080")?;
+    let req_accept = hc2.do_post(
+        "/api/invitations/accept",
+        json!({"token": token, "username": "demo2", "pwd": "welcome2"}),
+    );
+    req_accept.await?.print().await?;
+    // 同一個邀請不能被使用第二次
+    let req_accept = hc2.do_post(
+        "/api/invitations/accept",
+        json!({"token": token, "username": "demo2", "pwd": "welcome2"}),
+    );
+    req_accept.await?.print().await?;
+    // 一般成員沒有權限查看邀請
+    hc2.do_get("/api/invitations").await?.print().await?;
     Ok(())
 }
@@ -0,0 +1,166 @@
+#![allow(unused)]
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+mod common;
+use common::{Backend, Service};
+
+const KEY_ID: &str = "test-key";
+const SECRET: &str = "test-secret";
+// 與 KEY_ID 共用同一個 secret 的另一個 client
+const OTHER_KEY_ID: &str = "other-key";
+
+// 以 SERVICE_HMAC_CLIENTS 設定一個對應到 demo1 的 client，確認簽章錯誤、時間誤差、重送與過大的 body 都會被拒絕
+#[tokio::test]
+async fn request_signing() -> Result<()> {
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[(
+            "SERVICE_HMAC_CLIENTS",
+            format!("{KEY_ID}:{SECRET}:demo1;{OTHER_KEY_ID}:{SECRET}:demo1"),
+        )],
+    )
+    .await?;
+    let now = now_unix_secs();
+
+    // 正確的簽章可以建立 ticket
+    let body = json!({"title": "Signed ticket"}).to_string();
+    let signature = sign("POST", "/api/tickets", now, body.as_bytes());
+    let res = send(&svc, now, &signature, body.clone()).await?;
+    assert!(res.status().is_success());
+
+    // 同一個簽章不能再使用第二次
+    let res = send(&svc, now, &signature, body.clone()).await?;
+    assert_eq!(res.status().as_u16(), 401);
+    assert_eq!(error_type(res).await?, "NO_AUTH");
+
+    // 簽章與內容不符（body 被修改）
+    let signature = sign("POST", "/api/tickets", now, b"{}");
+    let res = send(&svc, now, &signature, body.clone()).await?;
+    assert_eq!(res.status().as_u16(), 401);
+    assert_eq!(error_type(res).await?, "NO_AUTH");
+
+    // 簽章包含 key id，共用 secret 的另一個 client 不能拿來使用
+    let other_body = json!({"title": "Other client"}).to_string();
+    let signature = sign("POST", "/api/tickets", now, other_body.as_bytes());
+    let res = send_as(&svc, OTHER_KEY_ID, now, &signature, other_body).await?;
+    assert_eq!(res.status().as_u16(), 401);
+    assert_eq!(error_type(res).await?, "NO_AUTH");
+
+    // 超出允許的時間誤差時，即使簽章正確也會被拒絕，並告知是時間的問題
+    let stale = now - 60 * 60;
+    let signature = sign("POST", "/api/tickets", stale, body.as_bytes());
+    let res = send(&svc, stale, &signature, body.clone()).await?;
+    assert_eq!(res.status().as_u16(), 401);
+    assert_eq!(error_type(res).await?, "SIGNATURE_CLOCK_SKEW");
+
+    // 超過大小限制的 body 不會被讀取，回應 413
+    let body = "x".repeat(2 * 1024 * 1024);
+    let signature = sign("POST", "/api/tickets", now, body.as_bytes());
+    let res = send(&svc, now, &signature, body).await?;
+    assert_eq!(res.status().as_u16(), 413);
+    assert_eq!(error_type(res).await?, "INVALID_PARAMS");
+
+    // 只有第一個請求建立了 ticket
+    let hc = login!(svc);
+    let res = hc.do_get("/api/tickets").await?;
+    assert_eq!(res.json_body()?["total"], 1);
+    Ok(())
+}
+
+// 格式錯誤的 client 設定在啟動時就拒絕，而不是悄悄略過該 client
+#[tokio::test]
+async fn malformed_hmac_clients_refused() -> Result<()> {
+    for clients in [
+        format!("{KEY_ID}:{SECRET}"),
+        format!("{KEY_ID}::demo1"),
+        format!("{KEY_ID}:{SECRET}:demo1;broken"),
+    ] {
+        common::assert_start_refused(&Backend::Memory, &[("SERVICE_HMAC_CLIENTS", clients)])
+            .await?;
+    }
+    Ok(())
+}
+
+// 兩個服務實例共用同一個 SQLite 資料庫，在一個實例使用過的簽章重送到另一個實例時同樣會被拒絕
+#[tokio::test]
+async fn request_signing_replay_across_instances() -> Result<()> {
+    let backend = Backend::sqlite();
+    let result = replay_across_instances(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn replay_across_instances(backend: &Backend) -> Result<()> {
+    let envs = [("SERVICE_HMAC_CLIENTS", format!("{KEY_ID}:{SECRET}:demo1"))];
+    let first = Service::start_with(backend, &envs).await?;
+    let second = Service::start_with(backend, &envs).await?;
+    let now = now_unix_secs();
+
+    let body = json!({"title": "Signed ticket"}).to_string();
+    let signature = sign("POST", "/api/tickets", now, body.as_bytes());
+    let res = send(&first, now, &signature, body.clone()).await?;
+    assert!(res.status().is_success());
+    let res = send(&second, now, &signature, body).await?;
+    assert_eq!(res.status().as_u16(), 401);
+    assert_eq!(error_type(res).await?, "NO_AUTH");
+    Ok(())
+}
+
+async fn send(
+    svc: &Service,
+    timestamp: u64,
+    signature: &str,
+    body: String,
+) -> Result<reqwest::Response> {
+    send_as(svc, KEY_ID, timestamp, signature, body).await
+}
+
+async fn send_as(
+    svc: &Service,
+    key_id: &str,
+    timestamp: u64,
+    signature: &str,
+    body: String,
+) -> Result<reqwest::Response> {
+    Ok(reqwest::Client::new()
+        .post(svc.url("/api/tickets"))
+        .header("content-type", "application/json")
+        .header("x-api-key", key_id)
+        .header("x-timestamp", timestamp.to_string())
+        .header("x-signature", signature)
+        .body(body)
+        .send()
+        .await?)
+}
+
+// 與伺服器相同的簽章方式："[key-id]\n[METHOD]\n[path-and-query]\n[timestamp]\n[body-sha256-hex]"
+fn sign(method: &str, path_and_query: &str, timestamp: u64, body: &[u8]) -> String {
+    let body_hash: String = Sha256::digest(body)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let content = format!("{KEY_ID}\n{method}\n{path_and_query}\n{timestamp}\n{body_hash}");
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+    mac.update(content.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+async fn error_type(res: reqwest::Response) -> Result<String> {
+    let body: serde_json::Value = res.json().await?;
+    Ok(body["error"]["type"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn now_unix_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
@@ -0,0 +1,327 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+const SCIM_TOKEN: &str = "scim-test-token";
+
+// IdP 透過 SCIM 同步使用者與群組：Bearer token 驗證、建立、查詢（eq filter）、PATCH 與刪除
+// 錯誤以 RFC 7644 3.12 的 Error schema 回傳，Content-Type 為 application/scim+json
+// filter 由儲存層查詢，每一種儲存層都執行一次
+#[tokio::test]
+async fn scim_memory() -> Result<()> {
+    users_and_groups(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn scim_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn scim_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn scim_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = users_and_groups(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn users_and_groups(backend: &Backend) -> Result<()> {
+    let svc =
+        Service::start_with(backend, &[("SERVICE_SCIM_TOKEN", SCIM_TOKEN.to_string())]).await?;
+    let scim = Scim {
+        client: reqwest::Client::new(),
+        svc: &svc,
+    };
+
+    // -- Bearer token
+    for token in [None, Some("wrong-token")] {
+        let mut req = scim.client.get(svc.url("/scim/v2/Users"));
+        if let Some(token) = token {
+            req = req.bearer_auth(token);
+        }
+        let res = req.send().await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let (_, body) = scim_error(res).await?;
+        assert_eq!(body["status"], "401");
+        assert!(body.get("scimType").is_none());
+    }
+
+    // -- Users
+    let (status, alice) = scim
+        .send(
+            Method::POST,
+            "/scim/v2/Users",
+            json!({
+                "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+                "userName": "alice",
+                "emails": [{"value": "alice@example.com", "primary": true}],
+            }),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(alice["userName"], "alice");
+    assert_eq!(alice["active"], true);
+    let alice_id = alice["id"].as_str().unwrap().to_string();
+
+    let (status, body) = scim
+        .send(Method::POST, "/scim/v2/Users", json!({"userName": "alice"}))
+        .await?;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["scimType"], "uniqueness");
+
+    let (_, user) = scim
+        .send(
+            Method::GET,
+            &format!("/scim/v2/Users/{alice_id}"),
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(user["emails"][0]["value"], "alice@example.com");
+
+    // userName 的比對不分大小寫
+    let (_, list) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Users?filter=userName%20eq%20%22ALICE%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(list["totalResults"], 1);
+    assert_eq!(list["Resources"][0]["id"], alice_id);
+    let (_, list) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Users?filter=emails.value%20eq%20%22nobody@example.com%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(list["totalResults"], 0);
+    let (_, list) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Users?filter=emails%20eq%20%22Alice@Example.com%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(list["totalResults"], 1);
+    assert_eq!(list["Resources"][0]["id"], alice_id);
+
+    // 只支援 eq
+    let (status, body) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Users?filter=userName%20sw%20%22a%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["scimType"], "invalidFilter");
+
+    let (status, user) = scim
+        .send(
+            Method::PATCH,
+            &format!("/scim/v2/Users/{alice_id}"),
+            json!({
+                "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                "Operations": [
+                    {"op": "replace", "path": "emails", "value": [{"value": "alice@corp.example.com", "primary": true}]},
+                    {"op": "Replace", "value": {"active": "False"}},
+                ],
+            }),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["emails"][0]["value"], "alice@corp.example.com");
+    assert_eq!(user["active"], false);
+
+    let (status, body) = scim
+        .send(
+            Method::PATCH,
+            &format!("/scim/v2/Users/{alice_id}"),
+            json!({"Operations": [{"op": "replace", "path": "nickName", "value": "al"}]}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["scimType"], "invalidValue");
+
+    let (status, body) = scim
+        .send(Method::GET, "/scim/v2/Users/999", Value::Null)
+        .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["status"], "404");
+
+    // -- Groups
+    // 不支援的屬性在還沒有任何群組時也要回傳 invalidFilter
+    let (status, body) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Groups?filter=externalId%20eq%20%22x%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["scimType"], "invalidFilter");
+
+    let (status, group) = scim
+        .send(
+            Method::POST,
+            "/scim/v2/Groups",
+            json!({"displayName": "Engineering", "members": [{"value": alice_id}]}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(group["members"][0]["display"], "alice");
+    let group_url = format!("/scim/v2/Groups/{}", group["id"].as_str().unwrap());
+
+    let (_, list) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Groups?filter=displayName%20eq%20%22Engineering%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(list["totalResults"], 1);
+    let (_, list) = scim
+        .send(
+            Method::GET,
+            "/scim/v2/Groups?filter=displayName%20eq%20%22Sales%22",
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(list["totalResults"], 0);
+
+    // 預設的 demo1 帳號 id 為 1
+    let (_, group) = scim
+        .send(
+            Method::PATCH,
+            &group_url,
+            json!({"Operations": [
+                {"op": "add", "path": "members", "value": [{"value": "1"}]},
+                {"op": "replace", "path": "displayName", "value": "Platform"},
+            ]}),
+        )
+        .await?;
+    assert_eq!(group["displayName"], "Platform");
+    assert_eq!(group["members"].as_array().unwrap().len(), 2);
+
+    let (_, group) = scim
+        .send(
+            Method::PATCH,
+            &group_url,
+            json!({"Operations": [
+                {"op": "remove", "path": format!("members[value eq \"{alice_id}\"]")},
+            ]}),
+        )
+        .await?;
+    assert_eq!(
+        group["members"],
+        json!([{"value": "1", "display": "demo1"}])
+    );
+
+    // 不存在的成員
+    let (status, _) = scim
+        .send(
+            Method::PATCH,
+            &group_url,
+            json!({"Operations": [{"op": "add", "path": "members", "value": [{"value": "999"}]}]}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = scim.send(Method::DELETE, &group_url, Value::Null).await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = scim.send(Method::GET, &group_url, Value::Null).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 刪除後的 id 不會被重新使用，避免指派給舊群組的 ticket 被新的群組接手
+    let (_, group) = scim
+        .send(
+            Method::POST,
+            "/scim/v2/Groups",
+            json!({"displayName": "Support"}),
+        )
+        .await?;
+    assert_ne!(
+        format!("/scim/v2/Groups/{}", group["id"].as_str().unwrap()),
+        group_url
+    );
+    let (_, list) = scim
+        .send(Method::GET, "/scim/v2/Groups", Value::Null)
+        .await?;
+    assert_eq!(list["totalResults"], 1);
+
+    // SCIM 的 DELETE 只會停用帳號
+    let user_url = format!("/scim/v2/Users/{alice_id}");
+    let (status, _) = scim
+        .send(
+            Method::PATCH,
+            &user_url,
+            json!({"Operations": [{"op": "replace", "path": "active", "value": true}]}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = scim.send(Method::DELETE, &user_url, Value::Null).await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, user) = scim.send(Method::GET, &user_url, Value::Null).await?;
+    assert_eq!(user["active"], false);
+    Ok(())
+}
+
+struct Scim<'a> {
+    client: reqwest::Client,
+    svc: &'a Service,
+}
+
+impl Scim<'_> {
+    // 帶著 Bearer token 送出，回傳 status 與 JSON body（沒有 body 時為 Null）
+    // 錯誤的回應同時確認是 SCIM 的錯誤格式
+    async fn send(&self, method: Method, path: &str, body: Value) -> Result<(StatusCode, Value)> {
+        let mut req = self
+            .client
+            .request(method, self.svc.url(path))
+            .bearer_auth(SCIM_TOKEN);
+        if !body.is_null() {
+            req = req.json(&body);
+        }
+        let res = req.send().await?;
+        if res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await?;
+            let body = match text.is_empty() {
+                true => Value::Null,
+                false => serde_json::from_str(&text)?,
+            };
+            return Ok((status, body));
+        }
+        scim_error(res).await
+    }
+}
+
+async fn scim_error(res: reqwest::Response) -> Result<(StatusCode, Value)> {
+    let status = res.status();
+    assert_eq!(res.headers()["content-type"], "application/scim+json");
+    let body: Value = res.json().await?;
+    assert_eq!(
+        body["schemas"],
+        json!(["urn:ietf:params:scim:api:messages:2.0:Error"])
+    );
+    assert_eq!(body["status"], status.as_u16().to_string());
+    Ok((status, body))
+}
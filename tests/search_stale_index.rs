@@ -0,0 +1,60 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// 全文檢索的索引只存在各自的 process 中，兩個服務共用同一個 SQLite 時，其中一個刪除的 ticket 仍然留在另一個的索引裡
+// 查詢時只讀取該頁的 ticket，這些 ticket 不會出現在結果中，但仍計入另一個服務的總數
+// 刪除的服務自己的索引會移除該 ticket，總數與分頁都不包含它
+#[tokio::test]
+async fn search_skips_stale_hits() -> Result<()> {
+    let backend = Backend::sqlite();
+    let result = stale_hits(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn stale_hits(backend: &Backend) -> Result<()> {
+    let first = Service::start(backend).await?;
+    let hc_first = login!(first);
+    let mut ids = Vec::new();
+    for title in ["apple one", "apple two", "apple three"] {
+        let res = hc_first
+            .do_post("/api/tickets", json!({ "title": title }))
+            .await?;
+        ids.push(res.json_body()?["id"].as_i64().unwrap());
+    }
+
+    // 第二個服務啟動時從資料庫建立索引，之後第一個服務刪除的 ticket 不會從它的索引中移除
+    let second = Service::start(backend).await?;
+    let hc_second = login!(second);
+    let res = hc_first
+        .do_delete(&format!("/api/tickets/{}", ids[0]))
+        .await?;
+    assert!(res.status().is_success());
+
+    let res = hc_second.do_get("/api/tickets/search?q=apple").await?;
+    let page = res.json_body()?;
+    assert_eq!(page["total"], 3);
+    let items: Vec<i64> = page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["ticket"]["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(items, ids[1..]);
+
+    let res = hc_first.do_get("/api/tickets/search?q=apple").await?;
+    let page = res.json_body()?;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["items"].as_array().unwrap().len(), 2);
+    let res = hc_first
+        .do_get("/api/tickets/search?q=apple&limit=1&offset=1")
+        .await?;
+    let page = res.json_body()?;
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+    assert!(page["next_offset"].is_null());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+#![allow(unused)]
+use anyhow::Result;
+
+mod common;
+use common::{assert_start_refused, temp_path, Backend, Service};
+
+// 只公開 SERVICE_STATIC_DIR（預設為 ./static）底下的檔案，工作目錄中的原始碼與資料檔不能被下載
+#[tokio::test]
+async fn serves_only_static_dir() -> Result<()> {
+    let svc = Service::start(&Backend::Memory).await?;
+    let res = reqwest::get(svc.url("/index.html")).await?;
+    assert_eq!(res.status(), 200);
+    for path in ["/Cargo.toml", "/src/main.rs", "/../Cargo.toml"] {
+        let res = reqwest::get(svc.url(path)).await?;
+        assert_eq!(res.status(), 404, "{path}");
+    }
+    drop(svc);
+
+    let dir = temp_path("static");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("logo.txt"), "logo")?;
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[("SERVICE_STATIC_DIR", dir.display().to_string())],
+    )
+    .await?;
+    let res = reqwest::get(svc.url("/logo.txt")).await?;
+    assert_eq!(res.text().await?, "logo");
+    drop(svc);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// journal、資料庫與附件的路徑在公開的目錄底下時拒絕啟動，包含以 ".." 繞回目錄中的路徑
+#[tokio::test]
+async fn data_paths_in_static_dir_refused() -> Result<()> {
+    let data_paths = [
+        ("SERVICE_JOURNAL_DIR", "./static/journal"),
+        ("SERVICE_KV_PATH", "static/tickets.redb"),
+        (
+            "SERVICE_DB_URL",
+            "sqlite://./static/../static/tickets.db?mode=rwc",
+        ),
+        ("SERVICE_BLOB_DIR", "./static/blobs"),
+    ];
+    for (name, path) in data_paths {
+        assert_start_refused(&Backend::Memory, &[(name, path.to_string())]).await?;
+    }
+    // 公開整個工作目錄時，工作目錄中的資料檔也會被拒絕
+    assert_start_refused(
+        &Backend::Memory,
+        &[
+            ("SERVICE_STATIC_DIR", "./".to_string()),
+            ("SERVICE_KV_PATH", "tickets.redb".to_string()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// 同一組 HTTP 測試在每一種儲存層上執行，確認各儲存層的行為一致
+// 持久化的儲存層另外確認重新啟動後資料仍然存在
+
+#[tokio::test]
+async fn memory_store() -> Result<()> {
+    run(Backend::Memory).await
+}
+
+#[tokio::test]
+async fn journal_store() -> Result<()> {
+    run(Backend::journal()).await
+}
+
+#[tokio::test]
+async fn sqlite_store() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn kv_store() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn postgres_store() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = store_conformance(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn store_conformance(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+
+    // 建立 ticket
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Ticket PG"}))
+        .await?;
+    let id = res.json_body()?["id"].as_i64().unwrap();
+
+    let res = hc.do_get("/api/tickets").await?;
+    assert_eq!(res.json_body()?["items"][0]["title"], "Ticket PG");
+    assert_eq!(res.json_body()?["total"], 1);
+    let res = hc.do_get(&format!("/api/tickets/{id}")).await?;
+    assert_eq!(res.json_body()?["title"], "Ticket PG");
+    let auth_token = hc.cookie_value("auth-token").unwrap();
+
+    // 留言與附件，附件以 multipart 上傳，下載時回傳原本的內容與類型
+    hc.do_post(
+        &format!("/api/tickets/{id}/comments"),
+        json!({"body": "Comment PG"}),
+    )
+    .await?;
+    let file = reqwest::multipart::Part::bytes(b"log line".to_vec())
+        .file_name("app.log")
+        .mime_str("text/plain")?;
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(svc.url(&format!("/api/tickets/{id}/attachments")))
+        .header("cookie", format!("auth-token={auth_token}"))
+        .multipart(reqwest::multipart::Form::new().part("file", file))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["filename"], "app.log");
+    assert_eq!(body["size"], 8);
+    let attachment_id = body["id"].as_i64().unwrap();
+    let res = reqwest::Client::new()
+        .get(svc.url(&format!("/api/tickets/{id}/attachments/{attachment_id}")))
+        .header("cookie", format!("auth-token={auth_token}"))
+        .send()
+        .await?;
+    assert_eq!(res.headers()["content-type"], "text/plain");
+    assert!(res.headers()["content-disposition"]
+        .to_str()?
+        .contains("filename=\"app.log\""));
+    assert_eq!(res.bytes().await?.as_ref(), b"log line");
+
+    // 刪除只會移到垃圾桶，查詢時視為找不到，再刪除一次會失敗
+    let res = hc.do_delete(&format!("/api/tickets/{id}")).await?;
+    assert!(res.status().is_success());
+    assert_eq!(res.json_body()?["deleted_by"], 1);
+    let res = hc.do_get(&format!("/api/tickets/{id}")).await?;
+    assert_eq!(res.status().as_u16(), 404);
+    let res = hc.do_delete(&format!("/api/tickets/{id}")).await?;
+    assert_eq!(res.status().as_u16(), 404);
+    let res = hc.do_get("/api/tickets/trash").await?;
+    assert_eq!(res.json_body()?[0]["id"], id);
+
+    // 還原後可以再次查詢
+    let res = hc
+        .do_post(&format!("/api/tickets/trash/{id}/restore"), json!({}))
+        .await?;
+    assert!(res.json_body()?["deleted_at"].is_null());
+    let res = hc.do_get(&format!("/api/tickets/{id}")).await?;
+    assert!(res.status().is_success());
+
+    // 永久刪除後不會再出現在垃圾桶中，底下的留言與附件也一併刪除，再刪除一次會失敗
+    hc.do_delete(&format!("/api/tickets/{id}")).await?;
+    let res = hc.do_delete(&format!("/api/tickets/trash/{id}")).await?;
+    assert!(res.status().is_success());
+    let res = hc.do_get("/api/tickets/trash").await?;
+    assert_eq!(res.json_body()?.as_array().unwrap().len(), 0);
+    for sub in ["comments", "attachments"] {
+        let res = hc.do_get(&format!("/api/tickets/{id}/{sub}")).await?;
+        assert_eq!(res.status().as_u16(), 404);
+    }
+    let res = hc.do_delete(&format!("/api/tickets/trash/{id}")).await?;
+    assert_eq!(res.status().as_u16(), 404);
+
+    // 永久刪除後仍然保留異動紀錄，依發生的順序列出
+    let res = hc.do_get(&format!("/api/tickets/{id}/history")).await?;
+    let history = res.json_body()?;
+    let history_len = history.as_array().unwrap().len();
+    let actions: Vec<&str> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| h["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions.first(), Some(&"Create"));
+    assert_eq!(
+        &actions[actions.len() - 3..],
+        ["Restore", "Delete", "Purge"]
+    );
+
+    // 刪除、還原與永久刪除記錄 deleted_at、deleted_by 與 version 的變化
+    let changes = |i: usize| {
+        let entry = &history[history_len - 3 + i];
+        let field = |name: &str| {
+            let change = entry["changes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|c| c["field"] == name)
+                .unwrap();
+            (change["before"].clone(), change["after"].clone())
+        };
+        (field("deleted_at"), field("deleted_by"), field("version"))
+    };
+    let (deleted_at, deleted_by, version) = changes(0);
+    assert!(deleted_at.0.is_u64() && deleted_at.1.is_null());
+    assert_eq!(deleted_by, (json!(1), json!(null)));
+    let restored_version = version.1.as_u64().unwrap();
+    assert_eq!(version.0, restored_version - 1);
+    let (deleted_at, deleted_by, version) = changes(1);
+    assert!(deleted_at.0.is_null() && deleted_at.1.is_u64());
+    assert_eq!(deleted_by, (json!(null), json!(1)));
+    assert_eq!(
+        version,
+        (json!(restored_version), json!(restored_version + 1))
+    );
+    let (deleted_at, deleted_by, version) = changes(2);
+    assert!(deleted_at.0.is_u64() && deleted_at.1.is_null());
+    assert_eq!(deleted_by, (json!(1), json!(null)));
+    assert_eq!(version, (json!(restored_version + 1), json!(null)));
+    let res = hc
+        .do_get(&format!("/api/tickets/{}/history", id + 1000))
+        .await?;
+    assert_eq!(res.status().as_u16(), 404);
+
+    // 被刪除的 id 不會被重新使用
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Ticket PG 2"}))
+        .await?;
+    assert!(res.json_body()?["id"].as_i64().unwrap() > id);
+
+    // 重新啟動後資料仍然存在
+    if backend.is_persistent() {
+        svc.stop().await?;
+        let svc = Service::start(backend).await?;
+        let hc = login!(svc);
+        let res = hc.do_get("/api/tickets").await?;
+        assert_eq!(res.json_body()?["items"][0]["title"], "Ticket PG 2");
+        assert_eq!(res.json_body()?["total"], 1);
+        let res = hc.do_get(&format!("/api/tickets/{id}/history")).await?;
+        assert_eq!(res.json_body()?.as_array().unwrap().len(), history_len);
+    }
+    Ok(())
+}
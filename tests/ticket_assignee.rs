@@ -0,0 +1,224 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+mod common;
+use common::{create_member, Backend, Service};
+
+const SCIM_TOKEN: &str = "scim-test-token";
+
+// 指派給使用者或群組、取消指派，以及 /api/tickets/assigned 列出直接或透過群組被指派的 ticket
+// 被指派的人（包含被指派群組的成員）可以修改 ticket，其他一般成員不行
+// 指派對象與群組成員都由儲存層查詢，每一種儲存層都執行一次
+#[tokio::test]
+async fn assignee_memory() -> Result<()> {
+    assign_and_list(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn assignee_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn assignee_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn assignee_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = assign_and_list(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn assign_and_list(backend: &Backend) -> Result<()> {
+    let svc =
+        Service::start_with(backend, &[("SERVICE_SCIM_TOKEN", SCIM_TOKEN.to_string())]).await?;
+    let admin = Client::login(&svc, "demo1", "welcome").await?;
+
+    // demo2 直接被指派，demo3 透過 Support 群組被指派，demo4 沒有被指派
+    let demo2_id = create_member(&svc, "demo2", "welcome2").await?;
+    let demo3_id = create_member(&svc, "demo3", "welcome3").await?;
+    create_member(&svc, "demo4", "welcome4").await?;
+    let demo2 = Client::login(&svc, "demo2", "welcome2").await?;
+    let demo3 = Client::login(&svc, "demo3", "welcome3").await?;
+    let demo4 = Client::login(&svc, "demo4", "welcome4").await?;
+    let res = reqwest::Client::new()
+        .post(svc.url("/scim/v2/Groups"))
+        .bearer_auth(SCIM_TOKEN)
+        .json(&json!({"displayName": "Support", "members": [{"value": demo3_id.to_string()}]}))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let group_id: u64 = res.json::<Value>().await?["id"].as_str().unwrap().parse()?;
+
+    let (_, user_ticket) = admin
+        .send(Method::POST, "/api/tickets", json!({"title": "For demo2"}))
+        .await?;
+    let user_ticket = user_ticket["id"].as_u64().unwrap();
+    let (_, group_ticket) = admin
+        .send(
+            Method::POST,
+            "/api/tickets",
+            json!({"title": "For Support"}),
+        )
+        .await?;
+    let group_ticket = group_ticket["id"].as_u64().unwrap();
+    admin
+        .send(Method::POST, "/api/tickets", json!({"title": "Unassigned"}))
+        .await?;
+
+    // 指派給使用者與群組
+    let (status, body) = admin
+        .send(
+            Method::PUT,
+            &format!("/api/tickets/{user_ticket}/assignee"),
+            json!({"type": "User", "id": demo2_id}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["assignee"], json!({"type": "User", "id": demo2_id}));
+    let (status, body) = admin
+        .send(
+            Method::PUT,
+            &format!("/api/tickets/{group_ticket}/assignee"),
+            json!({"type": "Group", "id": group_id}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["assignee"], json!({"type": "Group", "id": group_id}));
+
+    // 直接被指派與透過群組被指派的 ticket 都會列出，沒有被指派時為空
+    assert_eq!(demo2.assigned().await?, [user_ticket]);
+    assert_eq!(demo3.assigned().await?, [group_ticket]);
+    assert_eq!(demo4.assigned().await?, Vec::<u64>::new());
+
+    // 被指派的人與群組的成員可以修改，其他一般成員不能修改也不能重新指派
+    for (client, id) in [(&demo2, user_ticket), (&demo3, group_ticket)] {
+        let (status, _) = client
+            .send(
+                Method::PATCH,
+                &format!("/api/tickets/{id}"),
+                json!({"description": "working on it"}),
+            )
+            .await?;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, body) = demo4
+        .send(
+            Method::PATCH,
+            &format!("/api/tickets/{user_ticket}"),
+            json!({"description": "not mine"}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"]["type"], "NO_PERMISSION");
+    let (status, _) = demo4
+        .send(
+            Method::PUT,
+            &format!("/api/tickets/{user_ticket}/assignee"),
+            json!({"type": "User", "id": demo2_id}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = demo4
+        .send(
+            Method::DELETE,
+            &format!("/api/tickets/{group_ticket}/assignee"),
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 取消指派之後不再列出，原本被指派的人也不能再修改
+    let (status, body) = admin
+        .send(
+            Method::DELETE,
+            &format!("/api/tickets/{user_ticket}/assignee"),
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["assignee"].is_null());
+    assert_eq!(demo2.assigned().await?, Vec::<u64>::new());
+    let (status, _) = demo2
+        .send(
+            Method::PATCH,
+            &format!("/api/tickets/{user_ticket}"),
+            json!({"description": "still mine?"}),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 移到垃圾桶的 ticket 不會列出
+    admin
+        .send(
+            Method::DELETE,
+            &format!("/api/tickets/{group_ticket}"),
+            Value::Null,
+        )
+        .await?;
+    assert_eq!(demo3.assigned().await?, Vec::<u64>::new());
+    Ok(())
+}
+
+// httpc-test 的 do_put、do_patch 實際上送出的是 POST，改用 reqwest 並帶著登入取得的 auth-token
+struct Client<'a> {
+    client: reqwest::Client,
+    svc: &'a Service,
+    token: String,
+}
+
+impl<'a> Client<'a> {
+    async fn login(svc: &'a Service, username: &str, pwd: &str) -> Result<Self> {
+        let hc = login!(svc, username, pwd);
+        Ok(Self {
+            client: reqwest::Client::new(),
+            svc,
+            token: hc.cookie_value("auth-token").unwrap(),
+        })
+    }
+
+    // 回傳 status 與 JSON body（沒有 body 時為 Null）
+    async fn send(&self, method: Method, path: &str, body: Value) -> Result<(StatusCode, Value)> {
+        let mut req = self
+            .client
+            .request(method, self.svc.url(path))
+            .header("cookie", format!("auth-token={}", self.token));
+        if !body.is_null() {
+            req = req.json(&body);
+        }
+        let res = req.send().await?;
+        let status = res.status();
+        let text = res.text().await?;
+        let body = match text.is_empty() {
+            true => Value::Null,
+            false => serde_json::from_str(&text)?,
+        };
+        Ok((status, body))
+    }
+
+    async fn assigned(&self) -> Result<Vec<u64>> {
+        let (status, body) = self
+            .send(Method::GET, "/api/tickets/assigned", Value::Null)
+            .await?;
+        assert_eq!(status, StatusCode::OK);
+        Ok(body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["id"].as_u64().unwrap())
+            .collect())
+    }
+}
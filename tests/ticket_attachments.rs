@@ -0,0 +1,86 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::multipart::{Form, Part};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+const MAX_BYTES: usize = 1024;
+
+// 附件的大小上限與允許的類型：超過上限回傳 413，不允許的類型回傳 415
+// 下載時以上傳的類型回傳，非 ASCII 與特殊字元的檔名在 Content-Disposition 中被跳脫
+#[tokio::test]
+async fn attachment_limits_and_download() -> Result<()> {
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[("SERVICE_ATTACHMENT_MAX_BYTES", MAX_BYTES.to_string())],
+    )
+    .await?;
+    let hc = login!(svc);
+    let token = hc.cookie_value("auth-token").unwrap();
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Attachments"}))
+        .await?;
+    let id = res.json_body()?["id"].as_i64().unwrap();
+    let upload = |data: Vec<u8>, filename: &str, mime: &str| {
+        let part = Part::bytes(data)
+            .file_name(filename.to_string())
+            .mime_str(mime)
+            .unwrap();
+        reqwest::Client::new()
+            .post(svc.url(&format!("/api/tickets/{id}/attachments")))
+            .header("cookie", format!("auth-token={token}"))
+            .multipart(Form::new().percent_encode_noop().part("file", part))
+            .send()
+    };
+
+    // 剛好等於上限可以上傳，多一個 byte 就被拒絕
+    // 遠超過上限時由 request body 的上限拒絕，回傳相同的錯誤
+    let res = upload(vec![b'a'; MAX_BYTES], "max.log", "text/plain").await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    for size in [MAX_BYTES + 1, MAX_BYTES + 256 * 1024] {
+        let res = upload(vec![b'a'; size], "big.log", "text/plain").await?;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = res.json().await?;
+        assert_eq!(body["error"]["type"], "INVALID_PARAMS");
+    }
+
+    // 不允許的類型，charset 等參數不影響判斷
+    for mime in ["text/html", "text/html; charset=utf-8"] {
+        let res = upload(b"<script></script>".to_vec(), "page.html", mime).await?;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body: Value = res.json().await?;
+        assert_eq!(body["error"]["type"], "INVALID_PARAMS");
+    }
+    let res = hc.do_get(&format!("/api/tickets/{id}/attachments")).await?;
+    assert_eq!(res.json_body()?.as_array().unwrap().len(), 1);
+
+    // 下載：類型只保留 MIME type 本身，filename 以 "_" 取代非 ASCII 字元，filename* 保留完整的檔名
+    // 與瀏覽器相同以 UTF-8 送出檔名（percent_encode_noop），multipart 的檔名中不會出現引號（瀏覽器送出 %22）
+    let res = upload(
+        "第一行".as_bytes().to_vec(),
+        "報告 100%.txt",
+        "text/plain; charset=utf-8",
+    )
+    .await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = res.json().await?;
+    assert_eq!(body["filename"], "報告 100%.txt");
+    let attachment_id = body["id"].as_i64().unwrap();
+    let res = reqwest::Client::new()
+        .get(svc.url(&format!("/api/tickets/{id}/attachments/{attachment_id}")))
+        .header("cookie", format!("auth-token={token}"))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "text/plain");
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"__ 100%.txt\"; filename*=UTF-8''%E5%A0%B1%E5%91%8A%20100%25.txt"
+    );
+    assert_eq!(res.headers()["x-content-type-options"], "nosniff");
+    assert_eq!(res.bytes().await?.as_ref(), "第一行".as_bytes());
+    Ok(())
+}
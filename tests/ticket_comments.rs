@@ -0,0 +1,103 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+mod common;
+use common::{create_member, Backend, Service};
+
+// 只有作者可以修改留言，刪除則是作者與管理者，其他人回傳 403 NO_PERMISSION
+// 修改後留言列表回傳新的內容與修改時間
+#[tokio::test]
+async fn comment_permissions() -> Result<()> {
+    let svc = Service::start(&Backend::Memory).await?;
+    let admin = login!(svc);
+    create_member(&svc, "demo2", "welcome2").await?;
+    create_member(&svc, "demo3", "welcome3").await?;
+    let author = login!(svc, "demo2", "welcome2");
+    let other = login!(svc, "demo3", "welcome3");
+
+    let res = admin
+        .do_post("/api/tickets", json!({"title": "Comments"}))
+        .await?;
+    let ticket_id = res.json_body()?["id"].as_i64().unwrap();
+    let comments_url = format!("/api/tickets/{ticket_id}/comments");
+    let res = author
+        .do_post(&comments_url, json!({"body": "first draft"}))
+        .await?;
+    let comment_id = res.json_body()?["id"].as_i64().unwrap();
+    let comment_url = format!("{comments_url}/{comment_id}");
+
+    // 其他成員不能修改或刪除，管理者也不能修改別人的留言
+    for hc in [&other, &admin] {
+        let token = hc.cookie_value("auth-token").unwrap();
+        let (status, body) = send(
+            &svc,
+            &token,
+            Method::PATCH,
+            &comment_url,
+            json!({"body": "hijacked"}),
+        )
+        .await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["type"], "NO_PERMISSION");
+    }
+    let res = other.do_delete(&comment_url).await?;
+    assert_eq!(res.status().as_u16(), 403);
+    assert_eq!(res.json_body()?["error"]["type"], "NO_PERMISSION");
+    let res = author.do_get(&comments_url).await?;
+    let comments = res.json_body()?;
+    assert_eq!(comments[0]["body"], "first draft");
+    assert!(comments[0]["updated_at"].is_null());
+
+    // 作者修改後，列表中是新的內容
+    let token = author.cookie_value("auth-token").unwrap();
+    let (status, _) = send(
+        &svc,
+        &token,
+        Method::PATCH,
+        &comment_url,
+        json!({"body": "final"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    let res = other.do_get(&comments_url).await?;
+    let comments = res.json_body()?;
+    assert_eq!(comments.as_array().unwrap().len(), 1);
+    assert_eq!(comments[0]["body"], "final");
+    assert!(comments[0]["updated_at"].is_u64());
+
+    // 留言必須屬於網址中的 ticket
+    let res = admin
+        .do_post("/api/tickets", json!({"title": "Another"}))
+        .await?;
+    let another_id = res.json_body()?["id"].as_i64().unwrap();
+    let res = author
+        .do_delete(&format!("/api/tickets/{another_id}/comments/{comment_id}"))
+        .await?;
+    assert_eq!(res.status().as_u16(), 404);
+
+    // 管理者可以刪除任何留言
+    let res = admin.do_delete(&comment_url).await?;
+    assert!(res.status().is_success());
+    let res = author.do_get(&comments_url).await?;
+    assert_eq!(res.json_body()?, json!([]));
+    Ok(())
+}
+
+// httpc-test 的 do_patch 實際上送出的是 POST，改用 reqwest，回傳 status 與 JSON body
+async fn send(
+    svc: &Service,
+    token: &str,
+    method: Method,
+    path: &str,
+    body: Value,
+) -> Result<(StatusCode, Value)> {
+    let res = reqwest::Client::new()
+        .request(method, svc.url(path))
+        .header("cookie", format!("auth-token={token}"))
+        .json(&body)
+        .send()
+        .await?;
+    Ok((res.status(), res.json().await?))
+}
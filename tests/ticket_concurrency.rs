@@ -0,0 +1,116 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// 同時送出多個狀態轉換，流程只允許從 Open 轉換到 InProgress 或 Closed
+// 只有一個轉換可以成功，其餘的都必須以轉換後的狀態重新檢查流程，不能出現 InProgress -> Closed 這類不允許的轉換
+// 使用 SQLite，讀取與寫入之間確實會讓出執行權，比較容易發生競爭
+#[tokio::test]
+async fn concurrent_transitions_follow_workflow() -> Result<()> {
+    let backend = Backend::sqlite();
+    let result = concurrent_transitions(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn concurrent_transitions(backend: &Backend) -> Result<()> {
+    let svc = Service::start_with(
+        backend,
+        &[(
+            "SERVICE_TICKET_WORKFLOW",
+            "Open->InProgress,Open->Closed".into(),
+        )],
+    )
+    .await?;
+    let hc = login!(svc);
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Concurrent ticket"}))
+        .await?;
+    let id = res.json_body()?["id"].as_i64().unwrap();
+    let auth_token = hc.cookie_value("auth-token").unwrap();
+
+    let client = reqwest::Client::new();
+    let requests: Vec<_> = (0..40)
+        .map(|i| {
+            let status = if i % 2 == 0 { "InProgress" } else { "Closed" };
+            tokio::spawn(
+                client
+                    .post(svc.url(&format!("/api/tickets/{id}/transitions")))
+                    .header("cookie", format!("auth-token={auth_token}"))
+                    .json(&json!({ "status": status }))
+                    .send(),
+            )
+        })
+        .collect();
+    for request in requests {
+        // 成功，或是不允許的轉換（以及多次衝突後放棄）
+        let status = request.await??.status().as_u16();
+        assert!(matches!(status, 200 | 409), "unexpected status {status}");
+    }
+
+    // 建立之後的異動紀錄中只有一次狀態的改變，而且是從 Open 開始
+    let res = hc.do_get(&format!("/api/tickets/{id}/history")).await?;
+    let status_changes: Vec<serde_json::Value> = res
+        .json_body()?
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|h| h["action"] == "Update")
+        .flat_map(|h| h["changes"].as_array().cloned().unwrap_or_default())
+        .filter(|c| c["field"] == "status")
+        .collect();
+    assert_eq!(status_changes.len(), 1, "{status_changes:?}");
+    assert_eq!(status_changes[0]["before"], "Open");
+    Ok(())
+}
+
+// 設定 SERVICE_TICKET_REQUIRE_IF_MATCH 時，沒有 If-Match 的修改回應 428；無法解析的 If-Match 一律回應 400
+#[tokio::test]
+async fn if_match_required_and_well_formed() -> Result<()> {
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[("SERVICE_TICKET_REQUIRE_IF_MATCH", "true".into())],
+    )
+    .await?;
+    let hc = login!(svc);
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "If-Match ticket"}))
+        .await?;
+    let id = res.json_body()?["id"].as_i64().unwrap();
+    let auth_token = hc.cookie_value("auth-token").unwrap();
+    let patch = |if_match: Option<&str>| {
+        let mut req = reqwest::Client::new()
+            .patch(svc.url(&format!("/api/tickets/{id}")))
+            .header("cookie", format!("auth-token={auth_token}"))
+            .json(&json!({"title": "If-Match ticket edited"}));
+        if let Some(if_match) = if_match {
+            req = req.header("if-match", if_match);
+        }
+        req.send()
+    };
+
+    let res = patch(None).await?;
+    assert_eq!(res.status().as_u16(), 428);
+    for malformed in ["1", "W/\"1\"", "\"one\"", "\"1\", \"2\""] {
+        let res = patch(Some(malformed)).await?;
+        assert_eq!(res.status().as_u16(), 400, "{malformed}");
+    }
+    // 以上的請求都沒有修改 ticket，版本仍然是 1
+    let res = patch(Some("\"1\"")).await?;
+    assert_eq!(res.status().as_u16(), 200);
+    assert_eq!(res.headers()["etag"], "\"2\"");
+    Ok(())
+}
+
+// 設定值無法解析時拒絕啟動，不會悄悄使用預設值（例如 "yes" 被當成 false 而關閉版本檢查）
+#[tokio::test]
+async fn unparsable_config_refused() -> Result<()> {
+    common::assert_start_refused(
+        &Backend::Memory,
+        &[("SERVICE_TICKET_REQUIRE_IF_MATCH", "yes".into())],
+    )
+    .await
+}
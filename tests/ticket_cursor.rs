@@ -0,0 +1,111 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+// 以 next_cursor 取得下一頁，中間有 ticket 新增或刪除時不會重複或遺漏
+// 被竄改、排序方式不同或同時指定 offset 的游標回傳 400，每一種儲存層都執行一次
+#[tokio::test]
+async fn cursor_memory() -> Result<()> {
+    cursor_paging(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn cursor_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn cursor_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn cursor_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = cursor_paging(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn cursor_paging(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    let mut ids = Vec::new();
+    for (title, priority) in [
+        ("Alpha", "High"),
+        ("Bravo", "Urgent"),
+        ("Charlie", "High"),
+        ("Delta", "Low"),
+    ] {
+        let res = hc
+            .do_post(
+                "/api/tickets",
+                json!({"title": title, "priority": priority}),
+            )
+            .await?;
+        ids.push(res.json_body()?["id"].as_i64().unwrap());
+    }
+    let titles = |page: &Value| -> Vec<String> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    const SORT: &str = "sort=priority&order=desc&limit=2";
+
+    // 第一頁
+    let res = hc.do_get(&format!("/api/tickets?{SORT}")).await?;
+    let page = res.json_body()?;
+    assert_eq!(titles(&page), ["Bravo", "Charlie"]);
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+
+    // 取得下一頁之前，第一頁的 Bravo 被刪除：以 offset 分頁時會跳過 Alpha，游標仍然從 Charlie 之後開始
+    hc.do_delete(&format!("/api/tickets/{}", ids[1])).await?;
+    let res = hc.do_get(&format!("/api/tickets?{SORT}&offset=2")).await?;
+    assert_eq!(titles(&res.json_body()?), ["Delta"]);
+    let res = hc
+        .do_get(&format!("/api/tickets?{SORT}&cursor={cursor}"))
+        .await?;
+    let page = res.json_body()?;
+    assert_eq!(titles(&page), ["Alpha", "Delta"]);
+    assert!(page["offset"].is_null());
+    assert!(page["next_cursor"].is_null());
+    assert!(page["next_offset"].is_null());
+
+    // 被竄改的游標
+    let res = hc
+        .do_get(&format!("/api/tickets?{SORT}&cursor=x{cursor}"))
+        .await?;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(res.json_body()?["error"]["type"], "INVALID_CURSOR");
+
+    // 游標只能搭配建立時的排序方式使用
+    let res = hc
+        .do_get(&format!(
+            "/api/tickets?sort=priority&order=asc&limit=2&cursor={cursor}"
+        ))
+        .await?;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(res.json_body()?["error"]["type"], "INVALID_CURSOR");
+
+    // 不能同時指定 offset
+    let res = hc
+        .do_get(&format!("/api/tickets?{SORT}&offset=1&cursor={cursor}"))
+        .await?;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(res.json_body()?["error"]["type"], "INVALID_PARAMS");
+    Ok(())
+}
@@ -0,0 +1,88 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+// ticket 的版本以 ETag 回傳，修改與刪除時帶上 If-Match，版本已經過期時回傳 412 VERSION_MISMATCH 且不做任何修改
+// 每一種儲存層都執行一次
+#[tokio::test]
+async fn etag_memory() -> Result<()> {
+    etag(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn etag_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn etag_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn etag_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = etag(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn etag(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    let token = hc.cookie_value("auth-token").unwrap();
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Versioned"}))
+        .await?;
+    let url = format!("/api/tickets/{}", res.json_body()?["id"]);
+    let send = |method: Method, if_match: &str, body: Value| {
+        reqwest::Client::new()
+            .request(method, svc.url(&url))
+            .header("cookie", format!("auth-token={token}"))
+            .header("if-match", if_match)
+            .json(&body)
+            .send()
+    };
+
+    // 新建立的 ticket 版本為 1
+    let res = hc.do_get(&url).await?;
+    assert_eq!(res.header("etag").as_deref(), Some("\"1\""));
+    assert_eq!(res.json_body()?["version"], 1);
+
+    // 帶上目前的版本修改，回傳新的版本
+    let res = send(Method::PATCH, "\"1\"", json!({"title": "Edited"})).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["etag"], "\"2\"");
+    let body: Value = res.json().await?;
+    assert_eq!(body["version"], 2);
+
+    // 另一個人以過期的版本修改，不會覆蓋前一次的修改
+    let res = send(Method::PATCH, "\"1\"", json!({"title": "Overwrite"})).await?;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    let body: Value = res.json().await?;
+    assert_eq!(body["error"]["type"], "VERSION_MISMATCH");
+    let res = hc.do_get(&url).await?;
+    assert_eq!(res.header("etag").as_deref(), Some("\"2\""));
+    assert_eq!(res.json_body()?["title"], "Edited");
+
+    // 刪除同樣檢查版本
+    let res = send(Method::DELETE, "\"1\"", json!({})).await?;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    assert!(hc.do_get(&url).await?.status().is_success());
+    let res = send(Method::DELETE, "\"2\"", json!({})).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hc.do_get(&url).await?.status().as_u16(), 404);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+// 說明、狀態、優先度與 labels 會保存在每一種儲存層，出現在 JSON 中，也可以用來篩選列表
+#[tokio::test]
+async fn fields_memory() -> Result<()> {
+    ticket_fields(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn fields_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn fields_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn fields_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = ticket_fields(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn ticket_fields(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+
+    // 沒有提供的欄位使用預設值
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Defaults"}))
+        .await?;
+    let body = res.json_body()?;
+    assert_eq!(body["description"], "");
+    assert_eq!(body["status"], "Open");
+    assert_eq!(body["priority"], "Normal");
+    assert_eq!(body["labels"], json!([]));
+
+    // labels 是集合，重複的會合併並依字母排序
+    let res = hc
+        .do_post(
+            "/api/tickets",
+            json!({"title": "Billing", "description": "desc", "priority": "High", "labels": ["b", "a", "b"]}),
+        )
+        .await?;
+    let body = res.json_body()?;
+    let url = format!("/api/tickets/{}", body["id"]);
+    assert_eq!(body["description"], "desc");
+    assert_eq!(body["priority"], "High");
+    assert_eq!(body["labels"], json!(["a", "b"]));
+
+    // 不合法的優先度不會建立 ticket
+    let res = hc
+        .do_post(
+            "/api/tickets",
+            json!({"title": "Bad", "priority": "Critical"}),
+        )
+        .await?;
+    assert!(res.status().is_client_error());
+
+    // 修改狀態與 labels
+    let token = hc.cookie_value("auth-token").unwrap();
+    let body: Value = reqwest::Client::new()
+        .request(Method::PATCH, svc.url(&url))
+        .header("cookie", format!("auth-token={token}"))
+        .json(&json!({"status": "InProgress", "labels": ["billing"]}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["status"], "InProgress");
+    assert_eq!(body["labels"], json!(["billing"]));
+    assert_eq!(body["priority"], "High");
+
+    // 各欄位都可以篩選，多個條件同時成立
+    let titles = |page: Value| -> Vec<String> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    for (query, expected) in [
+        ("status=InProgress", vec!["Billing"]),
+        ("status=Open", vec!["Defaults"]),
+        ("priority=High&label=billing", vec!["Billing"]),
+        ("priority=Normal&label=billing", vec![]),
+        ("label=a", vec![]),
+    ] {
+        let res = hc.do_get(&format!("/api/tickets?{query}")).await?;
+        assert_eq!(titles(res.json_body()?), expected, "{query}");
+    }
+
+    // 重新啟動後欄位仍然存在
+    if backend.is_persistent() {
+        svc.stop().await?;
+        let svc = Service::start(backend).await?;
+        let hc = login!(svc);
+        let body = hc.do_get(&url).await?.json_body()?;
+        assert_eq!(body["description"], "desc");
+        assert_eq!(body["status"], "InProgress");
+        assert_eq!(body["priority"], "High");
+        assert_eq!(body["labels"], json!(["billing"]));
+    }
+    Ok(())
+}
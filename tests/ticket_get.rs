@@ -0,0 +1,71 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// GET /api/tickets/:id 回傳單一 ticket，不存在或不在列表中（垃圾桶）的 ticket 回傳 404 ENTITY_NOT_FOUND
+// 每一種儲存層都執行一次
+#[tokio::test]
+async fn get_memory() -> Result<()> {
+    get_ticket(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn get_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn get_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn get_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = get_ticket(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn get_ticket(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    let mut ids = Vec::new();
+    for title in ["First", "Second"] {
+        let res = hc.do_post("/api/tickets", json!({"title": title})).await?;
+        ids.push(res.json_body()?["id"].as_i64().unwrap());
+    }
+
+    // 只回傳指定的 ticket，內容與建立時的回應相同
+    let res = hc.do_get(&format!("/api/tickets/{}", ids[1])).await?;
+    assert_eq!(res.status().as_u16(), 200);
+    let ticket = res.json_body()?;
+    assert_eq!(ticket["id"], ids[1]);
+    assert_eq!(ticket["title"], "Second");
+    assert_eq!(ticket["cid"], 1);
+
+    // 不存在的 ticket
+    let res = hc
+        .do_get(&format!("/api/tickets/{}", ids[1] + 1000))
+        .await?;
+    assert_eq!(res.status().as_u16(), 404);
+    assert_eq!(res.json_body()?["error"]["type"], "ENTITY_NOT_FOUND");
+
+    // 移到垃圾桶的 ticket 同樣視為不存在
+    hc.do_delete(&format!("/api/tickets/{}", ids[0])).await?;
+    let res = hc.do_get(&format!("/api/tickets/{}", ids[0])).await?;
+    assert_eq!(res.status().as_u16(), 404);
+    assert_eq!(res.json_body()?["error"]["type"], "ENTITY_NOT_FOUND");
+    Ok(())
+}
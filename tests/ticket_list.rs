@@ -0,0 +1,126 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::{json, Value};
+
+mod common;
+use common::{create_member, Backend, Service};
+
+// 列表的篩選、排序與 offset 分頁在每一種儲存層的結果都相同，回傳符合條件的總數與下一頁的 offset
+#[tokio::test]
+async fn list_memory() -> Result<()> {
+    list_tickets(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn list_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn list_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn list_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = list_tickets(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn list_tickets(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let admin = login!(svc);
+    let member_id = create_member(&svc, "demo2", "welcome2").await?;
+    let member = login!(svc, "demo2", "welcome2");
+
+    let mut created_at = 0;
+    for (hc, title, priority, labels) in [
+        (&admin, "Alpha", "High", json!(["billing"])),
+        (&admin, "Bravo", "Urgent", json!([])),
+        (&member, "Charlie", "Low", json!(["billing"])),
+        (&member, "Delta", "High", json!([])),
+    ] {
+        let res = hc
+            .do_post(
+                "/api/tickets",
+                json!({"title": title, "priority": priority, "labels": labels}),
+            )
+            .await?;
+        created_at = res.json_body()?["created_at"].as_u64().unwrap();
+    }
+    let page = |query: &str| {
+        let admin = &admin;
+        let query = query.to_string();
+        async move {
+            let res = admin.do_get(&format!("/api/tickets?{query}")).await?;
+            anyhow::ensure!(res.status().is_success(), "{query}: {}", res.status());
+            Ok(res.json_body()?)
+        }
+    };
+    let titles = |page: &Value| -> Vec<String> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // 沒有條件時依 id 由小到大列出全部
+    let all = page("").await?;
+    assert_eq!(titles(&all), ["Alpha", "Bravo", "Charlie", "Delta"]);
+    assert_eq!(all["total"], 4);
+    assert!(all["next_offset"].is_null());
+
+    // 篩選建立者、labels 與建立時間的範圍（兩端都包含在內）
+    let by_member = page(&format!("creator={member_id}")).await?;
+    assert_eq!(titles(&by_member), ["Charlie", "Delta"]);
+    let billing = page(&format!("creator={member_id}&label=billing")).await?;
+    assert_eq!(titles(&billing), ["Charlie"]);
+    assert_eq!(page("creator=999").await?["total"], 0);
+    let range = format!("created_from={created_at}&created_to={created_at}");
+    let created = page(&range).await?;
+    assert!(created["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|t| t["created_at"] == created_at));
+    let before = page(&format!("created_to={}", created_at - 1000)).await?;
+    assert_eq!(before["total"], 0);
+
+    // 依優先度由高到低排序，相同時依 id（方向相同）
+    let sorted = page("sort=priority&order=desc").await?;
+    assert_eq!(titles(&sorted), ["Bravo", "Delta", "Alpha", "Charlie"]);
+    let sorted = page("sort=title&order=desc").await?;
+    assert_eq!(titles(&sorted), ["Delta", "Charlie", "Bravo", "Alpha"]);
+
+    // offset 分頁，total 是符合條件的總數而不是這一頁的筆數
+    let first = page("sort=priority&order=desc&limit=3").await?;
+    assert_eq!(titles(&first), ["Bravo", "Delta", "Alpha"]);
+    assert_eq!(first["total"], 4);
+    assert_eq!(first["limit"], 3);
+    assert_eq!(first["next_offset"], 3);
+    let last = page("sort=priority&order=desc&limit=3&offset=3").await?;
+    assert_eq!(titles(&last), ["Charlie"]);
+    assert_eq!(last["total"], 4);
+    assert!(last["next_offset"].is_null());
+    let filtered = page("priority=High&limit=1").await?;
+    assert_eq!(titles(&filtered), ["Alpha"]);
+    assert_eq!(filtered["total"], 2);
+    assert_eq!(filtered["next_offset"], 1);
+
+    // 無法解析的參數回傳 400
+    let res = admin.do_get("/api/tickets?sort=unknown").await?;
+    assert_eq!(res.status().as_u16(), 400);
+    Ok(())
+}
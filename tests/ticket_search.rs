@@ -0,0 +1,137 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+// 全文檢索涵蓋標題、描述與留言，中文以 bigram 比對，符合的詞以 <mark> 標示
+// 垃圾桶中的 ticket 不會出現在結果中，持久化的儲存層重新啟動後從資料重建索引，每一種儲存層都執行一次
+#[tokio::test]
+async fn search_memory() -> Result<()> {
+    search(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn search_journal() -> Result<()> {
+    run(Backend::journal()).await
+}
+
+#[tokio::test]
+async fn search_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn search_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn search_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = search(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn search(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let hc = login!(svc);
+    let token = hc.cookie_value("auth-token").unwrap();
+    let res = hc
+        .do_post(
+            "/api/tickets",
+            json!({"title": "登入失敗", "description": "輸入密碼後無法登入 <b>"}),
+        )
+        .await?;
+    let login_id = res.json_body()?["id"].as_i64().unwrap();
+    let res = hc
+        .do_post(
+            "/api/tickets",
+            json!({"title": "Billing & invoices", "description": "密碼錯誤"}),
+        )
+        .await?;
+    let billing_id = res.json_body()?["id"].as_i64().unwrap();
+    hc.do_post(
+        &format!("/api/tickets/{login_id}/comments"),
+        json!({"body": "Login cookie expired"}),
+    )
+    .await?;
+
+    // 中文的查詢，標題與描述都有符合時，各欄位都回傳摘要，內容中的 HTML 經過跳脫
+    let page = get(&svc, &token, "登入").await?;
+    assert_eq!(page["total"], 1);
+    let hit = &page["items"][0];
+    assert_eq!(hit["ticket"]["id"], login_id);
+    assert_eq!(hit["highlights"][0]["field"], "title");
+    assert_eq!(hit["highlights"][0]["snippet"], "<mark>登入</mark>失敗");
+    assert_eq!(hit["highlights"][1]["field"], "description");
+    assert_eq!(
+        hit["highlights"][1]["snippet"],
+        "輸入密碼後無法<mark>登入</mark> &lt;b&gt;"
+    );
+
+    // 所有的詞都要出現，不分大小寫，留言符合時回傳留言的 id
+    let page = get(&svc, &token, "COOKIE login").await?;
+    assert_eq!(page["total"], 1);
+    let highlight = &page["items"][0]["highlights"][0];
+    assert_eq!(highlight["field"], "comment");
+    assert!(highlight["comment_id"].is_u64());
+    assert_eq!(get(&svc, &token, "cookie billing").await?["total"], 0);
+
+    // 兩個 ticket 都有的詞，單一個字的查詢也找得到
+    assert_eq!(get(&svc, &token, "密碼").await?["total"], 2);
+    assert_eq!(get(&svc, &token, "密").await?["total"], 2);
+    let page = get(&svc, &token, "invoices").await?;
+    assert_eq!(page["items"][0]["ticket"]["id"], billing_id);
+    assert_eq!(
+        page["items"][0]["highlights"][0]["snippet"],
+        "Billing &amp; <mark>invoices</mark>"
+    );
+
+    // 空白的查詢回傳 400
+    let res = hc.do_get("/api/tickets/search?q=%20").await?;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // 移到垃圾桶後不會出現在結果中，還原後再次出現（包含留言）
+    hc.do_delete(&format!("/api/tickets/{login_id}")).await?;
+    assert_eq!(get(&svc, &token, "登入").await?["total"], 0);
+    assert_eq!(get(&svc, &token, "cookie").await?["total"], 0);
+    hc.do_post(&format!("/api/tickets/trash/{login_id}/restore"), json!({}))
+        .await?;
+    assert_eq!(get(&svc, &token, "登入").await?["total"], 1);
+    assert_eq!(get(&svc, &token, "cookie").await?["total"], 1);
+
+    // 重新啟動後從儲存層重建索引，留言也一併收錄
+    if backend.is_persistent() {
+        svc.stop().await?;
+        let svc = Service::start(backend).await?;
+        let token = login!(svc).cookie_value("auth-token").unwrap();
+        assert_eq!(get(&svc, &token, "登入").await?["total"], 1);
+        assert_eq!(get(&svc, &token, "密碼").await?["total"], 2);
+        let page = get(&svc, &token, "cookie").await?;
+        assert_eq!(page["items"][0]["ticket"]["id"], login_id);
+    }
+    Ok(())
+}
+
+// httpc-test 沒有公開 Client 的型別，改用 reqwest 帶上 cookie
+async fn get(svc: &Service, token: &str, q: &str) -> Result<Value> {
+    let res = reqwest::Client::new()
+        .get(svc.url("/api/tickets/search"))
+        .query(&[("q", q)])
+        .header("cookie", format!("auth-token={token}"))
+        .send()
+        .await?;
+    anyhow::ensure!(res.status().is_success(), "{q}: {}", res.status());
+    Ok(res.json().await?)
+}
@@ -0,0 +1,288 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// 刪除、還原與查看垃圾桶需要與修改 ticket 相同的權限，垃圾桶中的 ticket 不能下載附件，也不能修改或刪除留言
+// 垃圾桶中 ticket 的異動紀錄同樣需要修改的權限，永久刪除之後只有管理者可以查詢
+#[tokio::test]
+async fn trash_permissions() -> Result<()> {
+    let svc = Service::start(&Backend::Memory).await?;
+    let admin = login!(svc);
+
+    // 以邀請建立一個一般成員
+    let res = admin
+        .do_post(
+            "/api/invitations",
+            json!({"email": "demo2@example.com", "role": "Member"}),
+        )
+        .await?;
+    let token = res.json_body()?["token"].as_str().unwrap().to_string();
+    let res = httpc_test::new_client(svc.url(""))?
+        .do_post(
+            "/api/invitations/accept",
+            json!({"token": token, "username": "demo2", "pwd": "welcome2"}),
+        )
+        .await?;
+    assert!(res.status().is_success());
+    let member = login!(svc, "demo2", "welcome2");
+
+    // 管理者的 ticket 附上一個附件
+    let res = admin
+        .do_post("/api/tickets", json!({"title": "Admin ticket"}))
+        .await?;
+    let admin_id = res.json_body()?["id"].as_i64().unwrap();
+    let file = reqwest::multipart::Part::bytes(b"log line".to_vec())
+        .file_name("app.log")
+        .mime_str("text/plain")?;
+    let admin_token = admin.cookie_value("auth-token").unwrap();
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(svc.url(&format!("/api/tickets/{admin_id}/attachments")))
+        .header("cookie", format!("auth-token={admin_token}"))
+        .multipart(reqwest::multipart::Form::new().part("file", file))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let attachment_id = body["id"].as_i64().unwrap();
+    let res = admin
+        .do_post(
+            &format!("/api/tickets/{admin_id}/comments"),
+            json!({"body": "first comment"}),
+        )
+        .await?;
+    let comment_id = res.json_body()?["id"].as_i64().unwrap();
+
+    // 讀取不限制可見範圍：一般成員可以查詢、列出與檢索別人的 ticket，未登入則不行
+    let res = member.do_get(&format!("/api/tickets/{admin_id}")).await?;
+    assert!(res.status().is_success());
+    let res = member.do_get("/api/tickets").await?;
+    assert_eq!(res.json_body()?["items"][0]["id"], admin_id);
+    let res = member.do_get("/api/tickets/search?q=admin").await?;
+    assert_eq!(res.json_body()?["total"], 1);
+    let res = httpc_test::new_client(svc.url(""))?
+        .do_get("/api/tickets")
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // 一般成員不能刪除別人的 ticket
+    let res = member
+        .do_delete(&format!("/api/tickets/{admin_id}"))
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // 管理者刪除後，附件無法再下載
+    let res = admin.do_delete(&format!("/api/tickets/{admin_id}")).await?;
+    assert!(res.status().is_success());
+    let res = reqwest::Client::new()
+        .get(svc.url(&format!(
+            "/api/tickets/{admin_id}/attachments/{attachment_id}"
+        )))
+        .header("cookie", format!("auth-token={admin_token}"))
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 404);
+    let comment_url = format!("/api/tickets/{admin_id}/comments/{comment_id}");
+    // httpc-test 的 do_patch 實際上送出的是 POST，改用 reqwest
+    let res = reqwest::Client::new()
+        .patch(svc.url(&comment_url))
+        .header("cookie", format!("auth-token={admin_token}"))
+        .json(&json!({"body": "edited"}))
+        .send()
+        .await?;
+    assert_eq!(res.status().as_u16(), 404);
+    let res = admin.do_delete(&comment_url).await?;
+    assert_eq!(res.status().as_u16(), 404);
+
+    // 一般成員看不到也不能還原別人的 ticket
+    let res = member.do_get("/api/tickets/trash").await?;
+    assert_eq!(res.json_body()?, json!([]));
+    let res = member
+        .do_post(&format!("/api/tickets/trash/{admin_id}/restore"), json!({}))
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = member
+        .do_get(&format!("/api/tickets/{admin_id}/history"))
+        .await?;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // 自己建立的 ticket 可以刪除、在垃圾桶中看到並還原
+    let res = member
+        .do_post("/api/tickets", json!({"title": "Member ticket"}))
+        .await?;
+    let member_id = res.json_body()?["id"].as_i64().unwrap();
+    let res = member
+        .do_delete(&format!("/api/tickets/{member_id}"))
+        .await?;
+    assert!(res.status().is_success());
+    let res = member.do_get("/api/tickets/trash").await?;
+    let trash = res.json_body()?;
+    assert_eq!(trash.as_array().unwrap().len(), 1);
+    assert_eq!(trash[0]["id"], member_id);
+    let res = member
+        .do_get(&format!("/api/tickets/{member_id}/history"))
+        .await?;
+    assert!(res.status().is_success());
+    let res = member
+        .do_post(
+            &format!("/api/tickets/trash/{member_id}/restore"),
+            json!({}),
+        )
+        .await?;
+    assert!(res.status().is_success());
+
+    // 管理者可以看到並還原所有的 ticket
+    let res = admin.do_get("/api/tickets/trash").await?;
+    assert_eq!(res.json_body()?[0]["id"], admin_id);
+    let res = admin
+        .do_post(&format!("/api/tickets/trash/{admin_id}/restore"), json!({}))
+        .await?;
+    assert!(res.status().is_success());
+
+    // 永久刪除之後，建立者也不能再查詢異動紀錄，管理者仍然可以
+    member
+        .do_delete(&format!("/api/tickets/{member_id}"))
+        .await?;
+    let res = admin
+        .do_delete(&format!("/api/tickets/trash/{member_id}"))
+        .await?;
+    assert!(res.status().is_success());
+    let history_url = format!("/api/tickets/{member_id}/history");
+    let res = member.do_get(&history_url).await?;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = admin.do_get(&history_url).await?;
+    let history = res.json_body()?;
+    assert_eq!(
+        history.as_array().unwrap().last().unwrap()["action"],
+        "Purge"
+    );
+    Ok(())
+}
+
+// 背景工作定期永久刪除在垃圾桶中超過保留期限的 ticket，底下的留言、附件資訊與附件內容一併刪除
+// 還在保留期限內的 ticket 不受影響，每一種儲存層都執行一次
+#[tokio::test]
+async fn retention_memory() -> Result<()> {
+    retention_purge(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn retention_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn retention_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn retention_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = retention_purge(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+const RETENTION_SECS: u64 = 3;
+
+async fn retention_purge(backend: &Backend) -> Result<()> {
+    let blob_dir = common::temp_path("blobs");
+    let svc = Service::start_with(
+        backend,
+        &[
+            ("SERVICE_TRASH_RETENTION_SECS", RETENTION_SECS.to_string()),
+            ("SERVICE_TRASH_PURGE_INTERVAL_SECS", "1".to_string()),
+            ("SERVICE_BLOB_STORE", "local".to_string()),
+            ("SERVICE_BLOB_DIR", blob_dir.display().to_string()),
+        ],
+    )
+    .await?;
+    let hc = login!(svc);
+    let token = hc.cookie_value("auth-token").unwrap();
+
+    // 兩個 ticket 都有留言與附件
+    let mut ids = Vec::new();
+    for title in ["Expired", "Fresh"] {
+        let res = hc.do_post("/api/tickets", json!({"title": title})).await?;
+        let id = res.json_body()?["id"].as_i64().unwrap();
+        hc.do_post(
+            &format!("/api/tickets/{id}/comments"),
+            json!({"body": "a comment"}),
+        )
+        .await?;
+        let file = reqwest::multipart::Part::bytes(b"log line".to_vec())
+            .file_name("app.log")
+            .mime_str("text/plain")?;
+        let res = reqwest::Client::new()
+            .post(svc.url(&format!("/api/tickets/{id}/attachments")))
+            .header("cookie", format!("auth-token={token}"))
+            .multipart(reqwest::multipart::Form::new().part("file", file))
+            .send()
+            .await?;
+        assert!(res.status().is_success());
+        ids.push(id);
+    }
+    let (expired_id, fresh_id) = (ids[0], ids[1]);
+    let blob_path = |id: i64| blob_dir.join(format!("tickets/{id}"));
+    assert!(blob_path(expired_id).exists());
+
+    // 等到過期的 ticket 被自動永久刪除，異動紀錄中沒有執行者
+    hc.do_delete(&format!("/api/tickets/{expired_id}")).await?;
+    let history_url = format!("/api/tickets/{expired_id}/history");
+    let mut purge = None;
+    for _ in 0..(RETENTION_SECS * 2 + 5) * 2 {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        let history = hc.do_get(&history_url).await?.json_body()?;
+        let last = history.as_array().unwrap().last().cloned();
+        if last.as_ref().is_some_and(|h| h["action"] == "Purge") {
+            purge = last;
+            break;
+        }
+    }
+    let purge = purge.expect("expired ticket was not purged");
+    assert!(purge["actor_id"].is_null());
+
+    // 留言、附件資訊與附件內容都已經刪除
+    let res = hc.do_get("/api/tickets/trash").await?;
+    assert_eq!(res.json_body()?, json!([]));
+    for sub in ["comments", "attachments"] {
+        let res = hc
+            .do_get(&format!("/api/tickets/{expired_id}/{sub}"))
+            .await?;
+        assert_eq!(res.status().as_u16(), 404);
+    }
+    assert!(!blob_path(expired_id).exists());
+
+    // 剛移到垃圾桶的 ticket 在保留期限內，經過幾次背景工作之後仍然可以還原
+    hc.do_delete(&format!("/api/tickets/{fresh_id}")).await?;
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let res = hc.do_get("/api/tickets/trash").await?;
+    assert_eq!(res.json_body()?[0]["id"], fresh_id);
+    assert!(blob_path(fresh_id).exists());
+    let res = hc
+        .do_post(&format!("/api/tickets/trash/{fresh_id}/restore"), json!({}))
+        .await?;
+    assert!(res.status().is_success());
+    let res = hc
+        .do_get(&format!("/api/tickets/{fresh_id}/comments"))
+        .await?;
+    assert_eq!(res.json_body()?.as_array().unwrap().len(), 1);
+    let res = hc
+        .do_get(&format!("/api/tickets/{fresh_id}/attachments"))
+        .await?;
+    assert_eq!(res.json_body()?.as_array().unwrap().len(), 1);
+
+    std::fs::remove_dir_all(&blob_dir)?;
+    Ok(())
+}
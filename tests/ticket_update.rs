@@ -0,0 +1,106 @@
+#![allow(unused)]
+use anyhow::Result;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+mod common;
+use common::{create_member, Backend, Service};
+
+// PATCH /api/tickets/:id 只修改有提供的欄位，並記錄修改的時間與修改者
+// 建立者與管理者可以修改，其他一般成員回傳 403 NO_PERMISSION，每一種儲存層都執行一次
+#[tokio::test]
+async fn update_memory() -> Result<()> {
+    update_ticket(&Backend::Memory).await
+}
+
+#[tokio::test]
+async fn update_sqlite() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn update_kv() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn update_postgres() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = update_ticket(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn update_ticket(backend: &Backend) -> Result<()> {
+    let svc = Service::start(backend).await?;
+    let admin = login!(svc);
+    let author_id = create_member(&svc, "demo2", "welcome2").await?;
+    create_member(&svc, "demo3", "welcome3").await?;
+    let author = login!(svc, "demo2", "welcome2");
+    let other = login!(svc, "demo3", "welcome3");
+    let admin_token = admin.cookie_value("auth-token").unwrap();
+    let author_token = author.cookie_value("auth-token").unwrap();
+    let other_token = other.cookie_value("auth-token").unwrap();
+
+    let res = author
+        .do_post(
+            "/api/tickets",
+            json!({"title": "Original", "description": "keep me"}),
+        )
+        .await?;
+    let ticket = res.json_body()?;
+    let url = format!("/api/tickets/{}", ticket["id"]);
+    assert!(ticket["updated_at"].is_null());
+    assert!(ticket["updated_by"].is_null());
+
+    // 建立者修改標題，沒有提供的欄位保持不變
+    let (status, body) = send(&svc, &author_token, &url, json!({"title": "Edited"})).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["title"], "Edited");
+    assert_eq!(body["description"], "keep me");
+    assert_eq!(body["updated_by"], author_id);
+    assert!(body["updated_at"].is_u64());
+    let res = other.do_get(&url).await?;
+    assert_eq!(res.json_body()?["title"], "Edited");
+
+    // 其他一般成員不能修改
+    let (status, body) = send(&svc, &other_token, &url, json!({"title": "Hijacked"})).await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"]["type"], "NO_PERMISSION");
+
+    // 管理者可以修改任何 ticket，修改者改為管理者
+    let (status, body) = send(&svc, &admin_token, &url, json!({"description": "by admin"})).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["title"], "Edited");
+    assert_eq!(body["updated_by"], 1);
+
+    // 不存在的 ticket
+    let (status, _) = send(
+        &svc,
+        &admin_token,
+        &format!("/api/tickets/{}", ticket["id"].as_u64().unwrap() + 1000),
+        json!({"title": "Missing"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+// httpc-test 的 do_patch 實際上送出的是 POST，改用 reqwest，回傳 status 與 JSON body
+async fn send(svc: &Service, token: &str, path: &str, body: Value) -> Result<(StatusCode, Value)> {
+    let res = reqwest::Client::new()
+        .request(Method::PATCH, svc.url(path))
+        .header("cookie", format!("auth-token={token}"))
+        .json(&body)
+        .send()
+        .await?;
+    Ok((res.status(), res.json().await?))
+}
@@ -0,0 +1,55 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::json;
+
+mod common;
+use common::{Backend, Service};
+
+// 不允許的狀態轉換回傳 409，錯誤的 detail 中列出目前的狀態、要求的狀態與允許的狀態
+// client 不需要再查詢一次 /transitions 就可以提示使用者
+#[tokio::test]
+async fn transition_not_allowed_detail() -> Result<()> {
+    let svc = Service::start_with(
+        &Backend::Memory,
+        &[(
+            "SERVICE_TICKET_WORKFLOW",
+            "Open->InProgress,Open->Closed,InProgress->Resolved".into(),
+        )],
+    )
+    .await?;
+    let hc = login!(svc);
+    let res = hc
+        .do_post("/api/tickets", json!({"title": "Workflow ticket"}))
+        .await?;
+    let id = res.json_body()?["id"].as_i64().unwrap();
+    let transitions_url = format!("/api/tickets/{id}/transitions");
+
+    let res = hc
+        .do_post(&transitions_url, json!({"status": "Resolved"}))
+        .await?;
+    assert_eq!(res.status().as_u16(), 409);
+    let error = &res.json_body()?["error"];
+    assert_eq!(error["type"], "INVALID_STATUS_TRANSITION");
+    assert_eq!(
+        error["detail"],
+        json!({"from": "Open", "to": "Resolved", "allowed": ["InProgress", "Closed"]})
+    );
+
+    // 允許的轉換成功之後，detail 依照新的狀態列出
+    let res = hc
+        .do_post(&transitions_url, json!({"status": "InProgress"}))
+        .await?;
+    assert!(res.status().is_success());
+    let res = hc
+        .do_post(&transitions_url, json!({"status": "Closed"}))
+        .await?;
+    assert_eq!(
+        res.json_body()?["error"]["detail"],
+        json!({"from": "InProgress", "to": "Closed", "allowed": ["Resolved"]})
+    );
+
+    // 其他錯誤沒有 detail
+    let res = hc.do_get(&format!("/api/tickets/{}", id + 1000)).await?;
+    assert!(res.json_body()?["error"].get("detail").is_none());
+    Ok(())
+}
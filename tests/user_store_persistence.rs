@@ -0,0 +1,192 @@
+#![allow(unused)]
+use anyhow::Result;
+use serde_json::{json, Value};
+
+mod common;
+use common::{Backend, Service};
+
+const SCIM_TOKEN: &str = "scim-test-token";
+
+// 使用者、群組與邀請預設與 ticket 使用相同的儲存層，重新啟動後 ticket 與 cookie 中的 user id 仍然對應到同一個人
+
+#[tokio::test]
+async fn journal_user_store() -> Result<()> {
+    run(Backend::journal()).await
+}
+
+#[tokio::test]
+async fn sqlite_user_store() -> Result<()> {
+    run(Backend::sqlite()).await
+}
+
+#[tokio::test]
+async fn kv_user_store() -> Result<()> {
+    run(Backend::kv()).await
+}
+
+// PostgreSQL 連不上時直接略過，詳見 Backend::postgres
+#[tokio::test]
+async fn postgres_user_store() -> Result<()> {
+    match Backend::postgres().await? {
+        Some(backend) => run(backend).await,
+        None => Ok(()),
+    }
+}
+
+// ticket 會保存下來，但使用者只存在記憶體中的組合無法啟動
+#[tokio::test]
+async fn memory_user_store_with_persistent_tickets_refused() -> Result<()> {
+    let backend = Backend::sqlite();
+    let result =
+        common::assert_start_refused(&backend, &[("SERVICE_USER_STORE", "memory".into())]).await;
+    // 啟動失敗的時間點不一定已經建立資料檔
+    let _ = backend.cleanup().await;
+    result
+}
+
+// 持久化的儲存層沒有明確設定 SERVICE_SEED_DEMO_USER 時，不建立帳密公開的 demo1，只存在記憶體中時才建立
+#[tokio::test]
+async fn demo_user_not_seeded_by_default() -> Result<()> {
+    let backend = Backend::sqlite();
+    let result = demo_user_not_seeded(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn demo_user_not_seeded(backend: &Backend) -> Result<()> {
+    let login = |svc: &Service| {
+        reqwest::Client::new()
+            .post(svc.url("/api/login"))
+            .json(&json!({"username": "demo1", "pwd": "welcome"}))
+            .send()
+    };
+    let svc = Service::start_unseeded(backend).await?;
+    assert_eq!(login(&svc).await?.status().as_u16(), 403);
+    let svc = Service::start_unseeded(&Backend::Memory).await?;
+    assert_eq!(login(&svc).await?.status().as_u16(), 200);
+    Ok(())
+}
+
+// 不論測試是否成功都刪除測試用的資料
+async fn run(backend: Backend) -> Result<()> {
+    let result = user_store_persistence(&backend).await;
+    backend.cleanup().await?;
+    result
+}
+
+async fn user_store_persistence(backend: &Backend) -> Result<()> {
+    let envs = [("SERVICE_SCIM_TOKEN", SCIM_TOKEN.to_string())];
+    let svc = Service::start_with(backend, &envs).await?;
+    let admin = login!(svc);
+
+    // 以邀請建立 demo2，並由 demo2 建立一個 ticket，ticket 的 cid 即為 demo2 的 user id
+    let res = admin
+        .do_post(
+            "/api/invitations",
+            json!({"email": "demo2@example.com", "role": "Member"}),
+        )
+        .await?;
+    let token = res.json_body()?["token"].as_str().unwrap().to_string();
+    let res = accept(&svc, &token, "demo2").await?;
+    assert!(res.status().is_success());
+    let member = login!(svc, "demo2", "welcome2");
+    let member_cookie = member.cookie_value("auth-token").unwrap();
+    let res = member
+        .do_post("/api/tickets", json!({"title": "Member ticket"}))
+        .await?;
+    let ticket = res.json_body()?;
+    let ticket_url = format!("/api/tickets/{}", ticket["id"]);
+    let member_id = ticket["cid"].as_u64().unwrap();
+
+    // 另外留下一個還沒被接受的邀請，以及一個包含 demo2 的群組
+    let res = admin
+        .do_post(
+            "/api/invitations",
+            json!({"email": "demo3@example.com", "role": "Member"}),
+        )
+        .await?;
+    let pending_token = res.json_body()?["token"].as_str().unwrap().to_string();
+    let group: Value = reqwest::Client::new()
+        .post(svc.url("/scim/v2/Groups"))
+        .bearer_auth(SCIM_TOKEN)
+        .json(&json!({"displayName": "Support", "members": [{"value": member_id.to_string()}]}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let group_url = format!("/scim/v2/Groups/{}", group["id"].as_str().unwrap());
+
+    svc.stop().await?;
+    let svc = Service::start_with(backend, &envs).await?;
+
+    // 重新啟動前拿到的 cookie 仍然可以使用，demo2 也仍然可以登入
+    let res = reqwest::Client::new()
+        .get(svc.url(&ticket_url))
+        .header("cookie", format!("auth-token={member_cookie}"))
+        .send()
+        .await?;
+    assert!(res.status().is_success());
+    login!(svc, "demo2", "welcome2");
+
+    // 群組與成員仍然存在
+    let group: Value = reqwest::Client::new()
+        .get(svc.url(&group_url))
+        .bearer_auth(SCIM_TOKEN)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(group["displayName"], "Support");
+    assert_eq!(group["members"][0]["display"], "demo2");
+
+    // 邀請的狀態與 nonce 都保存下來：已接受的不能再使用，還沒接受的仍然可以使用
+    let admin = login!(svc);
+    let res = admin.do_get("/api/invitations").await?;
+    let invitations = res.json_body()?;
+    assert_eq!(invitations[0]["status"], "Accepted");
+    assert_eq!(invitations[0]["accepted_by"], member_id);
+    assert_eq!(invitations[1]["status"], "Pending");
+    let res = accept(&svc, &token, "demo2b").await?;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = accept(&svc, &pending_token, "demo3").await?;
+    assert!(res.status().is_success());
+
+    // 新的帳號不會拿到 demo2 的 id，也就不會接手 demo2 的 ticket
+    let other = login!(svc, "demo3", "welcome2");
+    let res = other
+        .do_post("/api/tickets", json!({"title": "Other ticket"}))
+        .await?;
+    assert_ne!(res.json_body()?["cid"], member_id);
+
+    // 登入以 username 索引查詢：改名之後以新的 username 登入，舊的 username 不能再登入，也不能改成已被使用的 username
+    let rename = |username: &str| {
+        reqwest::Client::new()
+            .patch(svc.url(&format!("/scim/v2/Users/{member_id}")))
+            .bearer_auth(SCIM_TOKEN)
+            .json(
+                &json!({"Operations": [{"op": "replace", "path": "userName", "value": username}]}),
+            )
+            .send()
+    };
+    assert!(rename("demo2-renamed").await?.status().is_success());
+    login!(svc, "demo2-renamed", "welcome2");
+    let hc = httpc_test::new_client(svc.url(""))?;
+    let res = hc
+        .do_post(
+            "/api/login",
+            json!({"username": "demo2", "pwd": "welcome2"}),
+        )
+        .await?;
+    assert_eq!(res.status(), 403);
+    assert_eq!(rename("demo3").await?.status().as_u16(), 409);
+    login!(svc, "demo3", "welcome2");
+    Ok(())
+}
+
+async fn accept(svc: &Service, token: &str, username: &str) -> Result<reqwest::Response> {
+    Ok(reqwest::Client::new()
+        .post(svc.url("/api/invitations/accept"))
+        .json(&json!({"token": token, "username": username, "pwd": "welcome2"}))
+        .send()
+        .await?)
+}